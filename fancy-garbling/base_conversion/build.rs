@@ -1,7 +1,11 @@
 extern crate cc;
 
 fn main() {
-    cc::Build::new()
-        .file("cbits/lookup_tables.c")
-        .compile("lookup_tables.a");
+    // The `portable` feature avoids compiling C, in which case no lookup
+    // tables are available.
+    if std::env::var_os("CARGO_FEATURE_PORTABLE").is_none() {
+        cc::Build::new()
+            .file("cbits/lookup_tables.c")
+            .compile("lookup_tables.a");
+    }
 }
@@ -6,6 +6,7 @@
 
 #![deny(clippy::all)]
 
+#[cfg(not(feature = "portable"))]
 pub fn lookup_digits_mod_at_position(x: u8, q: u16, pos: usize) -> &'static [u16] {
     unsafe {
         let tab = c_get_table(q, pos);
@@ -14,10 +15,24 @@ pub fn lookup_digits_mod_at_position(x: u8, q: u16, pos: usize) -> &'static [u16
     }
 }
 
+#[cfg(not(feature = "portable"))]
 pub fn lookup_defined_for_mod(q: u16) -> bool {
     unsafe { c_num_digits(q, 0) > 0 }
 }
 
+// Without the C lookup tables, no modulus has a table, and callers fall back
+// to converting by division.
+#[cfg(feature = "portable")]
+pub fn lookup_digits_mod_at_position(_x: u8, q: u16, _pos: usize) -> &'static [u16] {
+    panic!("no lookup table for modulus {} in portable mode", q)
+}
+
+#[cfg(feature = "portable")]
+pub fn lookup_defined_for_mod(_q: u16) -> bool {
+    false
+}
+
+#[cfg(not(feature = "portable"))]
 extern "C" {
     fn c_get_table(q: u16, pos: usize) -> *const u16;
     fn c_num_digits(q: u16, pos: usize) -> usize;
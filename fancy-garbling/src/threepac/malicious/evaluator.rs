@@ -15,7 +15,14 @@ use crate::{
 };
 use digest::{FixedOutput, Input, Reset};
 use rand::{CryptoRng, Rng};
-use scuttlebutt::{AbstractChannel, Block, Malicious, SemiHonest, UniversalDigest};
+use scuttlebutt::{
+    sharing::additive,
+    AbstractChannel,
+    Block,
+    Malicious,
+    SemiHonest,
+    UniversalDigest,
+};
 use std::{
     cmp::min,
     io,
@@ -78,13 +85,12 @@ impl<C1: AbstractChannel, C2: AbstractChannel, RNG: CryptoRng + Rng, H: Universa
 
     /// Secret share Evaluator input among Garbler 1 and 2.
     fn secret_share(&mut self, input: u16, modulus: u16) -> Result<(), Error> {
-        let garbler_1_share: u16 = self.rng.gen_range(0, modulus);
+        let shares = additive::share_mod(u64::from(input), u64::from(modulus), 2, &mut self.rng);
         let channel_garbler_1 = self.get_channel_garbler_1();
-        channel_garbler_1.write_u16(garbler_1_share)?;
+        channel_garbler_1.write_u16(shares[0] as u16)?;
 
-        let garbler_2_share = (garbler_1_share as u32 + input as u32) % modulus as u32;
         let channel_garbler_2 = self.get_channel_garbler_2();
-        channel_garbler_2.write_u16(garbler_2_share as u16)?;
+        channel_garbler_2.write_u16(shares[1] as u16)?;
 
         Ok(())
     }
@@ -145,7 +151,7 @@ impl<C1: AbstractChannel, C2: AbstractChannel, RNG: CryptoRng + Rng, H: Universa
         wires_garbler_1
             .iter()
             .zip(wires_garbler_2.iter())
-            .map(|(w1, w2)| self.evaluator.sub(w2, w1).map_err(Self::Error::from))
+            .map(|(w1, w2)| self.evaluator.add(w1, w2).map_err(Self::Error::from))
             .collect()
     }
 }
@@ -144,7 +144,7 @@ impl<C: AbstractChannel, RNG: CryptoRng + Rng, H: UniversalHash> FancyInput for
             let wires = wires1
                 .iter()
                 .zip(wires2.iter())
-                .map(|(w1, w2)| self.garbler.sub(w2, w1).map_err(Self::Error::from))
+                .map(|(w1, w2)| self.garbler.add(w1, w2).map_err(Self::Error::from))
                 .collect::<Result<Vec<Wire>, Error>>()?;
             Ok(wires)
         } else {
@@ -8,7 +8,7 @@
 //!
 //! Note: all number representations in this library are little-endian.
 
-#[cfg(feature = "nightly")]
+#[cfg(all(feature = "nightly", not(feature = "portable")))]
 use core::arch::x86_64::*;
 use itertools::Itertools;
 use scuttlebutt::Block;
@@ -17,18 +17,18 @@ use scuttlebutt::Block;
 // tweak functions for garbling
 
 /// Tweak function for a single item.
-#[cfg(feature = "nightly")]
+#[cfg(all(feature = "nightly", not(feature = "portable")))]
 pub fn tweak(i: usize) -> Block {
     let data = unsafe { _mm_set_epi64(_mm_setzero_si64(), *(&i as *const _ as *const __m64)) };
     Block(data)
 }
-#[cfg(not(feature = "nightly"))]
+#[cfg(any(not(feature = "nightly"), feature = "portable"))]
 pub fn tweak(i: usize) -> Block {
     Block::from(i as u128)
 }
 
 /// Tweak function for two items.
-#[cfg(feature = "nightly")]
+#[cfg(all(feature = "nightly", not(feature = "portable")))]
 pub fn tweak2(i: u64, j: u64) -> Block {
     let data = unsafe {
         _mm_set_epi64(
@@ -38,7 +38,7 @@ pub fn tweak2(i: u64, j: u64) -> Block {
     };
     Block(data)
 }
-#[cfg(not(feature = "nightly"))]
+#[cfg(any(not(feature = "nightly"), feature = "portable"))]
 pub fn tweak2(i: u64, j: u64) -> Block {
     Block::from(((i as u128) << 64) + j as u128)
 }
@@ -8,7 +8,15 @@
 
 use criterion::{criterion_group, criterion_main, Criterion};
 use ocelot::{
-    oprf::{self, kkrt, kmprt, Receiver as OprfReceiver, Sender as OprfSender},
+    oprf::{
+        self,
+        kkrt,
+        kmprt,
+        ProgrammableReceiver,
+        ProgrammableSender,
+        Receiver as OprfReceiver,
+        Sender as OprfSender,
+    },
     ot::chou_orlandi,
 };
 use scuttlebutt::{AesRng, Block, Block512, Channel};
@@ -96,6 +104,13 @@ fn bench_oprf(c: &mut Criterion) {
             criterion::black_box(result);
         })
     });
+    let inputs = rand_block_vec(1 << 12);
+    c.bench_function("oprf::dh (n = 2^12)", move |bench| {
+        bench.iter(|| {
+            let result = _bench_oprf::<oprf::DhSender, oprf::DhReceiver>(inputs.clone());
+            criterion::black_box(result);
+        })
+    });
 }
 
 fn bench_oprf_compute(c: &mut Criterion) {
@@ -11,6 +11,8 @@ use ocelot::ot::{
     self,
     CorrelatedReceiver,
     CorrelatedSender,
+    ParallelReceiver,
+    ParallelSender,
     RandomReceiver,
     RandomSender,
     Receiver,
@@ -25,6 +27,9 @@ use std::{
 
 /// Specifies the number of OTs to run when benchmarking OT extension.
 const T: usize = 1 << 18;
+/// Specifies the number of threads to use when benchmarking parallel OT
+/// extension.
+const NTHREADS: usize = 4;
 
 fn rand_block_vec(size: usize) -> Vec<Block> {
     (0..size).map(|_| rand::random::<Block>()).collect()
@@ -105,6 +110,42 @@ fn _bench_block_rot<
     handle.join().unwrap();
 }
 
+fn _bench_block_ot_parallel<
+    OTSender: ParallelSender<Msg = Block> + Send,
+    OTReceiver: ParallelReceiver<Msg = Block> + Send,
+>(
+    bs: &[bool],
+    ms: Vec<(Block, Block)>,
+) {
+    let (sender, receiver) = UnixStream::pair().unwrap();
+    let (senders, receivers): (Vec<UnixStream>, Vec<UnixStream>) = (0..NTHREADS)
+        .map(|_| UnixStream::pair().unwrap())
+        .unzip();
+    let handle = std::thread::spawn(move || {
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(sender.try_clone().unwrap());
+        let writer = BufWriter::new(sender);
+        let mut channel = Channel::new(reader, writer);
+        let mut channels = senders
+            .into_iter()
+            .map(|s| Channel::new(BufReader::new(s.try_clone().unwrap()), BufWriter::new(s)))
+            .collect::<Vec<_>>();
+        let mut ot = OTSender::init(&mut channel, &mut rng).unwrap();
+        ot.send_parallel(&mut channels, &ms, &mut rng).unwrap();
+    });
+    let mut rng = AesRng::new();
+    let reader = BufReader::new(receiver.try_clone().unwrap());
+    let writer = BufWriter::new(receiver);
+    let mut channel = Channel::new(reader, writer);
+    let mut channels = receivers
+        .into_iter()
+        .map(|s| Channel::new(BufReader::new(s.try_clone().unwrap()), BufWriter::new(s)))
+        .collect::<Vec<_>>();
+    let mut ot = OTReceiver::init(&mut channel, &mut rng).unwrap();
+    ot.receive_parallel(&mut channels, bs, &mut rng).unwrap();
+    handle.join().unwrap();
+}
+
 fn bench_ot(c: &mut Criterion) {
     c.bench_function("ot::ChouOrlandiOT", move |bench| {
         let m0s = rand_block_vec(128);
@@ -165,6 +206,27 @@ fn bench_otext(c: &mut Criterion) {
     });
 }
 
+fn bench_parallel_otext(c: &mut Criterion) {
+    c.bench_function("ot::AlszOT (parallel)", move |bench| {
+        let ms = rand_block_vec(T)
+            .into_iter()
+            .zip(rand_block_vec(T))
+            .collect::<Vec<(Block, Block)>>();
+        let bs = rand_bool_vec(T);
+        bench.iter(|| {
+            _bench_block_ot_parallel::<ot::AlszSender, ot::AlszReceiver>(&bs, ms.clone())
+        })
+    });
+    c.bench_function("ot::KosOT (parallel)", move |bench| {
+        let ms = rand_block_vec(T)
+            .into_iter()
+            .zip(rand_block_vec(T))
+            .collect::<Vec<(Block, Block)>>();
+        let bs = rand_bool_vec(T);
+        bench.iter(|| _bench_block_ot_parallel::<ot::KosSender, ot::KosReceiver>(&bs, ms.clone()))
+    });
+}
+
 fn bench_correlated_otext(c: &mut Criterion) {
     c.bench_function("cot::AlszOT", move |bench| {
         let deltas = rand_block_vec(T);
@@ -192,7 +254,7 @@ fn bench_random_otext(c: &mut Criterion) {
 criterion_group! {
     name = ot;
     config = Criterion::default().warm_up_time(Duration::from_millis(100)).sample_size(10);
-    targets = bench_ot, bench_otext, bench_correlated_otext, bench_random_otext
+    targets = bench_ot, bench_otext, bench_parallel_otext, bench_correlated_otext, bench_random_otext
 }
 
 criterion_main!(ot);
@@ -1,6 +1,10 @@
 extern crate cc;
 
 fn main() {
+    // The `portable` feature uses a pure-Rust transpose instead.
+    if std::env::var_os("CARGO_FEATURE_PORTABLE").is_some() {
+        return;
+    }
     cc::Build::new()
         .file("cbits/transpose.c")
         .flag("-maes")
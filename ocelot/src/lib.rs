@@ -18,5 +18,6 @@ mod errors;
 mod utils;
 
 pub use crate::errors::Error;
+pub mod ole;
 pub mod oprf;
 pub mod ot;
@@ -0,0 +1,289 @@
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of Gilboa's oblivious linear evaluation protocol secure
+//! against a malicious sender, following Doerner, Kondi, Lee, and shelat (cf.
+//! <https://eprint.iacr.org/2018/499>, Protocol 5).
+//!
+//! On top of the Gilboa OLE (see `gilboa`), this adds:
+//!
+//! * A KOS-style consistency check. Alongside each input `a` the sender
+//!   multiplies random masks `â`, and after a coin toss on the challenges `(χ,
+//!   χ̂)` sends `χ ⋅ a + χ̂ ⋅ â` plus the same linear combination of its shares
+//!   of each OT. The receiver checks these against its own shares, which
+//!   catches a sender that does not use the same `a` in every OT. Each check
+//!   is repeated `Ring::NCHECKS` times.
+//!
+//! * A randomized encoding of the receiver's input. The receiver appends
+//!   `SSP` random choice bits with a random public gadget, so that the choice
+//!   bits are independent of its input, and a sender that cheats on a few OTs
+//!   (and hence fails the check depending on the choice bits) learns nothing
+//!   about the input.
+//!
+//! Note that the OLE outputs are not authenticated: a malicious party can
+//! still add an arbitrary offset to its own share.
+
+use super::{
+    gilboa::{binary_gadget, read_element, receive_core, send_core, write_element},
+    Receiver as OleReceiver,
+    Ring,
+    Sender as OleSender,
+    SSP,
+};
+use crate::{
+    errors::Error,
+    ot::{CorrelatedReceiver, CorrelatedSender},
+};
+use rand::{CryptoRng, Rng, SeedableRng};
+use scuttlebutt::{cointoss, AbstractChannel, AesRng, Block, Malicious, SemiHonest};
+use std::io::ErrorKind;
+
+// The gadget used to encode the receiver's input: its bits, followed by `SSP`
+// random elements derived from `seed`.
+fn gadget<R: Ring>(seed: Block) -> Vec<R> {
+    let mut rng = AesRng::from_seed(seed);
+    let mut gadget = binary_gadget();
+    gadget.extend((0..SSP).map(|_| R::random(&mut rng)));
+    gadget
+}
+
+// The (coin-tossed) challenges for the consistency checks of a single OLE.
+fn challenges<R: Ring>(rng: &mut AesRng) -> Vec<(R, R)> {
+    (0..R::NCHECKS)
+        .map(|_| (R::random(rng), R::random(rng)))
+        .collect()
+}
+
+// Run the sender's side of the consistency check, given the OLE values
+// `values` and the shares `ts` output by `send_core`.
+fn send_checks<R: Ring, C: AbstractChannel, RNG: CryptoRng + Rng>(
+    channel: &mut C,
+    values: &[R],
+    ts: &[R],
+    gadget_len: usize,
+    rng: &mut RNG,
+) -> Result<(), Error> {
+    let width = 1 + R::NCHECKS;
+    let seed = cointoss::send(channel, &[rng.gen::<Block>()], rng)?;
+    let mut rng = AesRng::from_seed(seed[0]);
+    for (values, ts) in values.chunks(width).zip(ts.chunks(gadget_len * width)) {
+        let chis = challenges::<R>(&mut rng);
+        for ((chi, chi_), a_) in chis.iter().zip(values[1..].iter()) {
+            write_element(channel, *chi * values[0] + *chi_ * *a_)?;
+        }
+        for ts in ts.chunks(width) {
+            for ((chi, chi_), t_) in chis.iter().zip(ts[1..].iter()) {
+                write_element(channel, *chi * ts[0] + *chi_ * *t_)?;
+            }
+        }
+    }
+    channel.flush()?;
+    Ok(())
+}
+
+/// DKLs OLE sender.
+pub struct Sender<OT, R> {
+    ot: OT,
+    gadget: Vec<R>,
+}
+
+impl<OT: CorrelatedSender<Msg = Block> + Malicious, R: Ring> OleSender for Sender<OT, R> {
+    type Ring = R;
+
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let ot = OT::init(channel, rng)?;
+        // The receiver chooses the random part of the gadget, which only
+        // serves to protect its own input.
+        let seed = channel.read_block()?;
+        Ok(Self {
+            ot,
+            gadget: gadget(seed),
+        })
+    }
+
+    fn send<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[R],
+        rng: &mut RNG,
+    ) -> Result<Vec<R>, Error> {
+        let width = 1 + R::NCHECKS;
+        let mut values = Vec::with_capacity(inputs.len() * width);
+        for a in inputs.iter() {
+            values.push(*a);
+            values.extend((0..R::NCHECKS).map(|_| R::random(rng)));
+        }
+        let ts = send_core(&mut self.ot, channel, &self.gadget, &values, width, rng)?;
+        send_checks(channel, &values, &ts, self.gadget.len(), rng)?;
+        Ok(ts
+            .chunks(self.gadget.len() * width)
+            .map(|ts| {
+                ts.chunks(width)
+                    .fold(R::default(), |acc, ts| acc + ts[0])
+            })
+            .collect())
+    }
+}
+
+impl<OT, R> std::fmt::Display for Sender<OT, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "DKLs OLE Sender")
+    }
+}
+
+/// DKLs OLE receiver.
+pub struct Receiver<OT, R> {
+    ot: OT,
+    gadget: Vec<R>,
+}
+
+impl<OT: CorrelatedReceiver<Msg = Block> + Malicious, R: Ring> OleReceiver for Receiver<OT, R> {
+    type Ring = R;
+
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let ot = OT::init(channel, rng)?;
+        let seed = rng.gen::<Block>();
+        channel.write_block(&seed)?;
+        channel.flush()?;
+        Ok(Self {
+            ot,
+            gadget: gadget(seed),
+        })
+    }
+
+    fn receive<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[R],
+        rng: &mut RNG,
+    ) -> Result<Vec<R>, Error> {
+        let width = 1 + R::NCHECKS;
+        let random_gadget = &self.gadget[R::NBITS..];
+        let mut choices = Vec::with_capacity(inputs.len() * self.gadget.len());
+        for b in inputs.iter() {
+            // Encode `b` as `b - ∑ gᵢ ⋅ ωᵢ` for random bits `ωᵢ`.
+            let omegas = (0..SSP).map(|_| rng.gen::<bool>()).collect::<Vec<bool>>();
+            let b_ = random_gadget
+                .iter()
+                .zip(omegas.iter())
+                .fold(*b, |acc, (g, w)| if *w { acc - *g } else { acc });
+            choices.extend((0..R::NBITS).map(|j| b_.bit(j)));
+            choices.extend(omegas);
+        }
+        let ts = receive_core::<OT, R, C, RNG>(&mut self.ot, channel, &choices, width, rng)?;
+        // Check consistency.
+        let seed = cointoss::receive(channel, &[rng.gen::<Block>()])?;
+        let mut rng = AesRng::from_seed(seed[0]);
+        let mut out = Vec::with_capacity(inputs.len());
+        let mut passed = true;
+        for (choices, ts) in choices
+            .chunks(self.gadget.len())
+            .zip(ts.chunks(self.gadget.len() * width))
+        {
+            let chis = challenges::<R>(&mut rng);
+            let us = (0..R::NCHECKS)
+                .map(|_| read_element(channel))
+                .collect::<Result<Vec<R>, Error>>()?;
+            for ((w, g), ts) in choices
+                .iter()
+                .zip(self.gadget.iter())
+                .zip(ts.chunks(width))
+            {
+                for (((chi, chi_), u), t_) in chis.iter().zip(us.iter()).zip(ts[1..].iter()) {
+                    let r = read_element::<R, C>(channel)?;
+                    let expected = if *w { *g * *u } else { R::default() };
+                    passed &= r + *chi * ts[0] + *chi_ * *t_ == expected;
+                }
+            }
+            out.push(
+                ts.chunks(width)
+                    .fold(R::default(), |acc, ts| acc + ts[0]),
+            );
+        }
+        if !passed {
+            return Err(Error::from(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "Consistency check failed",
+            )));
+        }
+        Ok(out)
+    }
+}
+
+impl<OT, R> std::fmt::Display for Receiver<OT, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "DKLs OLE Receiver")
+    }
+}
+
+impl<OT: Malicious, R> SemiHonest for Sender<OT, R> {}
+impl<OT: Malicious, R> SemiHonest for Receiver<OT, R> {}
+impl<OT: Malicious, R> Malicious for Sender<OT, R> {}
+impl<OT: Malicious, R> Malicious for Receiver<OT, R> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ole::{DklsReceiver, F61};
+    use crate::ot::KosSender;
+    use scuttlebutt::Channel;
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    // A sender that uses a different input in one of the OTs of each OLE.
+    fn cheating_send<C: AbstractChannel>(
+        channel: &mut C,
+        inputs: &[F61],
+        rng: &mut AesRng,
+    ) -> Result<(), Error> {
+        let mut ole = Sender::<KosSender, F61>::init(channel, rng)?;
+        let width = 1 + F61::NCHECKS;
+        let mut values = Vec::with_capacity(inputs.len() * width);
+        for a in inputs.iter() {
+            values.push(*a);
+            values.extend((0..F61::NCHECKS).map(|_| F61::random(rng)));
+        }
+        // Shift the last gadget entry, which is equivalent to using the input
+        // `a + 1` in the last OT.
+        let mut gadget = ole.gadget.clone();
+        let last = gadget.len() - 1;
+        gadget[last] += F61::from_u128(1);
+        let ts = send_core(&mut ole.ot, channel, &gadget, &values, width, rng)?;
+        send_checks(channel, &values, &ts, gadget.len(), rng)
+    }
+
+    #[test]
+    fn test_cheating_sender() {
+        let mut rng = AesRng::new();
+        let ninputs = 64;
+        let xs = (0..ninputs).map(|_| F61::random(&mut rng)).collect::<Vec<F61>>();
+        let ys = (0..ninputs).map(|_| F61::random(&mut rng)).collect::<Vec<F61>>();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            cheating_send(&mut channel, &xs, &mut rng).unwrap();
+        });
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut ole = DklsReceiver::<F61>::init(&mut channel, &mut rng).unwrap();
+        // The cheating is only caught when the last (random) choice bit of
+        // some OLE is set, which fails to happen with probability `2^-64`.
+        assert!(ole.receive(&mut channel, &ys, &mut rng).is_err());
+        handle.join().unwrap();
+    }
+}
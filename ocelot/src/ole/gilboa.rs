@@ -0,0 +1,208 @@
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of Gilboa's semi-honest oblivious linear evaluation protocol
+//! (cf. <https://doi.org/10.1007/3-540-48405-1_8>) from correlated OT.
+//!
+//! The receiver decomposes its input `b` into bits `b_j`, and uses `b_j` as the
+//! choice bit of the `j`th OT. The sender derives a pair of random pads
+//! `(p₀, p₁)` from the messages of each correlated OT, and sends the
+//! correction `p₁ - p₀ - 2^j ⋅ a`, so that the receiver learns
+//! `p₀ + b_j ⋅ 2^j ⋅ a`. Summing over all `j` gives `-∑ p₀` and
+//! `∑ p₀ + a ⋅ b` as the sender and receiver shares.
+
+use crate::{
+    errors::Error,
+    ole::{Receiver as OleReceiver, Ring, Sender as OleSender},
+    ot::{CorrelatedReceiver, CorrelatedSender},
+};
+use rand::{CryptoRng, Rng};
+use scuttlebutt::{AbstractChannel, Block, SemiHonest, AES_HASH};
+use std::marker::PhantomData;
+
+// Derive the `l`th pad from the OT message `x`.
+#[inline]
+fn pad<R: Ring>(x: Block, l: usize) -> R {
+    R::from_block(AES_HASH.tccr_hash(Block::from(l as u128), x))
+}
+
+// Write a ring element to the channel.
+#[inline]
+pub(super) fn write_element<R: Ring, C: AbstractChannel>(
+    channel: &mut C,
+    x: R,
+) -> Result<(), Error> {
+    channel.write_u64(x.to_u64())?;
+    Ok(())
+}
+
+// Read a ring element from the channel.
+#[inline]
+pub(super) fn read_element<R: Ring, C: AbstractChannel>(channel: &mut C) -> Result<R, Error> {
+    Ok(R::from_u128(u128::from(channel.read_u64()?)))
+}
+
+// Run the sender's side of `inputs.len() / width` OLEs, each of which uses one
+// correlated OT per entry of `gadget`. Each OT multiplies the gadget entry by
+// `width` values at once: the `i`th OLE uses `inputs[i * width..(i + 1) *
+// width]`. Returns the sender's shares of `b_j ⋅ g_j ⋅ a_l` for each OLE `i`,
+// OT `j`, and value `l`, in that order.
+pub(super) fn send_core<OT, R, C, RNG>(
+    ot: &mut OT,
+    channel: &mut C,
+    gadget: &[R],
+    inputs: &[R],
+    width: usize,
+    rng: &mut RNG,
+) -> Result<Vec<R>, Error>
+where
+    OT: CorrelatedSender<Msg = Block>,
+    R: Ring,
+    C: AbstractChannel,
+    RNG: CryptoRng + Rng,
+{
+    let n = inputs.len() / width;
+    // The correlations are random, so that `x₁` is independent of `x₀` and
+    // the pads derived from `x₁` remain hidden from a receiver with choice
+    // bit zero.
+    let deltas = (0..n * gadget.len())
+        .map(|_| rng.gen::<Block>())
+        .collect::<Vec<Block>>();
+    let xs = ot.send_correlated(channel, &deltas, rng)?;
+    let mut out = Vec::with_capacity(n * gadget.len() * width);
+    for (i, xs) in xs.chunks(gadget.len()).enumerate() {
+        let inputs = &inputs[i * width..(i + 1) * width];
+        for ((x0, x1), g) in xs.iter().zip(gadget.iter()) {
+            for (l, a) in inputs.iter().enumerate() {
+                let p0 = pad::<R>(*x0, l);
+                let p1 = pad::<R>(*x1, l);
+                write_element(channel, p1 - p0 - *g * *a)?;
+                out.push(-p0);
+            }
+        }
+    }
+    channel.flush()?;
+    Ok(out)
+}
+
+// Run the receiver's side of `send_core`, where `choices` contains the choice
+// bit of each OT.
+pub(super) fn receive_core<OT, R, C, RNG>(
+    ot: &mut OT,
+    channel: &mut C,
+    choices: &[bool],
+    width: usize,
+    rng: &mut RNG,
+) -> Result<Vec<R>, Error>
+where
+    OT: CorrelatedReceiver<Msg = Block>,
+    R: Ring,
+    C: AbstractChannel,
+    RNG: CryptoRng + Rng,
+{
+    let xs = ot.receive_correlated(channel, choices, rng)?;
+    let mut out = Vec::with_capacity(choices.len() * width);
+    for (x, b) in xs.into_iter().zip(choices.iter()) {
+        for l in 0..width {
+            let c = read_element::<R, C>(channel)?;
+            let p = pad::<R>(x, l);
+            out.push(if *b { p - c } else { p });
+        }
+    }
+    Ok(out)
+}
+
+// The gadget `(1, 2, 4, ..., 2^{NBITS-1})` used to decompose an input into bits.
+pub(super) fn binary_gadget<R: Ring>() -> Vec<R> {
+    (0..R::NBITS).map(R::pow2).collect()
+}
+
+/// Gilboa OLE sender.
+pub struct Sender<OT, R> {
+    ot: OT,
+    gadget: Vec<R>,
+}
+
+impl<OT: CorrelatedSender<Msg = Block> + SemiHonest, R: Ring> OleSender for Sender<OT, R> {
+    type Ring = R;
+
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let ot = OT::init(channel, rng)?;
+        Ok(Self {
+            ot,
+            gadget: binary_gadget(),
+        })
+    }
+
+    fn send<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[R],
+        rng: &mut RNG,
+    ) -> Result<Vec<R>, Error> {
+        let ts = send_core(&mut self.ot, channel, &self.gadget, inputs, 1, rng)?;
+        Ok(ts
+            .chunks(self.gadget.len())
+            .map(|ts| ts.iter().fold(R::default(), |acc, t| acc + *t))
+            .collect())
+    }
+}
+
+impl<OT, R> std::fmt::Display for Sender<OT, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Gilboa OLE Sender")
+    }
+}
+
+/// Gilboa OLE receiver.
+pub struct Receiver<OT, R> {
+    ot: OT,
+    _ring: PhantomData<R>,
+}
+
+impl<OT: CorrelatedReceiver<Msg = Block> + SemiHonest, R: Ring> OleReceiver for Receiver<OT, R> {
+    type Ring = R;
+
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let ot = OT::init(channel, rng)?;
+        Ok(Self {
+            ot,
+            _ring: PhantomData::<R>,
+        })
+    }
+
+    fn receive<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[R],
+        rng: &mut RNG,
+    ) -> Result<Vec<R>, Error> {
+        let choices = inputs
+            .iter()
+            .flat_map(|b| (0..R::NBITS).map(move |j| b.bit(j)))
+            .collect::<Vec<bool>>();
+        let ts = receive_core::<OT, R, C, RNG>(&mut self.ot, channel, &choices, 1, rng)?;
+        Ok(ts
+            .chunks(R::NBITS)
+            .map(|ts| ts.iter().fold(R::default(), |acc, t| acc + *t))
+            .collect())
+    }
+}
+
+impl<OT, R> std::fmt::Display for Receiver<OT, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Gilboa OLE Receiver")
+    }
+}
+
+impl<OT: SemiHonest, R> SemiHonest for Sender<OT, R> {}
+impl<OT: SemiHonest, R> SemiHonest for Receiver<OT, R> {}
@@ -0,0 +1,165 @@
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Oblivious linear evaluation (OLE) traits + instantiations.
+//!
+//! An OLE takes an input `a` from the sender and `b` from the receiver, and
+//! outputs additive shares `u` and `v` to the sender and receiver,
+//! respectively, such that `u + v = a ⋅ b`. This module provides traits for
+//! OLE over rings of integers modulo `N` (see `ring`), alongside the
+//! following implementations, both built on correlated OT:
+//!
+//! * `gilboa`: Gilboa's semi-honest OLE.
+//! * `dkls`: Gilboa's OLE, with the Doerner-Kondi-Lee-shelat consistency check
+//!   and randomized input encoding for security against a malicious sender.
+//!
+//! The `triple` module combines OLEs in both directions to generate Beaver
+//! multiplication triples.
+
+pub mod dkls;
+pub mod gilboa;
+pub mod ring;
+pub mod triple;
+
+pub use ring::{Fp, Ring, Z2k};
+
+use crate::{
+    errors::Error,
+    ot::{AlszReceiver, AlszSender, KosReceiver, KosSender},
+};
+use rand::{CryptoRng, Rng};
+use scuttlebutt::AbstractChannel;
+
+// The statistical security parameter.
+const SSP: usize = 40;
+
+/// The ring of integers modulo `2^64`.
+pub type Z2_64 = Z2k<64>;
+/// The field of integers modulo the Mersenne prime `2^61 - 1`.
+pub type F61 = Fp<2_305_843_009_213_693_951>;
+
+/// Instantiation of the Gilboa OLE sender, using ALSZ as the correlated OT.
+pub type GilboaSender<R> = gilboa::Sender<AlszSender, R>;
+/// Instantiation of the Gilboa OLE receiver, using ALSZ as the correlated OT.
+pub type GilboaReceiver<R> = gilboa::Receiver<AlszReceiver, R>;
+/// Instantiation of the DKLs OLE sender, using KOS as the correlated OT.
+pub type DklsSender<R> = dkls::Sender<KosSender, R>;
+/// Instantiation of the DKLs OLE receiver, using KOS as the correlated OT.
+pub type DklsReceiver<R> = dkls::Receiver<KosReceiver, R>;
+/// Instantiation of the semi-honest triple generator sender.
+pub type TripleSender<R> = triple::Sender<GilboaSender<R>, GilboaReceiver<R>>;
+/// Instantiation of the semi-honest triple generator receiver.
+pub type TripleReceiver<R> = triple::Receiver<GilboaSender<R>, GilboaReceiver<R>>;
+/// Instantiation of the malicious triple generator sender.
+pub type MaliciousTripleSender<R> = triple::Sender<DklsSender<R>, DklsReceiver<R>>;
+/// Instantiation of the malicious triple generator receiver.
+pub type MaliciousTripleReceiver<R> = triple::Receiver<DklsSender<R>, DklsReceiver<R>>;
+
+/// Trait for oblivious linear evaluation from the sender's point-of-view.
+pub trait Sender
+where
+    Self: Sized,
+{
+    /// The ring over which the OLE is computed.
+    type Ring: Ring;
+    /// Runs any one-time initialization.
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error>;
+    /// Runs one OLE per entry of `inputs`, returning the sender's shares of
+    /// the products.
+    fn send<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[Self::Ring],
+        rng: &mut RNG,
+    ) -> Result<Vec<Self::Ring>, Error>;
+}
+
+/// Trait for oblivious linear evaluation from the receiver's point-of-view.
+pub trait Receiver
+where
+    Self: Sized,
+{
+    /// The ring over which the OLE is computed.
+    type Ring: Ring;
+    /// Runs any one-time initialization.
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error>;
+    /// Runs one OLE per entry of `inputs`, returning the receiver's shares of
+    /// the products.
+    fn receive<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[Self::Ring],
+        rng: &mut RNG,
+    ) -> Result<Vec<Self::Ring>, Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scuttlebutt::{AesRng, Channel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    fn test_ole<
+        R: Ring + 'static,
+        OLESender: Sender<Ring = R> + Send + 'static,
+        OLEReceiver: Receiver<Ring = R>,
+    >(
+        ninputs: usize,
+    ) {
+        let mut rng = AesRng::new();
+        let xs = (0..ninputs).map(|_| R::random(&mut rng)).collect::<Vec<R>>();
+        let ys = (0..ninputs).map(|_| R::random(&mut rng)).collect::<Vec<R>>();
+        let xs_ = xs.clone();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut ole = OLESender::init(&mut channel, &mut rng).unwrap();
+            // Run twice to check that the OLE object can be reused.
+            let us = ole.send(&mut channel, &xs_, &mut rng).unwrap();
+            let us_ = ole.send(&mut channel, &xs_, &mut rng).unwrap();
+            (us, us_)
+        });
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut ole = OLEReceiver::init(&mut channel, &mut rng).unwrap();
+        let vs = ole.receive(&mut channel, &ys, &mut rng).unwrap();
+        let vs_ = ole.receive(&mut channel, &ys, &mut rng).unwrap();
+        let (us, us_) = handle.join().unwrap();
+        for i in 0..ninputs {
+            assert_eq!(us[i] + vs[i], xs[i] * ys[i]);
+            assert_eq!(us_[i] + vs_[i], xs[i] * ys[i]);
+        }
+    }
+
+    #[test]
+    fn test_gilboa() {
+        test_ole::<Z2_64, GilboaSender<Z2_64>, GilboaReceiver<Z2_64>>(100);
+        test_ole::<Z2k<13>, GilboaSender<Z2k<13>>, GilboaReceiver<Z2k<13>>>(100);
+        test_ole::<F61, GilboaSender<F61>, GilboaReceiver<F61>>(100);
+        test_ole::<Fp<65537>, GilboaSender<Fp<65537>>, GilboaReceiver<Fp<65537>>>(1);
+    }
+
+    #[test]
+    fn test_dkls() {
+        test_ole::<Z2_64, DklsSender<Z2_64>, DklsReceiver<Z2_64>>(10);
+        test_ole::<Z2k<13>, DklsSender<Z2k<13>>, DklsReceiver<Z2k<13>>>(10);
+        test_ole::<F61, DklsSender<F61>, DklsReceiver<F61>>(100);
+        test_ole::<Fp<65537>, DklsSender<Fp<65537>>, DklsReceiver<Fp<65537>>>(1);
+    }
+}
@@ -0,0 +1,249 @@
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Rings over which oblivious linear evaluations are computed.
+//!
+//! This module provides `Z2k`, the ring of integers modulo `2^K` for `K ≤ 64`,
+//! and `Fp`, the field of integers modulo a prime `P < 2^63`.
+
+use super::SSP;
+use rand::Rng;
+use scuttlebutt::Block;
+use std::{
+    fmt::Debug,
+    ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign},
+};
+
+/// Trait for rings of integers modulo some `N < 2^64`.
+pub trait Ring:
+    Copy
+    + Clone
+    + Debug
+    + Default
+    + PartialEq
+    + Eq
+    + Send
+    + Sync
+    + Add<Output = Self>
+    + AddAssign
+    + Sub<Output = Self>
+    + SubAssign
+    + Mul<Output = Self>
+    + Neg<Output = Self>
+{
+    /// The number of bits needed to represent an element.
+    const NBITS: usize;
+    /// The number of independent consistency checks needed by the malicious
+    /// OLE protocol, such that a cheating sender passes with probability at
+    /// most `2^{-SSP}`.
+    const NCHECKS: usize;
+
+    /// Reduce `x` into the ring.
+    fn from_u128(x: u128) -> Self;
+    /// Return the canonical representative of the element, in `[0, N)`.
+    fn to_u64(self) -> u64;
+
+    /// The `i`th power of two.
+    #[inline]
+    fn pow2(i: usize) -> Self {
+        Self::from_u128(1 << i)
+    }
+    /// Return the `i`th bit of the canonical representative.
+    #[inline]
+    fn bit(self, i: usize) -> bool {
+        (self.to_u64() >> i) & 1 == 1
+    }
+    /// Map a (uniformly random) block into the ring. For `N` not a power of
+    /// two, the result is statistically close to uniform.
+    #[inline]
+    fn from_block(b: Block) -> Self {
+        Self::from_u128(u128::from(b))
+    }
+    /// Sample a uniformly random element.
+    #[inline]
+    fn random<RNG: Rng>(rng: &mut RNG) -> Self {
+        Self::from_block(rng.gen::<Block>())
+    }
+}
+
+/// The ring of integers modulo `2^K`, for `1 ≤ K ≤ 64`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Z2k<const K: u32>(u64);
+
+impl<const K: u32> Z2k<K> {
+    const MASK: u64 = if K >= 64 { u64::MAX } else { (1 << K) - 1 };
+}
+
+impl<const K: u32> Ring for Z2k<K> {
+    const NBITS: usize = K as usize;
+    // A consistency check can be passed with probability `1/2` by an error
+    // that is a multiple of `2^{K-1}`, so each check only gives one bit of
+    // security.
+    const NCHECKS: usize = SSP;
+
+    #[inline]
+    fn from_u128(x: u128) -> Self {
+        Self(x as u64 & Self::MASK)
+    }
+    #[inline]
+    fn to_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl<const K: u32> Add for Z2k<K> {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self::from_u128(u128::from(self.0.wrapping_add(rhs.0)))
+    }
+}
+
+impl<const K: u32> AddAssign for Z2k<K> {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const K: u32> Sub for Z2k<K> {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self::from_u128(u128::from(self.0.wrapping_sub(rhs.0)))
+    }
+}
+
+impl<const K: u32> SubAssign for Z2k<K> {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<const K: u32> Mul for Z2k<K> {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        Self::from_u128(u128::from(self.0.wrapping_mul(rhs.0)))
+    }
+}
+
+impl<const K: u32> Neg for Z2k<K> {
+    type Output = Self;
+    #[inline]
+    fn neg(self) -> Self {
+        Self(self.0.wrapping_neg() & Self::MASK)
+    }
+}
+
+/// The field of integers modulo the prime `P`, for `P < 2^63`. Primality of
+/// `P` is *not* checked, but is needed for the malicious OLE protocol to be
+/// secure.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Fp<const P: u64>(u64);
+
+impl<const P: u64> Ring for Fp<P> {
+    const NBITS: usize = 64 - P.leading_zeros() as usize;
+    // A consistency check is passed with probability at most `1/P`.
+    const NCHECKS: usize = SSP.div_ceil(Self::NBITS - 1);
+
+    #[inline]
+    fn from_u128(x: u128) -> Self {
+        Self((x % P as u128) as u64)
+    }
+    #[inline]
+    fn to_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl<const P: u64> Add for Fp<P> {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        let x = self.0 + rhs.0;
+        Self(if x >= P { x - P } else { x })
+    }
+}
+
+impl<const P: u64> AddAssign for Fp<P> {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const P: u64> Sub for Fp<P> {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl<const P: u64> SubAssign for Fp<P> {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<const P: u64> Mul for Fp<P> {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        Self::from_u128(self.0 as u128 * rhs.0 as u128)
+    }
+}
+
+impl<const P: u64> Neg for Fp<P> {
+    type Output = Self;
+    #[inline]
+    fn neg(self) -> Self {
+        Self(if self.0 == 0 { 0 } else { P - self.0 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ole::{F61, Z2_64};
+    use scuttlebutt::AesRng;
+
+    fn test_ring<R: Ring>(modulus: u128) {
+        let mut rng = AesRng::new();
+        for _ in 0..128 {
+            let x = R::random(&mut rng);
+            let y = R::random(&mut rng);
+            let (x_, y_) = (x.to_u64() as u128, y.to_u64() as u128);
+            assert!(x_ < modulus);
+            assert_eq!((x + y).to_u64() as u128, (x_ + y_) % modulus);
+            assert_eq!((x - y).to_u64() as u128, (x_ + modulus - y_) % modulus);
+            assert_eq!((x * y).to_u64() as u128, (x_ * y_) % modulus);
+            assert_eq!(x + -x, R::default());
+            let bits = (0..R::NBITS)
+                .map(|i| if x.bit(i) { R::pow2(i) } else { R::default() })
+                .fold(R::default(), |acc, b| acc + b);
+            assert_eq!(bits, x);
+        }
+    }
+
+    #[test]
+    fn test_z2k() {
+        test_ring::<Z2_64>(1 << 64);
+        test_ring::<Z2k<32>>(1 << 32);
+        test_ring::<Z2k<13>>(1 << 13);
+        test_ring::<Z2k<1>>(2);
+    }
+
+    #[test]
+    fn test_fp() {
+        test_ring::<F61>((1 << 61) - 1);
+        test_ring::<Fp<65537>>(65537);
+        test_ring::<Fp<3>>(3);
+    }
+}
@@ -0,0 +1,182 @@
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Generation of Beaver multiplication triples from oblivious linear
+//! evaluation.
+//!
+//! Each party samples random shares `(a, b)`, and the cross terms of `(a₀ +
+//! a₁) ⋅ (b₀ + b₁)` are computed using one OLE in each direction, so that each
+//! party ends up with an additive share of `c = a ⋅ b`. Both parties hold an
+//! OLE sender and an OLE receiver, initialized in opposite orders.
+
+use super::{Receiver as OleReceiver, Ring, Sender as OleSender};
+use crate::errors::Error;
+use rand::{CryptoRng, Rng};
+use scuttlebutt::{AbstractChannel, Malicious, SemiHonest};
+
+/// One party's additive shares of a multiplication triple `(a, b, c)`, where
+/// `c = a ⋅ b`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Triple<R> {
+    /// Share of `a`.
+    pub a: R,
+    /// Share of `b`.
+    pub b: R,
+    /// Share of `c = a ⋅ b`.
+    pub c: R,
+}
+
+// Sample `n` random `(a, b)` pairs.
+fn random_pairs<R: Ring, RNG: CryptoRng + Rng>(n: usize, rng: &mut RNG) -> (Vec<R>, Vec<R>) {
+    let a = (0..n).map(|_| R::random(rng)).collect();
+    let b = (0..n).map(|_| R::random(rng)).collect();
+    (a, b)
+}
+
+// Combine the local shares with the OLE outputs for the cross terms.
+fn combine<R: Ring>(a: Vec<R>, b: Vec<R>, us: Vec<R>, vs: Vec<R>) -> Vec<Triple<R>> {
+    a.into_iter()
+        .zip(b)
+        .zip(us.into_iter().zip(vs))
+        .map(|((a, b), (u, v))| Triple {
+            a,
+            b,
+            c: a * b + u + v,
+        })
+        .collect()
+}
+
+/// Triple generation sender.
+pub struct Sender<OLES, OLER> {
+    sender: OLES,
+    receiver: OLER,
+}
+
+impl<R: Ring, OLES: OleSender<Ring = R>, OLER: OleReceiver<Ring = R>> Sender<OLES, OLER> {
+    /// Initialize the triple generator.
+    pub fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let sender = OLES::init(channel, rng)?;
+        let receiver = OLER::init(channel, rng)?;
+        Ok(Self { sender, receiver })
+    }
+
+    /// Generate `n` multiplication triples.
+    pub fn generate<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+        rng: &mut RNG,
+    ) -> Result<Vec<Triple<R>>, Error> {
+        let (a, b) = random_pairs(n, rng);
+        let us = self.sender.send(channel, &a, rng)?;
+        let vs = self.receiver.receive(channel, &b, rng)?;
+        Ok(combine(a, b, us, vs))
+    }
+}
+
+impl<OLES, OLER> std::fmt::Display for Sender<OLES, OLER> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Triple Sender")
+    }
+}
+
+/// Triple generation receiver.
+pub struct Receiver<OLES, OLER> {
+    sender: OLES,
+    receiver: OLER,
+}
+
+impl<R: Ring, OLES: OleSender<Ring = R>, OLER: OleReceiver<Ring = R>> Receiver<OLES, OLER> {
+    /// Initialize the triple generator.
+    pub fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let receiver = OLER::init(channel, rng)?;
+        let sender = OLES::init(channel, rng)?;
+        Ok(Self { sender, receiver })
+    }
+
+    /// Generate `n` multiplication triples.
+    pub fn generate<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+        rng: &mut RNG,
+    ) -> Result<Vec<Triple<R>>, Error> {
+        let (a, b) = random_pairs(n, rng);
+        let vs = self.receiver.receive(channel, &b, rng)?;
+        let us = self.sender.send(channel, &a, rng)?;
+        Ok(combine(a, b, us, vs))
+    }
+}
+
+impl<OLES, OLER> std::fmt::Display for Receiver<OLES, OLER> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Triple Receiver")
+    }
+}
+
+impl<OLES: SemiHonest, OLER: SemiHonest> SemiHonest for Sender<OLES, OLER> {}
+impl<OLES: SemiHonest, OLER: SemiHonest> SemiHonest for Receiver<OLES, OLER> {}
+impl<OLES: Malicious, OLER: Malicious> Malicious for Sender<OLES, OLER> {}
+impl<OLES: Malicious, OLER: Malicious> Malicious for Receiver<OLES, OLER> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ole::{DklsReceiver, DklsSender, GilboaReceiver, GilboaSender, F61, Z2_64};
+    use scuttlebutt::{AesRng, Channel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    fn test_triples<
+        R: Ring + 'static,
+        OLES: OleSender<Ring = R> + 'static,
+        OLER: OleReceiver<Ring = R> + 'static,
+    >(
+        n: usize,
+    ) {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut gen = Sender::<OLES, OLER>::init(&mut channel, &mut rng).unwrap();
+            gen.generate(&mut channel, n, &mut rng).unwrap()
+        });
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut gen = Receiver::<OLES, OLER>::init(&mut channel, &mut rng).unwrap();
+        let triples1 = gen.generate(&mut channel, n, &mut rng).unwrap();
+        let triples0 = handle.join().unwrap();
+        assert_eq!(triples0.len(), n);
+        assert_eq!(triples1.len(), n);
+        for (t0, t1) in triples0.into_iter().zip(triples1) {
+            assert_eq!(t0.c + t1.c, (t0.a + t1.a) * (t0.b + t1.b));
+        }
+    }
+
+    #[test]
+    fn test_semihonest() {
+        test_triples::<Z2_64, GilboaSender<Z2_64>, GilboaReceiver<Z2_64>>(100);
+        test_triples::<F61, GilboaSender<F61>, GilboaReceiver<F61>>(100);
+    }
+
+    #[test]
+    fn test_malicious() {
+        test_triples::<Z2_64, DklsSender<Z2_64>, DklsReceiver<Z2_64>>(4);
+        test_triples::<F61, DklsSender<F61>, DklsReceiver<F61>>(100);
+    }
+}
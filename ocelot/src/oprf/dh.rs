@@ -0,0 +1,306 @@
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of the Diffie-Hellman-based oblivious PRF `F_k(x) = H'(x,
+//! H(x)^k)` of Jarecki, Kiayias, and Krawczyk (cf.
+//! <https://eprint.iacr.org/2014/650>), where `H` hashes into the Ristretto
+//! group and `H'` is SHA-512.
+//!
+//! The receiver blinds each `H(x)` with a fresh scalar `r`, the sender raises
+//! the blinded points to its key `k`, and the receiver unblinds the result with
+//! `r⁻¹`. Unlike KKRT, the PRF key is a single scalar that is independent of
+//! the number of OPRF instances. Hence the sender can evaluate the PRF on
+//! arbitrary inputs locally, and can reuse the same key across many sessions
+//! (see `Sender::from_key`).
+//!
+//! All group operations are batched, and the expensive ones (hashing to the
+//! curve, point decompression and scalar multiplication) are spread across
+//! `nthreads` threads.
+
+use crate::{
+    errors::Error,
+    oprf::{ObliviousPrf, Receiver as OprfReceiver, Sender as OprfSender},
+    utils,
+};
+use curve25519_dalek::{
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+};
+use rand::{CryptoRng, Rng};
+use scuttlebutt::{AbstractChannel, Block, Block512, SemiHonest};
+use sha2::{Digest, Sha512};
+
+// Domain separator used when hashing inputs into the group.
+const HASH_TO_POINT_PREFIX: &[u8] = b"ocelot::oprf::dh::H";
+// Domain separator used when hashing group elements into PRF outputs.
+const HASH_TO_OUTPUT_PREFIX: &[u8] = b"ocelot::oprf::dh::H'";
+
+// Hash `x` into the Ristretto group.
+#[inline]
+fn hash_to_point(x: &Block) -> RistrettoPoint {
+    let mut h = Sha512::new();
+    h.input(HASH_TO_POINT_PREFIX);
+    h.input(x.as_ref());
+    RistrettoPoint::from_hash(h)
+}
+
+// Compute the final PRF output from the input `x` and the point `H(x)^k`.
+#[inline]
+fn hash_to_output(x: &Block, pt: &RistrettoPoint) -> Block512 {
+    let mut h = Sha512::new();
+    h.input(HASH_TO_OUTPUT_PREFIX);
+    h.input(x.as_ref());
+    h.input(pt.compress().as_bytes());
+    let mut out = [0u8; 64];
+    out.copy_from_slice(&h.result());
+    Block512::from(out)
+}
+
+// Decompress a batch of points in parallel, failing if any encoding is invalid.
+fn decompress_points(
+    bytes: &[[u8; 32]],
+    nthreads: usize,
+) -> Result<Vec<RistrettoPoint>, Error> {
+    utils::parallel_map(bytes, nthreads, |b| CompressedRistretto(*b).decompress())
+        .into_iter()
+        .map(|pt| {
+            pt.ok_or_else(|| {
+                Error::from(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "unable to decompress ristretto point",
+                ))
+            })
+        })
+        .collect()
+}
+
+fn read_compressed_points<C: AbstractChannel>(
+    channel: &mut C,
+    n: usize,
+) -> Result<Vec<[u8; 32]>, Error> {
+    let mut out = vec![[0u8; 32]; n];
+    for bytes in out.iter_mut() {
+        channel.read_exact(bytes)?;
+    }
+    Ok(out)
+}
+
+/// Diffie-Hellman oblivious PRF sender.
+pub struct Sender {
+    key: Scalar,
+    nthreads: usize,
+}
+
+impl Sender {
+    /// Create a sender using the PRF key `key`. This allows the same key (and
+    /// hence the same PRF) to be used across multiple sessions.
+    pub fn from_key(key: Scalar) -> Self {
+        Self {
+            key,
+            nthreads: utils::default_nthreads(),
+        }
+    }
+
+    /// Return the PRF key.
+    pub fn key(&self) -> &Scalar {
+        &self.key
+    }
+
+    /// Set the number of threads used for the group operations.
+    pub fn set_nthreads(&mut self, nthreads: usize) {
+        self.nthreads = std::cmp::max(nthreads, 1);
+    }
+
+    /// Evaluate the PRF on `input` locally.
+    pub fn evaluate(&self, input: Block) -> Block512 {
+        hash_to_output(&input, &(hash_to_point(&input) * self.key))
+    }
+
+    /// Evaluate the PRF on each entry of `inputs` locally, using multiple
+    /// threads.
+    pub fn evaluate_many(&self, inputs: &[Block]) -> Vec<Block512> {
+        utils::parallel_map(inputs, self.nthreads, |x| self.evaluate(*x))
+    }
+}
+
+impl ObliviousPrf for Sender {
+    type Seed = ();
+    type Input = Block;
+    type Output = Block512;
+}
+
+impl OprfSender for Sender {
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        _: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        Ok(Self::from_key(Scalar::random(rng)))
+    }
+
+    fn send<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        m: usize,
+        _: &mut RNG,
+    ) -> Result<Vec<Self::Seed>, Error> {
+        // Read all the blinded points before responding, so that neither party
+        // blocks on a full channel.
+        let bytes = read_compressed_points(channel, m)?;
+        let pts = decompress_points(&bytes, self.nthreads)?;
+        let key = self.key;
+        let pts = utils::parallel_map(&pts, self.nthreads, |pt| (pt * key).compress());
+        for pt in pts.iter() {
+            channel.write_all(pt.as_bytes())?;
+        }
+        channel.flush()?;
+        Ok(vec![(); m])
+    }
+
+    #[inline]
+    fn compute(&self, _: Self::Seed, input: Self::Input) -> Self::Output {
+        self.evaluate(input)
+    }
+}
+
+impl std::fmt::Display for Sender {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "DH-OPRF Sender")
+    }
+}
+
+/// Diffie-Hellman oblivious PRF receiver.
+pub struct Receiver {
+    nthreads: usize,
+}
+
+impl Receiver {
+    /// Set the number of threads used for the group operations.
+    pub fn set_nthreads(&mut self, nthreads: usize) {
+        self.nthreads = std::cmp::max(nthreads, 1);
+    }
+}
+
+impl ObliviousPrf for Receiver {
+    type Seed = ();
+    type Input = Block;
+    type Output = Block512;
+}
+
+impl OprfReceiver for Receiver {
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        _: &mut C,
+        _: &mut RNG,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            nthreads: utils::default_nthreads(),
+        })
+    }
+
+    fn receive<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[Self::Input],
+        rng: &mut RNG,
+    ) -> Result<Vec<Self::Output>, Error> {
+        let blinds = inputs
+            .iter()
+            .map(|x| (*x, Scalar::random(rng)))
+            .collect::<Vec<(Block, Scalar)>>();
+        let blinded = utils::parallel_map(&blinds, self.nthreads, |(x, r)| {
+            (hash_to_point(x) * r).compress()
+        });
+        for pt in blinded.iter() {
+            channel.write_all(pt.as_bytes())?;
+        }
+        channel.flush()?;
+        let bytes = read_compressed_points(channel, inputs.len())?;
+        let pts = decompress_points(&bytes, self.nthreads)?;
+        let mut rs = blinds.iter().map(|(_, r)| *r).collect::<Vec<Scalar>>();
+        Scalar::batch_invert(&mut rs);
+        let unblind = inputs
+            .iter()
+            .zip(pts.into_iter().zip(rs))
+            .map(|(x, (pt, r))| (*x, pt, r))
+            .collect::<Vec<(Block, RistrettoPoint, Scalar)>>();
+        Ok(utils::parallel_map(
+            &unblind,
+            self.nthreads,
+            |(x, pt, r)| hash_to_output(x, &(pt * r)),
+        ))
+    }
+}
+
+impl std::fmt::Display for Receiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "DH-OPRF Receiver")
+    }
+}
+
+impl SemiHonest for Sender {}
+impl SemiHonest for Receiver {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scuttlebutt::{AesRng, Channel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    fn rand_block_vec(size: usize) -> Vec<Block> {
+        (0..size).map(|_| rand::random::<Block>()).collect()
+    }
+
+    fn _test_oprf(n: usize, key: Scalar) {
+        let selections = rand_block_vec(n);
+        let selections_ = selections.clone();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut oprf = Sender::from_key(key);
+            let seeds = oprf.send(&mut channel, n, &mut rng).unwrap();
+            let results = selections_
+                .iter()
+                .zip(seeds)
+                .map(|(inp, seed)| oprf.compute(seed, *inp))
+                .collect::<Vec<Block512>>();
+            assert_eq!(results, oprf.evaluate_many(&selections_));
+            results
+        });
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut oprf = Receiver::init(&mut channel, &mut rng).unwrap();
+        let outputs = oprf.receive(&mut channel, &selections, &mut rng).unwrap();
+        let results = handle.join().unwrap();
+        assert_eq!(results, outputs);
+    }
+
+    #[test]
+    fn test_oprf() {
+        let mut rng = AesRng::new();
+        let key = Scalar::random(&mut rng);
+        _test_oprf(1, key);
+        _test_oprf(11, key);
+        _test_oprf(1 << 10, key);
+    }
+
+    #[test]
+    fn test_key_reuse() {
+        let mut rng = AesRng::new();
+        let x = rng.gen::<Block>();
+        let sender = Sender::from_key(Scalar::random(&mut rng));
+        let sender_ = Sender::from_key(*sender.key());
+        assert_eq!(sender.evaluate(x), sender_.evaluate(x));
+        let other = Sender::from_key(Scalar::random(&mut rng));
+        assert_ne!(sender.evaluate(x), other.evaluate(x));
+    }
+}
@@ -55,7 +55,7 @@ impl<OT: OtReceiver<Msg = Block> + SemiHonest> OprfSender for Sender<OT> {
         rng.fill_bytes(&mut s_);
         let s = utils::u8vec_to_boolvec(&s_);
         let seeds = (0..4).map(|_| rng.gen()).collect::<Vec<Block>>();
-        let keys = cointoss::send(channel, &seeds)?;
+        let keys = cointoss::send(channel, &seeds, rng)?;
         let code = PseudorandomCode::new(keys[0], keys[1], keys[2], keys[3]);
         let ks = ot.receive(channel, &s, rng)?;
         let rngs = ks
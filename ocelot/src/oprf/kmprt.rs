@@ -6,34 +6,33 @@
 
 //! Implementation of the hash-based multi-use OPPRF of Kolesnikov, Matania,
 //! Pinkas, Rosulek, and Trieu (cf. <https://eprint.iacr.org/2017/799>).
+//!
+//! The table-based and polynomial-based OPPRFs from the same paper are
+//! available in the `table` and `poly` submodules. All three implement the
+//! `ProgrammableSender` and `ProgrammableReceiver` traits.
 
 use crate::{
     errors::Error,
-    oprf::{Receiver as OprfReceiver, Sender as OprfSender},
+    oprf::{
+        ProgrammableOprf,
+        ProgrammableReceiver,
+        ProgrammableSender,
+        Receiver as OprfReceiver,
+        Sender as OprfSender,
+    },
 };
 use rand::{CryptoRng, Rng};
-use scuttlebutt::{AbstractChannel, Aes128, Block, Block512, SemiHonest};
-use std::collections::HashSet;
-
-mod cuckoo;
-
-impl From<cuckoo::Error> for Error {
-    fn from(e: cuckoo::Error) -> Error {
-        Error::Other(format!("Cuckoo hash error: {}", e))
-    }
-}
-
-// Number of times to iterate when creating the sender's hash table.
-const N_TABLE_LOOPS: usize = 128;
+use scuttlebutt::{
+    cuckoo::{self, AesHasher, CuckooHash, CuckooItem, SimpleHash},
+    AbstractChannel,
+    Aes128,
+    Block,
+    Block512,
+    SemiHonest,
+};
 
-// Hash `x` with key `k`, producing a result in the range `[0..range-1]`. We use
-// the Davies-Meyer-esque single-block-length compression function
-// under-the-hood, and we pre-key `k`.
-#[inline(always)]
-fn hash_input_keyed(k: &Aes128, x: Block, range: usize) -> usize {
-    let h = k.encrypt(x) ^ x;
-    (u128::from(h) % (range as u128)) as usize
-}
+pub mod poly;
+pub mod table;
 
 // Hash `y` with key `k`, producing a result in the range `[0..range-1]`.
 fn hash_output(k: Block, y: Block512, range: usize) -> usize {
@@ -101,6 +100,32 @@ impl Parameters {
     }
 }
 
+// Cuckoo hash `inputs` into `m₁ + m₂` bins using `hashkeys`. Each input is
+// placed in the first `m₁` bins using the first `h₁` hash functions, and those
+// that do not fit are placed in the last `m₂` bins using the other `h₂`.
+fn cuckoo_hash(
+    inputs: &[Block],
+    hashkeys: &[Block],
+    params: &Parameters,
+) -> Result<Vec<Option<CuckooItem>>, cuckoo::Error> {
+    let (keys1, keys2) = hashkeys.split_at(params.h1);
+    let table1 = CuckooHash::build(
+        inputs,
+        cuckoo::Parameters::new(params.m1, params.h1, inputs.len()),
+        AesHasher::new(keys1),
+    )?;
+    let mut table2 = CuckooHash::new(
+        cuckoo::Parameters::new(params.m2, params.h2, 0),
+        AesHasher::new(keys2),
+    );
+    for item in table1.stash.iter() {
+        table2.insert(item.entry, item.input_index)?;
+    }
+    let mut items = table1.items;
+    items.extend(table2.items);
+    Ok(items)
+}
+
 /// KMPRT hashing-based OPPRF sender.
 ///
 /// This implements the hashing-based OPPRF sender in Figure 7 of the paper. It
@@ -110,11 +135,15 @@ pub struct Sender<OPRF> {
     oprf: OPRF,
 }
 
+impl<OPRF> ProgrammableOprf for Sender<OPRF> {
+    type Input = Block;
+    type Output = Block512;
+}
+
 impl<OPRF: OprfSender<Seed = Block512, Input = Block, Output = Block512> + SemiHonest>
-    Sender<OPRF>
+    ProgrammableSender for Sender<OPRF>
 {
-    /// Initialize the OPPRF sender.
-    pub fn init<C, RNG>(channel: &mut C, rng: &mut RNG) -> Result<Self, Error>
+    fn init<C, RNG>(channel: &mut C, rng: &mut RNG) -> Result<Self, Error>
     where
         C: AbstractChannel,
         RNG: CryptoRng + Rng,
@@ -123,9 +152,7 @@ impl<OPRF: OprfSender<Seed = Block512, Input = Block, Output = Block512> + SemiH
         Ok(Self { oprf })
     }
 
-    /// Run the OPPRF for `ninputs` inputs with the pairs given in
-    /// `points` as the programmed points.
-    pub fn send<C, RNG>(
+    fn send<C, RNG>(
         &mut self,
         channel: &mut C,
         points: &[(Block, Block512)],
@@ -140,42 +167,25 @@ impl<OPRF: OprfSender<Seed = Block512, Input = Block, Output = Block512> + SemiH
         // Receive `hashkeys` from the receiver. These are used to fill `bins` below.
         let mut hashkeys = Vec::with_capacity(params.h1 + params.h2);
         for _ in 0..params.h1 + params.h2 {
-            let h = channel.read_block()?;
-            let aes = Aes128::new(h);
-            hashkeys.push(aes);
-        }
-        // `bins` contains `m = m₁ + m₂` vectors. The first `m₁` vectors are each of
-        // size `β₁`, and the second `m₂` vectors are each of size `β₂`.
-        let mut bins = Vec::with_capacity(params.m1 + params.m2);
-        for _ in 0..params.m1 {
-            bins.push(Vec::with_capacity(params.beta1));
-        }
-        for _ in params.m1..params.m1 + params.m2 {
-            bins.push(Vec::with_capacity(params.beta2));
-        }
-        // Place each point in the hash table, once for each hash function.
-        for (x, y) in points.iter() {
-            let mut hs = Vec::with_capacity(params.h1);
-            for key in hashkeys[0..params.h1].iter() {
-                let h = hash_input_keyed(key, *x, params.m1);
-                // Only add the point if it doesn't already exist in the `h`th
-                // bin.
-                if hs.iter().find(|&&h_| h_ == h).is_none() {
-                    bins[h].push((*x, *y));
-                    hs.push(h);
-                }
-            }
-            let mut hs = Vec::with_capacity(params.h1);
-            for key in hashkeys[params.h1..params.h1 + params.h2].iter() {
-                let h = hash_input_keyed(key, *x, params.m2);
-                // Only add the point if it doesn't already exist in the `h`th
-                // bin.
-                if hs.iter().find(|&&h_| h_ == h).is_none() {
-                    bins[params.m1 + h].push((*x, *y));
-                    hs.push(h);
-                }
-            }
+            hashkeys.push(channel.read_block()?);
         }
+        let (keys1, keys2) = hashkeys.split_at(params.h1);
+        // Place each point in the `m = m₁ + m₂` bins, once for each hash
+        // function. The first `m₁` bins each hold at most `β₁` points, and the
+        // last `m₂` bins at most `β₂` points.
+        let xs = points.iter().map(|(x, _)| *x).collect::<Vec<Block>>();
+        let table1 = SimpleHash::build(&xs, params.m1, params.h1, &AesHasher::new(keys1));
+        let table2 = SimpleHash::build(&xs, params.m2, params.h2, &AesHasher::new(keys2));
+        let bins = table1
+            .bins
+            .into_iter()
+            .chain(table2.bins)
+            .map(|bin| {
+                bin.into_iter()
+                    .map(|item| points[item.input_index])
+                    .collect::<Vec<(Block, Block512)>>()
+            })
+            .collect::<Vec<_>>();
         let seeds = self.oprf.send(channel, bins.len(), rng)?;
         // Run the one-time OPPRF on each bin.
         for (j, (bin, seed)) in bins.into_iter().zip(seeds.into_iter()).enumerate() {
@@ -185,110 +195,11 @@ impl<OPRF: OprfSender<Seed = Block512, Input = Block, Output = Block512> + SemiH
             } else {
                 params.beta2
             };
-
-            self.process_oprf_output(channel, seed, bin, beta, rng)?;
-        }
-        Ok(())
-    }
-
-    fn process_oprf_output<C, RNG>(
-        &mut self,
-        channel: &mut C,
-        seed: Block512,
-        points: Vec<(Block, Block512)>,
-        npoints: usize,
-        rng: &mut RNG,
-    ) -> Result<(), Error>
-    where
-        C: AbstractChannel,
-        RNG: CryptoRng + Rng,
-    {
-        // Check that all input points are unique.
-        debug_assert_eq!(
-            {
-                let mut points = points.iter().map(|(x, _)| *x).collect::<Vec<Block>>();
-                points.sort();
-                points.dedup();
-                points.len()
-            },
-            points.len()
-        );
-        assert!(points.len() <= npoints);
-        let mut v = rng.gen::<Block>();
-        let mut aes = Aes128::new(v);
-        let mut map = HashSet::with_capacity(points.len());
-        // Store compute `y`s and `h`s for later use.
-        let mut ys = vec![Block512::default(); points.len()];
-        let mut hs = vec![usize::default(); points.len()];
-        // Guess a size for `table` using `offset`, and then try to fill
-        // `map` with points hashed into the space `[0..m-1]`. If this fails
-        // (because `m` is too small), we change `offset` and try again,
-        // looping until we choose an appropriate `m` such that we can find
-        // a `v` such that every entry in `map` is distinct.
-        //
-        // Note that choosing `m` correctly quickly matters **a lot** to the
-        // overall running time.
-        let mut m = Self::table_size(npoints);
-        let increment = m;
-        loop {
-            // Sample `v` until all values in `map` are distinct.
-            for _ in 0..N_TABLE_LOOPS {
-                for (i, (x, _)) in points.iter().enumerate() {
-                    ys[i] = self.oprf.compute(seed, *x);
-                    hs[i] = hash_output_keyed(&aes, ys[i], m);
-                    if !map.insert(hs[i]) {
-                        break;
-                    }
-                }
-                if map.len() == points.len() {
-                    break;
-                }
-                // Try again.
-                v = rng.gen::<Block>();
-                aes = Aes128::new(v);
-                map.clear();
-            }
-            if map.len() == points.len() {
-                // Success! Send `m` to the receiver and exit the loop.
-                channel.write_usize(m)?;
-                break;
-            }
-            // Failure :-(. Increment `offset` and try again.
-            m += increment;
-        }
-        let mut table = vec![Block512::default(); m];
-        // Place points in table based on the hash of their OPRF output.
-        for (h, (y_, (_, y))) in hs.into_iter().zip(ys.into_iter().zip(points.into_iter())) {
-            table[h] = y ^ y_;
-        }
-        // Fill rest of table with random elements.
-        for entry in table.iter_mut() {
-            if *entry == Block512::default() {
-                *entry = rng.gen::<Block512>();
-            }
-        }
-        // Send `v` and `table` to the receiver.
-        channel.write_block(&v)?;
-        for entry in table.iter() {
-            channel.write_block512(entry)?;
+            table::send_table(&self.oprf, channel, seed, &bin, beta, rng)?;
         }
         channel.flush()?;
         Ok(())
     }
-
-    // Compute the table size for the OPPRF.
-    #[inline(always)]
-    fn table_size(npoints: usize) -> usize {
-        // These are over-approximations, but appear to lead to better running
-        // times (at the expense of more communication).
-        if npoints <= 32 {
-            32
-        } else if npoints <= 64 {
-            256
-        } else {
-            (((npoints + 2) as f32).log2().ceil()).exp2() as usize
-        }
-    }
 }
 
 /// KMPRT oblivious programmable PRF receiver.
@@ -300,11 +211,15 @@ pub struct Receiver<OPRF: OprfReceiver + SemiHonest> {
     oprf: OPRF,
 }
 
+impl<OPRF: OprfReceiver + SemiHonest> ProgrammableOprf for Receiver<OPRF> {
+    type Input = Block;
+    type Output = Block512;
+}
+
 impl<OPRF: OprfReceiver<Seed = Block512, Input = Block, Output = Block512> + SemiHonest>
-    Receiver<OPRF>
+    ProgrammableReceiver for Receiver<OPRF>
 {
-    /// Initialize the OPPRF receiver.
-    pub fn init<C, RNG>(channel: &mut C, rng: &mut RNG) -> Result<Self, Error>
+    fn init<C, RNG>(channel: &mut C, rng: &mut RNG) -> Result<Self, Error>
     where
         C: AbstractChannel,
         RNG: CryptoRng + Rng,
@@ -313,8 +228,7 @@ impl<OPRF: OprfReceiver<Seed = Block512, Input = Block, Output = Block512> + Sem
         Ok(Self { oprf })
     }
 
-    /// Run the OPPRF on inputs provided by the `inputs` slice.
-    pub fn receive<C, RNG>(
+    fn receive<C, RNG>(
         &mut self,
         channel: &mut C,
         inputs: &[Block],
@@ -325,7 +239,7 @@ impl<OPRF: OprfReceiver<Seed = Block512, Input = Block, Output = Block512> + Sem
         RNG: CryptoRng + Rng,
     {
         let params = Parameters::new(inputs.len())?;
-        let table;
+        let items;
         // Generate random values to be used for the hash functions. We loop,
         // trying random `hashkeys` each time until we can successfully build
         // the cuckoo hash. Once successful, we send `hashkeys` to the sender so
@@ -335,13 +249,8 @@ impl<OPRF: OprfReceiver<Seed = Block512, Input = Block, Output = Block512> + Sem
                 .map(|_| rng.gen())
                 .collect::<Vec<Block>>();
             // Build a cuckoo hash table using `hashkeys`.
-            if let Ok(table_) = cuckoo::CuckooHash::build(
-                inputs,
-                &hashkeys,
-                (params.m1, params.m2),
-                (params.h1, params.h2),
-            ) {
-                table = table_;
+            if let Ok(items_) = cuckoo_hash(inputs, &hashkeys, &params) {
+                items = items_;
                 // Send `hashkeys` to the sender.
                 for h in hashkeys.into_iter() {
                     channel.write_block(&h)?;
@@ -354,8 +263,7 @@ impl<OPRF: OprfReceiver<Seed = Block512, Input = Block, Output = Block512> + Sem
             .map(|_| Default::default())
             .collect::<Vec<Block512>>();
 
-        let items = table
-            .items
+        let entries = items
             .iter()
             .map(|item| {
                 if let Some(item) = item {
@@ -365,20 +273,12 @@ impl<OPRF: OprfReceiver<Seed = Block512, Input = Block, Output = Block512> + Sem
                 }
             })
             .collect::<Vec<Block>>();
-        let oprf_outputs = self.oprf.receive(channel, &items, rng)?;
+        let oprf_outputs = self.oprf.receive(channel, &entries, rng)?;
 
-        let zero = Block512::default();
-        for (item, output) in table.items.into_iter().zip(oprf_outputs.into_iter()) {
-            let m = channel.read_usize()?;
-            let v = channel.read_block()?;
-            let h = hash_output(v, output, m);
-            let mut output = output;
-            for i in 0..m {
-                let entry = channel.read_block512()?;
-                output ^= if i == h { entry } else { zero };
-            }
+        for (item, output) in items.into_iter().zip(oprf_outputs) {
+            let output = table::receive_table(channel, output)?;
             if let Some(item) = item {
-                outputs[item.index] = output;
+                outputs[item.input_index] = output;
             }
         }
         Ok(outputs)
@@ -482,13 +382,4 @@ mod benchmarks {
         let range = 15;
         b.iter(|| super::hash_output_keyed(&aes, x, range));
     }
-
-    #[bench]
-    fn bench_hash_input_keyed(b: &mut Bencher) {
-        let k = black_box(rand::random::<Block>());
-        let x = black_box(rand::random::<Block>());
-        let aes = Aes128::new(k);
-        let range = 15;
-        b.iter(|| super::hash_input_keyed(&aes, x, range));
-    }
 }
@@ -0,0 +1,228 @@
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of the polynomial-based OPPRF of Kolesnikov, Matania,
+//! Pinkas, Rosulek, and Trieu (cf. <https://eprint.iacr.org/2017/799>, §5).
+//!
+//! Each receiver input is evaluated using a fresh OPRF instance with seed `k`,
+//! for which the sender sends as hint the polynomial `P` over `GF(2^128)`
+//! interpolating the points `(x, y ⊕ F(k, x))`, and the receiver outputs
+//! `P(x) ⊕ F(k, x)`. The output is 512 bits long, so `P` is really four
+//! polynomials, one per 128-bit block. The hint for each instance consists of
+//! exactly one coefficient per point, which makes it smaller than the table of
+//! the table-based OPPRF, at the cost of a quadratic-time interpolation.
+
+use crate::{
+    errors::Error,
+    oprf::{
+        ProgrammableOprf,
+        ProgrammableReceiver,
+        ProgrammableSender,
+        Receiver as OprfReceiver,
+        Sender as OprfSender,
+    },
+};
+use rand::{CryptoRng, Rng};
+use scuttlebutt::{
+    field::{evaluate, lagrange_basis, Gf128},
+    AbstractChannel,
+    Block,
+    Block512,
+    SemiHonest,
+};
+
+// The maximum number of coefficients to allocate space for upfront, as the
+// number of points is sent by the sender and not trusted. Larger polynomials
+// grow as their coefficients are read.
+const MAX_PREALLOC: usize = 1 << 16;
+
+/// KMPRT polynomial-based OPPRF sender.
+pub struct Sender<OPRF> {
+    oprf: OPRF,
+}
+
+impl<OPRF> ProgrammableOprf for Sender<OPRF> {
+    type Input = Block;
+    type Output = Block512;
+}
+
+impl<OPRF: OprfSender<Seed = Block512, Input = Block, Output = Block512> + SemiHonest>
+    ProgrammableSender for Sender<OPRF>
+{
+    fn init<C, RNG>(channel: &mut C, rng: &mut RNG) -> Result<Self, Error>
+    where
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        let oprf = OPRF::init(channel, rng)?;
+        Ok(Self { oprf })
+    }
+
+    fn send<C, RNG>(
+        &mut self,
+        channel: &mut C,
+        points: &[(Block, Block512)],
+        ninputs: usize,
+        rng: &mut RNG,
+    ) -> Result<(), Error>
+    where
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        // The interpolation points are the same for every instance, so only
+        // compute the basis polynomials once.
+        let xs = points
+            .iter()
+            .map(|(x, _)| Gf128::from(*x))
+            .collect::<Vec<Gf128>>();
+        let basis = lagrange_basis(&xs).ok_or_else(|| {
+            Error::Other("polynomial OPPRF requires distinct points".to_string())
+        })?;
+        let seeds = self.oprf.send(channel, ninputs, rng)?;
+        channel.write_usize(points.len())?;
+        for seed in seeds.into_iter() {
+            let mut coeffs = vec![[Gf128::default(); 4]; points.len()];
+            for ((x, y), ls) in points.iter().zip(basis.iter()) {
+                let v: [Block; 4] = (*y ^ self.oprf.compute(seed, *x)).into();
+                for (c, l) in coeffs.iter_mut().zip(ls.iter()) {
+                    for (c, v) in c.iter_mut().zip(v.iter()) {
+                        *c += *l * Gf128::from(*v);
+                    }
+                }
+            }
+            for c in coeffs.into_iter() {
+                let c = [
+                    Block::from(c[0]),
+                    Block::from(c[1]),
+                    Block::from(c[2]),
+                    Block::from(c[3]),
+                ];
+                channel.write_block512(&Block512::from(c))?;
+            }
+        }
+        channel.flush()?;
+        Ok(())
+    }
+}
+
+/// KMPRT polynomial-based OPPRF receiver.
+pub struct Receiver<OPRF> {
+    oprf: OPRF,
+}
+
+impl<OPRF> ProgrammableOprf for Receiver<OPRF> {
+    type Input = Block;
+    type Output = Block512;
+}
+
+impl<OPRF: OprfReceiver<Seed = Block512, Input = Block, Output = Block512> + SemiHonest>
+    ProgrammableReceiver for Receiver<OPRF>
+{
+    fn init<C, RNG>(channel: &mut C, rng: &mut RNG) -> Result<Self, Error>
+    where
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        let oprf = OPRF::init(channel, rng)?;
+        Ok(Self { oprf })
+    }
+
+    fn receive<C, RNG>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[Block],
+        rng: &mut RNG,
+    ) -> Result<Vec<Block512>, Error>
+    where
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        let outputs = self.oprf.receive(channel, inputs, rng)?;
+        let npoints = channel.read_usize()?;
+        let mut coeffs = (0..4)
+            .map(|_| Vec::with_capacity(npoints.min(MAX_PREALLOC)))
+            .collect::<Vec<Vec<Gf128>>>();
+        inputs
+            .iter()
+            .zip(outputs)
+            .map(|(x, output)| {
+                coeffs.iter_mut().for_each(Vec::clear);
+                for _ in 0..npoints {
+                    let c: [Block; 4] = channel.read_block512()?.into();
+                    for (coeffs, c) in coeffs.iter_mut().zip(c.iter()) {
+                        coeffs.push(Gf128::from(*c));
+                    }
+                }
+                let mut ys: [Block; 4] = output.into();
+                for (y, coeffs) in ys.iter_mut().zip(coeffs.iter()) {
+                    *y ^= Block::from(evaluate(coeffs, Gf128::from(*x)));
+                }
+                Ok(Block512::from(ys))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oprf::{KmprtPolyReceiver, KmprtPolySender};
+    use scuttlebutt::{AesRng, Channel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    fn _test_opprf(ninputs: usize, npoints: usize) {
+        let mut rng = AesRng::new();
+        let points = (0..npoints)
+            .map(|_| (rng.gen::<Block>(), rng.gen()))
+            .collect::<Vec<(Block, Block512)>>();
+        // Query every other point, interleaved with inputs that are not
+        // programmed.
+        let inputs = (0..ninputs)
+            .map(|i| {
+                if i % 2 == 0 && i / 2 < npoints {
+                    points[i / 2].0
+                } else {
+                    rng.gen::<Block>()
+                }
+            })
+            .collect::<Vec<Block>>();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let points_ = points.clone();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut oprf = KmprtPolySender::init(&mut channel, &mut rng).unwrap();
+            oprf.send(&mut channel, &points_, ninputs, &mut rng).unwrap();
+        });
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut oprf = KmprtPolyReceiver::init(&mut channel, &mut rng).unwrap();
+        let outputs = oprf.receive(&mut channel, &inputs, &mut rng).unwrap();
+        handle.join().unwrap();
+        for (i, output) in outputs.iter().enumerate() {
+            if i % 2 == 0 && i / 2 < npoints {
+                assert_eq!(*output, points[i / 2].1);
+            } else {
+                assert!(points.iter().all(|(_, y)| y != output));
+            }
+        }
+    }
+
+    #[test]
+    fn test_opprf() {
+        _test_opprf(1, 1);
+        _test_opprf(8, 3);
+        _test_opprf(16, 0);
+        _test_opprf(32, 100);
+    }
+}
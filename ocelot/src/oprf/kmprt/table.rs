@@ -0,0 +1,295 @@
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of the table-based OPPRF of Kolesnikov, Matania, Pinkas,
+//! Rosulek, and Trieu (cf. <https://eprint.iacr.org/2017/799>, Figure 6).
+//!
+//! Each receiver input is evaluated using a fresh OPRF instance, for which the
+//! sender sends a hint table programmed with *all* of its points. The hint for
+//! each instance has size roughly the smallest power of two larger than the
+//! number of points, so this variant is best suited to small point sets. The
+//! hashing-based OPPRF uses it on each of its bins.
+
+use super::{hash_output, hash_output_keyed};
+use crate::{
+    errors::Error,
+    oprf::{
+        ProgrammableOprf,
+        ProgrammableReceiver,
+        ProgrammableSender,
+        Receiver as OprfReceiver,
+        Sender as OprfSender,
+    },
+};
+use rand::{CryptoRng, Rng};
+use scuttlebutt::{AbstractChannel, Aes128, Block, Block512, SemiHonest};
+use std::collections::HashSet;
+
+// Number of times to iterate when creating the sender's hash table.
+const N_TABLE_LOOPS: usize = 128;
+
+// Compute the table size for the OPPRF.
+#[inline(always)]
+fn table_size(npoints: usize) -> usize {
+    // These are over-approximations, but appear to lead to better running
+    // times (at the expense of more communication).
+    if npoints <= 32 {
+        32
+    } else if npoints <= 64 {
+        256
+    } else {
+        (((npoints + 2) as f32).log2().ceil()).exp2() as usize
+    }
+}
+
+// Run the sender's side of a single one-time OPPRF instance, using OPRF seed
+// `seed`, programmed on the pairs in `points`. `npoints` is an upper bound on
+// the number of points, and determines the initial table size.
+pub(super) fn send_table<OPRF, C, RNG>(
+    oprf: &OPRF,
+    channel: &mut C,
+    seed: Block512,
+    points: &[(Block, Block512)],
+    npoints: usize,
+    rng: &mut RNG,
+) -> Result<(), Error>
+where
+    OPRF: OprfSender<Seed = Block512, Input = Block, Output = Block512>,
+    C: AbstractChannel,
+    RNG: CryptoRng + Rng,
+{
+    // Check that all input points are unique.
+    debug_assert_eq!(
+        {
+            let mut points = points.iter().map(|(x, _)| *x).collect::<Vec<Block>>();
+            points.sort();
+            points.dedup();
+            points.len()
+        },
+        points.len()
+    );
+    assert!(points.len() <= npoints);
+    let mut v = rng.gen::<Block>();
+    let mut aes = Aes128::new(v);
+    let mut map = HashSet::with_capacity(points.len());
+    // Store compute `y`s and `h`s for later use.
+    let mut ys = vec![Block512::default(); points.len()];
+    let mut hs = vec![usize::default(); points.len()];
+    // Guess a size for `table` using `offset`, and then try to fill
+    // `map` with points hashed into the space `[0..m-1]`. If this fails
+    // (because `m` is too small), we change `offset` and try again,
+    // looping until we choose an appropriate `m` such that we can find
+    // a `v` such that every entry in `map` is distinct.
+    //
+    // Note that choosing `m` correctly quickly matters **a lot** to the
+    // overall running time.
+    let mut m = table_size(npoints);
+    let increment = m;
+    loop {
+        // Sample `v` until all values in `map` are distinct.
+        for _ in 0..N_TABLE_LOOPS {
+            for (i, (x, _)) in points.iter().enumerate() {
+                ys[i] = oprf.compute(seed, *x);
+                hs[i] = hash_output_keyed(&aes, ys[i], m);
+                if !map.insert(hs[i]) {
+                    break;
+                }
+            }
+            if map.len() == points.len() {
+                break;
+            }
+            // Try again.
+            v = rng.gen::<Block>();
+            aes = Aes128::new(v);
+            map.clear();
+        }
+        if map.len() == points.len() {
+            // Success! Send `m` to the receiver and exit the loop.
+            channel.write_usize(m)?;
+            break;
+        }
+        // Failure :-(. Increment `offset` and try again.
+        m += increment;
+    }
+    let mut table = vec![Block512::default(); m];
+    // Place points in table based on the hash of their OPRF output.
+    for (h, (y_, (_, y))) in hs.into_iter().zip(ys.into_iter().zip(points.iter())) {
+        table[h] = *y ^ y_;
+    }
+    // Fill rest of table with random elements.
+    for entry in table.iter_mut() {
+        if *entry == Block512::default() {
+            *entry = rng.gen::<Block512>();
+        }
+    }
+    // Send `v` and `table` to the receiver.
+    channel.write_block(&v)?;
+    for entry in table.iter() {
+        channel.write_block512(entry)?;
+    }
+    Ok(())
+}
+
+// Run the receiver's side of a single one-time OPPRF instance, given the
+// OPRF output `output` for the receiver's input.
+pub(super) fn receive_table<C: AbstractChannel>(
+    channel: &mut C,
+    output: Block512,
+) -> Result<Block512, Error> {
+    let m = channel.read_usize()?;
+    let v = channel.read_block()?;
+    let h = hash_output(v, output, m);
+    let zero = Block512::default();
+    let mut output = output;
+    for i in 0..m {
+        let entry = channel.read_block512()?;
+        output ^= if i == h { entry } else { zero };
+    }
+    Ok(output)
+}
+
+/// KMPRT table-based OPPRF sender.
+pub struct Sender<OPRF> {
+    oprf: OPRF,
+}
+
+impl<OPRF> ProgrammableOprf for Sender<OPRF> {
+    type Input = Block;
+    type Output = Block512;
+}
+
+impl<OPRF: OprfSender<Seed = Block512, Input = Block, Output = Block512> + SemiHonest>
+    ProgrammableSender for Sender<OPRF>
+{
+    fn init<C, RNG>(channel: &mut C, rng: &mut RNG) -> Result<Self, Error>
+    where
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        let oprf = OPRF::init(channel, rng)?;
+        Ok(Self { oprf })
+    }
+
+    fn send<C, RNG>(
+        &mut self,
+        channel: &mut C,
+        points: &[(Block, Block512)],
+        ninputs: usize,
+        rng: &mut RNG,
+    ) -> Result<(), Error>
+    where
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        let seeds = self.oprf.send(channel, ninputs, rng)?;
+        for seed in seeds.into_iter() {
+            send_table(&self.oprf, channel, seed, points, points.len(), rng)?;
+        }
+        channel.flush()?;
+        Ok(())
+    }
+}
+
+/// KMPRT table-based OPPRF receiver.
+pub struct Receiver<OPRF> {
+    oprf: OPRF,
+}
+
+impl<OPRF> ProgrammableOprf for Receiver<OPRF> {
+    type Input = Block;
+    type Output = Block512;
+}
+
+impl<OPRF: OprfReceiver<Seed = Block512, Input = Block, Output = Block512> + SemiHonest>
+    ProgrammableReceiver for Receiver<OPRF>
+{
+    fn init<C, RNG>(channel: &mut C, rng: &mut RNG) -> Result<Self, Error>
+    where
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        let oprf = OPRF::init(channel, rng)?;
+        Ok(Self { oprf })
+    }
+
+    fn receive<C, RNG>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[Block],
+        rng: &mut RNG,
+    ) -> Result<Vec<Block512>, Error>
+    where
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        let outputs = self.oprf.receive(channel, inputs, rng)?;
+        outputs
+            .into_iter()
+            .map(|output| receive_table(channel, output))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oprf::{KmprtTableReceiver, KmprtTableSender};
+    use scuttlebutt::{AesRng, Channel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    fn _test_opprf(ninputs: usize, npoints: usize) {
+        let mut rng = AesRng::new();
+        let points = (0..npoints)
+            .map(|_| (rng.gen::<Block>(), rng.gen()))
+            .collect::<Vec<(Block, Block512)>>();
+        // Query every other point, interleaved with inputs that are not
+        // programmed.
+        let inputs = (0..ninputs)
+            .map(|i| {
+                if i % 2 == 0 && i / 2 < npoints {
+                    points[i / 2].0
+                } else {
+                    rng.gen::<Block>()
+                }
+            })
+            .collect::<Vec<Block>>();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let points_ = points.clone();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut oprf = KmprtTableSender::init(&mut channel, &mut rng).unwrap();
+            oprf.send(&mut channel, &points_, ninputs, &mut rng).unwrap();
+        });
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut oprf = KmprtTableReceiver::init(&mut channel, &mut rng).unwrap();
+        let outputs = oprf.receive(&mut channel, &inputs, &mut rng).unwrap();
+        handle.join().unwrap();
+        for (i, output) in outputs.iter().enumerate() {
+            if i % 2 == 0 && i / 2 < npoints {
+                assert_eq!(*output, points[i / 2].1);
+            } else {
+                assert!(points.iter().all(|(_, y)| y != output));
+            }
+        }
+    }
+
+    #[test]
+    fn test_opprf() {
+        _test_opprf(1, 1);
+        _test_opprf(8, 3);
+        _test_opprf(16, 0);
+        _test_opprf(32, 100);
+    }
+}
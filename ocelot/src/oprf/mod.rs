@@ -4,16 +4,24 @@
 // Copyright © 2019 Galois, Inc.
 // See LICENSE for licensing information.
 
-//! Oblivious PRF traits + instantiations.
+//! Oblivious (programmable) PRF traits + instantiations.
 
+pub mod dh;
 pub mod kkrt;
 pub mod kmprt;
+pub mod oos;
 mod prc;
+pub mod vole;
 
 use crate::{errors::Error, ot};
 use rand::{CryptoRng, Rng};
-use scuttlebutt::AbstractChannel;
+use scuttlebutt::{AbstractChannel, Block};
+use std::ops::BitXor;
 
+/// Diffie-Hellman oblivious PRF sender.
+pub type DhSender = dh::Sender;
+/// Diffie-Hellman oblivious PRF receiver.
+pub type DhReceiver = dh::Receiver;
 /// KKRT oblivious PRF sender using ALSZ OT extension with Chou-Orlandi as the base OT.
 pub type KkrtSender = kkrt::Sender<ot::AlszReceiver>;
 /// KKRT oblivious PRF receiver using ALSZ OT extension with Chou-Orlandi as the base OT.
@@ -22,6 +30,22 @@ pub type KkrtReceiver = kkrt::Receiver<ot::AlszSender>;
 pub type KmprtSender = kmprt::Sender<KkrtSender>;
 /// KMPRT hash-based OPPRF receiver, using KKRT as the underlying OPRF.
 pub type KmprtReceiver = kmprt::Receiver<KkrtReceiver>;
+/// KMPRT table-based OPPRF sender, using KKRT as the underlying OPRF.
+pub type KmprtTableSender = kmprt::table::Sender<KkrtSender>;
+/// KMPRT table-based OPPRF receiver, using KKRT as the underlying OPRF.
+pub type KmprtTableReceiver = kmprt::table::Receiver<KkrtReceiver>;
+/// KMPRT polynomial-based OPPRF sender, using KKRT as the underlying OPRF.
+pub type KmprtPolySender = kmprt::poly::Sender<KkrtSender>;
+/// KMPRT polynomial-based OPPRF receiver, using KKRT as the underlying OPRF.
+pub type KmprtPolyReceiver = kmprt::poly::Receiver<KkrtReceiver>;
+/// OOS linear OPRF sender using ALSZ OT extension with Chou-Orlandi as the base OT.
+pub type OosSender = oos::Sender<ot::AlszReceiver>;
+/// OOS linear OPRF receiver using ALSZ OT extension with Chou-Orlandi as the base OT.
+pub type OosReceiver = oos::Receiver<ot::AlszSender>;
+/// VOLE-based linear OPRF sender using ALSZ OT extension with Chou-Orlandi as the base OT.
+pub type VoleSender = vole::Sender<ot::AlszReceiver>;
+/// VOLE-based linear OPRF receiver using ALSZ OT extension with Chou-Orlandi as the base OT.
+pub type VoleReceiver = vole::Receiver<ot::AlszSender>;
 
 /// Trait containing the associated types used by an oblivious PRF.
 pub trait ObliviousPrf
@@ -75,3 +99,111 @@ where
         rng: &mut RNG,
     ) -> Result<Vec<Self::Output>, Error>;
 }
+
+/// Trait containing the associated types used by an oblivious programmable PRF.
+pub trait ProgrammableOprf
+where
+    Self: Sized,
+{
+    /// OPPRF input.
+    type Input: Sized;
+    /// OPPRF output.
+    type Output: Sized;
+}
+
+/// Trait for an oblivious programmable PRF sender.
+pub trait ProgrammableSender: ProgrammableOprf
+where
+    Self: Sized,
+{
+    /// Runs any one-time initialization.
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error>;
+    /// Runs the OPPRF for `ninputs` receiver inputs, programmed on the
+    /// input-output pairs in `points`.
+    fn send<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        points: &[(Self::Input, Self::Output)],
+        ninputs: usize,
+        rng: &mut RNG,
+    ) -> Result<(), Error>;
+}
+
+/// Trait for an oblivious programmable PRF receiver.
+pub trait ProgrammableReceiver: ProgrammableOprf
+where
+    Self: Sized,
+{
+    /// Runs any one-time initialization.
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error>;
+    /// Runs the OPPRF on inputs `inputs`, returning the OPPRF outputs. Any
+    /// input matching a programmed point returns the programmed output.
+    fn receive<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[Self::Input],
+        rng: &mut RNG,
+    ) -> Result<Vec<Self::Output>, Error>;
+}
+
+/// Trait containing the associated types used by a linear oblivious PRF.
+///
+/// A linear OPRF gives the receiver an output `t` and the sender an output `q
+/// = t ⊕ Δ(x)` for each receiver input `x`, where `Δ` is a secret linear map
+/// (over `GF(2)`) known only to the sender. Because `Δ` is linear, any linear
+/// combination of outputs is itself an output on the same combination of
+/// inputs, which is what lets PSI protocols based on oblivious key-value
+/// stores evaluate the OPRF on decoded values.
+pub trait LinearOprf
+where
+    Self: Sized,
+{
+    /// OPRF output.
+    type Output: Copy + Default + BitXor<Output = Self::Output> + AsRef<[u8]> + Send + Sync;
+}
+
+/// Trait for a linear oblivious PRF sender.
+pub trait LinearSender: LinearOprf
+where
+    Self: Sized,
+{
+    /// Runs any one-time initialization.
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error>;
+    /// Runs `m` linear OPRF instances as the sender, returning the outputs `q`.
+    fn send<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        m: usize,
+        rng: &mut RNG,
+    ) -> Result<Vec<Self::Output>, Error>;
+    /// Computes the secret linear map `Δ` on input `input`.
+    fn correlate(&self, input: Block) -> Self::Output;
+}
+
+/// Trait for a linear oblivious PRF receiver.
+pub trait LinearReceiver: LinearOprf
+where
+    Self: Sized,
+{
+    /// Runs any one-time initialization.
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error>;
+    /// Runs the linear OPRF on inputs `inputs`, returning the outputs `t`.
+    fn receive<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[Block],
+        rng: &mut RNG,
+    ) -> Result<Vec<Self::Output>, Error>;
+}
@@ -0,0 +1,380 @@
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of a linear oblivious PRF from OT extension with a linear
+//! code, following Orrù, Orsini, and Scholl (cf.
+//! <https://eprint.iacr.org/2016/933>) as used by Pinkas, Rosulek, Trieu, and
+//! Yanai (cf. <https://eprint.iacr.org/2020/193>, §5).
+//!
+//! This is the KKRT OPRF with the pseudorandom code replaced by a linear code
+//! `C : GF(2)^76 → GF(2)^512`, so that the sender learns `q = t ⊕ (C(x) ∧ s)`
+//! for each receiver input `x`. Security requires the minimum distance of `C`
+//! to be at least the computational security parameter, which a random code of
+//! this size does not provide: a random `[512, 128]` code has minimum distance
+//! around 110. Instead, `C` is the extended binary BCH code `[512, 76, 172]`,
+//! as used by Pinkas, Rosulek, Trieu, and Yanai. Only the low 76 bits of each
+//! input are encoded, so inputs must be hashed (as PaXoS-based PSI does), and
+//! two inputs colliding on these bits, which happens with probability `2^-76`
+//! for each pair, are treated as equal.
+
+#![allow(non_upper_case_globals)]
+
+use crate::{
+    errors::Error,
+    oprf::{LinearOprf, LinearReceiver, LinearSender},
+    ot::{Receiver as OtReceiver, Sender as OtSender},
+    utils,
+};
+use rand::{CryptoRng, Rng, RngCore, SeedableRng};
+use scuttlebutt::{
+    utils as scutils,
+    AbstractChannel,
+    AesRng,
+    Block,
+    Block512,
+    SemiHonest,
+};
+use std::{convert::TryInto, marker::PhantomData};
+
+// The number of columns of the OT extension matrix.
+const ncols: usize = 512;
+
+// The number of input bits of the code.
+const CODE_DIM: usize = 76;
+// The designed distance of the (non-extended) BCH code of length `511`.
+const CODE_DISTANCE: usize = 171;
+
+// Multiply `a` and `b` in `GF(2^9) = GF(2)[X]/(X^9 + X^4 + 1)`.
+fn gf512_mul(a: u16, b: u16) -> u16 {
+    let mut out = 0u32;
+    for i in 0..9 {
+        if (b >> i) & 1 == 1 {
+            out ^= u32::from(a) << i;
+        }
+    }
+    for i in (9..17).rev() {
+        if (out >> i) & 1 == 1 {
+            out ^= 0b10_0001_0001 << (i - 9);
+        }
+    }
+    out as u16
+}
+
+// Compute the generator polynomial of the binary BCH code of length `511` and
+// designed distance `CODE_DISTANCE`, as the product of the minimal polynomials
+// of `α^1, ..., α^(CODE_DISTANCE - 1)` for the primitive element `α = X`. The
+// coefficient of `X^i` is at index `i`.
+fn bch_generator() -> Vec<bool> {
+    let mut g = vec![true];
+    let mut done = vec![false; 511];
+    for i in 1..CODE_DISTANCE {
+        if done[i] {
+            continue;
+        }
+        // The minimal polynomial of `α^i` has the conjugates `α^(i ⋅ 2^j)` as
+        // roots.
+        let mut m = vec![1u16];
+        let mut k = i;
+        while !done[k] {
+            done[k] = true;
+            let root = (0..k).fold(1, |acc, _| gf512_mul(acc, 0b10));
+            // Multiply `m` by `X + root`.
+            let mut m_ = vec![0u16; m.len() + 1];
+            for (j, c) in m.iter().enumerate() {
+                m_[j + 1] ^= c;
+                m_[j] ^= gf512_mul(*c, root);
+            }
+            m = m_;
+            k = (2 * k) % 511;
+        }
+        // The minimal polynomial has binary coefficients; multiply it into `g`.
+        debug_assert!(m.iter().all(|c| *c <= 1));
+        let mut g_ = vec![false; g.len() + m.len() - 1];
+        for (j, a) in g.iter().enumerate() {
+            for (l, b) in m.iter().enumerate() {
+                g_[j + l] ^= *a && *b == 1;
+            }
+        }
+        g = g_;
+    }
+    g
+}
+
+// The extended BCH code `[512, 76, 172]`. Encoding looks up each byte of the
+// input in a table containing all combinations of the corresponding eight rows
+// of the generator matrix, whose `i`th row is `X^i ⋅ g(X)` followed by a parity
+// bit.
+struct LinearCode {
+    tables: Vec<[Block512; 256]>,
+}
+
+impl LinearCode {
+    fn new() -> Self {
+        let g = bch_generator();
+        let rows = (0..CODE_DIM)
+            .map(|i| {
+                let mut row = [0u8; 64];
+                for (j, c) in g.iter().enumerate() {
+                    row[(i + j) / 8] |= (*c as u8) << ((i + j) % 8);
+                }
+                let parity = g.iter().filter(|c| **c).count() % 2;
+                row[63] |= (parity as u8) << 7;
+                Block512::from(row)
+            })
+            .collect::<Vec<Block512>>();
+        let tables = rows
+            .chunks(8)
+            .map(|rows| {
+                let mut table = [Block512::default(); 256];
+                for b in 1..256 {
+                    let i = (b as u8).trailing_zeros() as usize;
+                    if i < rows.len() {
+                        table[b] = table[b & (b - 1)] ^ rows[i];
+                    } else {
+                        table[b] = table[b & (b - 1)];
+                    }
+                }
+                table
+            })
+            .collect();
+        Self { tables }
+    }
+
+    // Encode the low `CODE_DIM` bits of `x`.
+    #[inline]
+    fn encode(&self, x: Block) -> Block512 {
+        x.as_ref()
+            .iter()
+            .zip(self.tables.iter())
+            .fold(Block512::default(), |acc, (b, table)| {
+                acc ^ table[*b as usize]
+            })
+    }
+}
+
+/// OOS linear oblivious PRF sender.
+pub struct Sender<OT: OtReceiver + SemiHonest> {
+    _ot: PhantomData<OT>,
+    s: Vec<bool>,
+    s_: Block512,
+    code: LinearCode,
+    rngs: Vec<AesRng>,
+}
+
+impl<OT: OtReceiver<Msg = Block> + SemiHonest> LinearOprf for Sender<OT> {
+    type Output = Block512;
+}
+
+impl<OT: OtReceiver<Msg = Block> + SemiHonest> LinearSender for Sender<OT> {
+    fn init<C, RNG>(channel: &mut C, rng: &mut RNG) -> Result<Self, Error>
+    where
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        let mut ot = OT::init(channel, rng)?;
+        let mut s_ = [0u8; 64];
+        rng.fill_bytes(&mut s_);
+        let s = utils::u8vec_to_boolvec(&s_);
+        let code = LinearCode::new();
+        let ks = ot.receive(channel, &s, rng)?;
+        let rngs = ks
+            .into_iter()
+            .map(AesRng::from_seed)
+            .collect::<Vec<AesRng>>();
+        Ok(Self {
+            _ot: PhantomData::<OT>,
+            s,
+            s_: Block512::from(s_),
+            code,
+            rngs,
+        })
+    }
+
+    fn send<C, RNG>(
+        &mut self,
+        channel: &mut C,
+        m: usize,
+        _: &mut RNG,
+    ) -> Result<Vec<Self::Output>, Error>
+    where
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        // Round up if necessary so that `m mod 16 ≡ 0`.
+        let nrows = m.next_multiple_of(16);
+        let mut u = vec![0u8; nrows / 8];
+        let mut qs = vec![0u8; nrows * ncols / 8];
+        for (j, b) in self.s.iter().enumerate() {
+            let range = j * nrows / 8..(j + 1) * nrows / 8;
+            let q = &mut qs[range];
+            self.rngs[j].fill_bytes(q);
+            channel.read_exact(&mut u)?;
+            if *b {
+                scutils::xor_inplace(q, &u);
+            }
+        }
+        let qs = utils::transpose(&qs, ncols, nrows);
+        Ok(qs
+            .chunks(ncols / 8)
+            .take(m)
+            .map(|q| q.try_into().unwrap())
+            .collect())
+    }
+
+    #[inline]
+    fn correlate(&self, input: Block) -> Self::Output {
+        let mut output = self.code.encode(input);
+        scutils::and_inplace(output.as_mut(), self.s_.as_ref());
+        output
+    }
+}
+
+/// OOS linear oblivious PRF receiver.
+pub struct Receiver<OT: OtSender + SemiHonest> {
+    _ot: PhantomData<OT>,
+    code: LinearCode,
+    rngs: Vec<(AesRng, AesRng)>,
+}
+
+impl<OT: OtSender<Msg = Block> + SemiHonest> LinearOprf for Receiver<OT> {
+    type Output = Block512;
+}
+
+impl<OT: OtSender<Msg = Block> + SemiHonest> LinearReceiver for Receiver<OT> {
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let mut ot = OT::init(channel, rng)?;
+        let code = LinearCode::new();
+        let ks = (0..ncols)
+            .map(|_| (rng.gen::<Block>(), rng.gen::<Block>()))
+            .collect::<Vec<(Block, Block)>>();
+        ot.send(channel, &ks, rng)?;
+        let rngs = ks
+            .into_iter()
+            .map(|(k0, k1)| (AesRng::from_seed(k0), AesRng::from_seed(k1)))
+            .collect::<Vec<(AesRng, AesRng)>>();
+        Ok(Self {
+            _ot: PhantomData::<OT>,
+            code,
+            rngs,
+        })
+    }
+
+    fn receive<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[Block],
+        _: &mut RNG,
+    ) -> Result<Vec<Self::Output>, Error> {
+        let m = inputs.len();
+        // Round up if necessary so that `m mod 16 ≡ 0`.
+        let nrows = m.next_multiple_of(16);
+        let mut cs = vec![0u8; nrows * ncols / 8];
+        for (c, input) in cs.chunks_mut(ncols / 8).zip(inputs.iter()) {
+            c.copy_from_slice(self.code.encode(*input).as_ref());
+        }
+        let cs = utils::transpose(&cs, nrows, ncols);
+        // For each column `j`, send `u_j = G(k_{0,j}) ⊕ G(k_{1,j}) ⊕ c_j`,
+        // where `c_j` is the `j`th column of the encoded inputs, and keep
+        // `t_j = G(k_{0,j})`.
+        let mut ts = vec![0u8; nrows * ncols / 8];
+        let mut u = vec![0u8; nrows / 8];
+        for (j, (t, c)) in ts
+            .chunks_mut(nrows / 8)
+            .zip(cs.chunks(nrows / 8))
+            .enumerate()
+        {
+            self.rngs[j].0.fill_bytes(t);
+            self.rngs[j].1.fill_bytes(&mut u);
+            scutils::xor_inplace(&mut u, t);
+            scutils::xor_inplace(&mut u, c);
+            channel.write_all(&u)?;
+        }
+        channel.flush()?;
+        let ts = utils::transpose(&ts, ncols, nrows);
+        Ok(ts
+            .chunks(ncols / 8)
+            .take(m)
+            .map(|t| t.try_into().unwrap())
+            .collect())
+    }
+}
+
+impl<OT: OtReceiver<Msg = Block> + SemiHonest> SemiHonest for Sender<OT> {}
+impl<OT: OtSender<Msg = Block> + SemiHonest> SemiHonest for Receiver<OT> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oprf::{OosReceiver, OosSender};
+    use scuttlebutt::Channel;
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    fn weight(x: Block512) -> u32 {
+        x.as_ref().iter().map(|b| b.count_ones()).sum()
+    }
+
+    #[test]
+    fn test_linear_code() {
+        let mut rng = AesRng::new();
+        assert_eq!(bch_generator().len(), 512 - CODE_DIM);
+        let code = LinearCode::new();
+        let x = rng.gen::<Block>();
+        let y = rng.gen::<Block>();
+        assert_eq!(code.encode(x ^ y), code.encode(x) ^ code.encode(y));
+        assert_eq!(code.encode(Block::default()), Block512::default());
+        // Only the low `CODE_DIM` bits are encoded.
+        assert_eq!(code.encode(x), code.encode(x ^ Block::from(1u128 << 100)));
+        for _ in 0..1000 {
+            let x = rng.gen::<Block>();
+            if x.as_ref()[..10].iter().any(|b| *b != 0) {
+                assert!(weight(code.encode(x)) >= 172);
+            }
+        }
+        for i in 0..CODE_DIM {
+            assert!(weight(code.encode(Block::from(1u128 << i))) >= 172);
+        }
+    }
+
+    fn test_oprf(n: usize) {
+        let mut rng = AesRng::new();
+        let inputs = (0..n).map(|_| rng.gen()).collect::<Vec<Block>>();
+        let inputs_ = inputs.clone();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut oprf = OosSender::init(&mut channel, &mut rng).unwrap();
+            let qs = oprf.send(&mut channel, n, &mut rng).unwrap();
+            inputs_
+                .iter()
+                .zip(qs)
+                .map(|(x, q)| q ^ oprf.correlate(*x))
+                .collect::<Vec<Block512>>()
+        });
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut oprf = OosReceiver::init(&mut channel, &mut rng).unwrap();
+        let ts = oprf.receive(&mut channel, &inputs, &mut rng).unwrap();
+        let results = handle.join().unwrap();
+        assert_eq!(results, ts);
+    }
+
+    #[test]
+    fn test() {
+        test_oprf(1);
+        test_oprf(11);
+        test_oprf(64);
+    }
+}
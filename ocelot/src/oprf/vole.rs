@@ -0,0 +1,213 @@
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of a linear oblivious PRF from vector oblivious linear
+//! evaluation (VOLE) over `GF(2^128)`, as used by Rindal and Schoppmann (cf.
+//! <https://eprint.iacr.org/2021/266>).
+//!
+//! The sender holds `Δ ∈ GF(2^128)` and learns `q = t + x ⋅ Δ` for each
+//! receiver input `x`. The VOLE itself is computed using the correlated
+//! oblivious product evaluation of Keller, Orsini, and Scholl (cf.
+//! <https://eprint.iacr.org/2016/505>, §4): the sender uses the bits `δ_j` of
+//! `Δ` as the choice bits of 128 base OTs, and the receiver sends `u_j = G(k_{0,
+//! j}) ⊕ G(k_{1,j}) ⊕ x` for each `j`, so that the sender learns `G(k_{0,j}) ⊕
+//! δ_j ⋅ x`. Combining these as `∑ X^j ⋅ (⋅)` gives the VOLE correlation.
+//!
+//! Communication is 128 field elements (2 KiB) per receiver input, which is
+//! roughly thirty times that of the OOS OPRF. At `2^24` inputs this is 32 GiB
+//! of traffic, so this OPRF does not support the `2^24`–`2^28`-element sets
+//! that PaXoS-based PSI targets. It is included as a reference instantiation
+//! of `LinearSender` and `LinearReceiver` until a silent (LPN-based) VOLE is
+//! available; use the OOS OPRF for large sets.
+
+use crate::{
+    errors::Error,
+    oprf::{LinearOprf, LinearReceiver, LinearSender},
+    ot::{Receiver as OtReceiver, Sender as OtSender},
+};
+use rand::{CryptoRng, Rng, SeedableRng};
+use scuttlebutt::{
+    field::Gf128,
+    AbstractChannel,
+    AesRng,
+    Block,
+    SemiHonest,
+};
+use std::marker::PhantomData;
+
+// Multiply `x` by `X` in `GF(2^128)`.
+#[inline]
+fn gf_mulx(x: Block) -> Block {
+    Block::from(Gf128::from(x).mul_x())
+}
+
+/// VOLE-based linear oblivious PRF sender.
+pub struct Sender<OT: OtReceiver + SemiHonest> {
+    _ot: PhantomData<OT>,
+    delta: Block,
+    rngs: Vec<AesRng>,
+}
+
+impl<OT: OtReceiver<Msg = Block> + SemiHonest> LinearOprf for Sender<OT> {
+    type Output = Block;
+}
+
+impl<OT: OtReceiver<Msg = Block> + SemiHonest> LinearSender for Sender<OT> {
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let mut ot = OT::init(channel, rng)?;
+        let delta = rng.gen::<Block>();
+        let bits = (0..128)
+            .map(|j| (u128::from(delta) >> j) & 1 == 1)
+            .collect::<Vec<bool>>();
+        let ks = ot.receive(channel, &bits, rng)?;
+        let rngs = ks.into_iter().map(AesRng::from_seed).collect();
+        Ok(Self {
+            _ot: PhantomData::<OT>,
+            delta,
+            rngs,
+        })
+    }
+
+    fn send<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        m: usize,
+        _: &mut RNG,
+    ) -> Result<Vec<Self::Output>, Error> {
+        let mut qs = vec![Block::default(); m];
+        // Compute `∑ X^j ⋅ q_j` using Horner's rule, starting from the highest
+        // power of `X`.
+        for j in (0..128).rev() {
+            let bit = (u128::from(self.delta) >> j) & 1 == 1;
+            for q in qs.iter_mut() {
+                let mut q_ = self.rngs[j].gen::<Block>();
+                let u = channel.read_block()?;
+                if bit {
+                    q_ ^= u;
+                }
+                *q = gf_mulx(*q) ^ q_;
+            }
+        }
+        Ok(qs)
+    }
+
+    #[inline]
+    fn correlate(&self, input: Block) -> Self::Output {
+        Block::from(Gf128::from(input) * Gf128::from(self.delta))
+    }
+}
+
+/// VOLE-based linear oblivious PRF receiver.
+pub struct Receiver<OT: OtSender + SemiHonest> {
+    _ot: PhantomData<OT>,
+    rngs: Vec<(AesRng, AesRng)>,
+}
+
+impl<OT: OtSender<Msg = Block> + SemiHonest> LinearOprf for Receiver<OT> {
+    type Output = Block;
+}
+
+impl<OT: OtSender<Msg = Block> + SemiHonest> LinearReceiver for Receiver<OT> {
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let mut ot = OT::init(channel, rng)?;
+        let ks = (0..128)
+            .map(|_| (rng.gen::<Block>(), rng.gen::<Block>()))
+            .collect::<Vec<(Block, Block)>>();
+        ot.send(channel, &ks, rng)?;
+        let rngs = ks
+            .into_iter()
+            .map(|(k0, k1)| (AesRng::from_seed(k0), AesRng::from_seed(k1)))
+            .collect();
+        Ok(Self {
+            _ot: PhantomData::<OT>,
+            rngs,
+        })
+    }
+
+    fn receive<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[Block],
+        _: &mut RNG,
+    ) -> Result<Vec<Self::Output>, Error> {
+        let mut ts = vec![Block::default(); inputs.len()];
+        for j in (0..128).rev() {
+            let (rng0, rng1) = &mut self.rngs[j];
+            for (t, x) in ts.iter_mut().zip(inputs.iter()) {
+                let t0 = rng0.gen::<Block>();
+                let t1 = rng1.gen::<Block>();
+                channel.write_block(&(t0 ^ t1 ^ *x))?;
+                *t = gf_mulx(*t) ^ t0;
+            }
+        }
+        channel.flush()?;
+        Ok(ts)
+    }
+}
+
+impl<OT: OtReceiver<Msg = Block> + SemiHonest> SemiHonest for Sender<OT> {}
+impl<OT: OtSender<Msg = Block> + SemiHonest> SemiHonest for Receiver<OT> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oprf::{VoleReceiver, VoleSender};
+    use scuttlebutt::Channel;
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    #[test]
+    fn test_mulx() {
+        let mut rng = AesRng::new();
+        let x = rng.gen::<Block>();
+        assert_eq!(gf_mulx(x), Block::from(Gf128::from(x) * Gf128::from(2u128)));
+    }
+
+    fn test_oprf(n: usize) {
+        let mut rng = AesRng::new();
+        let inputs = (0..n).map(|_| rng.gen()).collect::<Vec<Block>>();
+        let inputs_ = inputs.clone();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut oprf = VoleSender::init(&mut channel, &mut rng).unwrap();
+            // Run twice to check that the OPRF object can be reused.
+            let _ = oprf.send(&mut channel, n, &mut rng).unwrap();
+            let qs = oprf.send(&mut channel, n, &mut rng).unwrap();
+            inputs_
+                .iter()
+                .zip(qs)
+                .map(|(x, q)| q ^ oprf.correlate(*x))
+                .collect::<Vec<Block>>()
+        });
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut oprf = VoleReceiver::init(&mut channel, &mut rng).unwrap();
+        let _ = oprf.receive(&mut channel, &inputs, &mut rng).unwrap();
+        let ts = oprf.receive(&mut channel, &inputs, &mut rng).unwrap();
+        let results = handle.join().unwrap();
+        assert_eq!(results, ts);
+    }
+
+    #[test]
+    fn test() {
+        test_oprf(1);
+        test_oprf(11);
+        test_oprf(64);
+    }
+}
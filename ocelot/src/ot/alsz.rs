@@ -14,6 +14,8 @@ use crate::{
     ot::{
         CorrelatedReceiver,
         CorrelatedSender,
+        ParallelReceiver,
+        ParallelSender,
         RandomReceiver,
         RandomSender,
         Receiver as OtReceiver,
@@ -118,6 +120,20 @@ impl<OT: OtReceiver<Msg = Block> + SemiHonest> OtSender for Sender<OT> {
     }
 }
 
+impl<OT: OtReceiver<Msg = Block> + SemiHonest> ParallelSender for Sender<OT> {
+    fn fork(&mut self) -> Self {
+        // Forking each base OT seeded RNG yields a fresh seed known to both
+        // parties, so the forked sender and receiver stay correlated.
+        Self {
+            _ot: PhantomData::<OT>,
+            hash: AES_HASH,
+            s: self.s.clone(),
+            s_: self.s_,
+            rngs: self.rngs.iter_mut().map(AesRng::fork).collect(),
+        }
+    }
+}
+
 impl<OT: OtReceiver<Msg = Block> + SemiHonest> std::fmt::Display for Sender<OT> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "ALSZ Sender")
@@ -289,6 +305,20 @@ impl<OT: OtSender<Msg = Block> + SemiHonest> RandomReceiver for Receiver<OT> {
     }
 }
 
+impl<OT: OtSender<Msg = Block> + SemiHonest> ParallelReceiver for Receiver<OT> {
+    fn fork(&mut self) -> Self {
+        Self {
+            _ot: PhantomData::<OT>,
+            hash: AES_HASH,
+            rngs: self
+                .rngs
+                .iter_mut()
+                .map(|(rng0, rng1)| (rng0.fork(), rng1.fork()))
+                .collect(),
+        }
+    }
+}
+
 impl<OT: OtSender<Msg = Block> + SemiHonest> std::fmt::Display for Receiver<OT> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "ALSZ Receiver")
@@ -13,6 +13,8 @@ use crate::{
         alsz::{Receiver as AlszReceiver, Sender as AlszSender},
         CorrelatedReceiver,
         CorrelatedSender,
+        ParallelReceiver,
+        ParallelSender,
         RandomReceiver,
         RandomSender,
         Receiver as OtReceiver,
@@ -49,7 +51,7 @@ impl<OT: OtReceiver<Msg = Block> + Malicious> Sender<OT> {
         // Check correlation
         let mut seed = Block::default();
         rng.fill_bytes(&mut seed.as_mut());
-        let seed = cointoss::send(channel, &[seed])?;
+        let seed = cointoss::send(channel, &[seed], rng)?;
         let mut rng = AesRng::from_seed(seed[0]);
         let mut check = (Block::default(), Block::default());
         let mut chi = Block::default();
@@ -159,6 +161,14 @@ impl<OT: OtReceiver<Msg = Block> + Malicious> RandomSender for Sender<OT> {
     }
 }
 
+impl<OT: OtReceiver<Msg = Block> + Malicious> ParallelSender for Sender<OT> {
+    fn fork(&mut self) -> Self {
+        Self {
+            ot: self.ot.fork(),
+        }
+    }
+}
+
 impl<OT: OtReceiver<Msg = Block> + Malicious> std::fmt::Display for Sender<OT> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "KOS Sender")
@@ -286,6 +296,14 @@ impl<OT: OtSender<Msg = Block> + Malicious> RandomReceiver for Receiver<OT> {
     }
 }
 
+impl<OT: OtSender<Msg = Block> + Malicious> ParallelReceiver for Receiver<OT> {
+    fn fork(&mut self) -> Self {
+        Self {
+            ot: self.ot.fork(),
+        }
+    }
+}
+
 impl<OT: OtSender<Msg = Block> + Malicious> std::fmt::Display for Receiver<OT> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "KOS Receiver")
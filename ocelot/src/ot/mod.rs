@@ -14,17 +14,23 @@
 //! * `chou_orlandi`: Chou-Orlandi malicious OT.
 //! * `alsz`: Asharov-Lindell-Schneider-Zohner semi-honest OT extension (+ correlated and random OT).
 //! * `kos`: Keller-Orsini-Scholl malicious OT extension (+ correlated and random OT).
+//! * `pool`: pools of precomputed random OTs, consumed using Beaver's derandomization.
 //!
+//! The OT extension protocols also implement the `ParallelSender` and
+//! `ParallelReceiver` traits, which split a batch of OTs across several
+//! threads, each with its own channel.
 
 pub mod alsz;
 pub mod chou_orlandi;
 pub mod dummy;
 pub mod kos;
 pub mod naor_pinkas;
+pub mod pool;
 
 use crate::errors::Error;
-use rand::{CryptoRng, Rng};
-use scuttlebutt::AbstractChannel;
+use rand::{CryptoRng, Rng, SeedableRng};
+use scuttlebutt::{AbstractChannel, AesRng, Block};
+use std::ops::Range;
 
 /// Instantiation of the Chou-Orlandi OT sender.
 pub type ChouOrlandiSender = chou_orlandi::Sender;
@@ -38,6 +44,10 @@ pub type DummyReceiver = dummy::Receiver;
 pub type NaorPinkasSender = naor_pinkas::Sender;
 /// Instantiation of the Naor-Pinkas OT receiver.
 pub type NaorPinkasReceiver = naor_pinkas::Receiver;
+/// Instantiation of the OT pool sender.
+pub type PoolSender = pool::Sender;
+/// Instantiation of the OT pool receiver.
+pub type PoolReceiver = pool::Receiver;
 /// Instantiation of the ALSZ OT extension sender, using Chou-Orlandi as the base OT.
 pub type AlszSender = alsz::Sender<ChouOrlandiReceiver>;
 /// Instantiation of the ALSZ OT extension receiver, using Chou-Orlandi as the base OT.
@@ -156,6 +166,211 @@ where
     ) -> Result<Vec<Self::Msg>, Error>;
 }
 
+/// Trait for oblivious transfer extension senders that can split a batch of
+/// OTs across several threads, each with its own channel.
+///
+/// Each thread runs on an independent fork of the sender, so the receiver must
+/// use the same number of channels, in the same order.
+pub trait ParallelSender: Sender {
+    /// Fork off an independent sender. The corresponding receiver must be
+    /// forked at the same point in the protocol.
+    fn fork(&mut self) -> Self;
+
+    /// Sends messages, splitting `inputs` into contiguous chunks, one per
+    /// channel in `channels`, which are processed in parallel.
+    fn send_parallel<C, RNG>(
+        &mut self,
+        channels: &mut [C],
+        inputs: &[(Self::Msg, Self::Msg)],
+        rng: &mut RNG,
+    ) -> Result<(), Error>
+    where
+        Self: Send,
+        Self::Msg: Sync,
+        C: AbstractChannel + Send,
+        RNG: CryptoRng + Rng,
+    {
+        let fork = <Self as ParallelSender>::fork;
+        run_parallel(self, fork, channels, inputs.len(), rng, |ot, channel, range, rng| {
+            ot.send(channel, &inputs[range], rng)
+        })
+        .map(|_| ())
+    }
+
+    /// Correlated oblivious transfer send, processing chunks of `deltas` in
+    /// parallel. The outputs are returned in the same order as `deltas`.
+    fn send_correlated_parallel<C, RNG>(
+        &mut self,
+        channels: &mut [C],
+        deltas: &[Self::Msg],
+        rng: &mut RNG,
+    ) -> Result<Vec<(Self::Msg, Self::Msg)>, Error>
+    where
+        Self: CorrelatedSender + Send,
+        Self::Msg: Send + Sync,
+        C: AbstractChannel + Send,
+        RNG: CryptoRng + Rng,
+    {
+        let fork = <Self as ParallelSender>::fork;
+        run_parallel(self, fork, channels, deltas.len(), rng, |ot, channel, range, rng| {
+            ot.send_correlated(channel, &deltas[range], rng)
+        })
+        .map(flatten)
+    }
+
+    /// Random oblivious transfer send, processing chunks of the `m` OTs in
+    /// parallel.
+    fn send_random_parallel<C, RNG>(
+        &mut self,
+        channels: &mut [C],
+        m: usize,
+        rng: &mut RNG,
+    ) -> Result<Vec<(Self::Msg, Self::Msg)>, Error>
+    where
+        Self: RandomSender + Send,
+        Self::Msg: Send,
+        C: AbstractChannel + Send,
+        RNG: CryptoRng + Rng,
+    {
+        let fork = <Self as ParallelSender>::fork;
+        run_parallel(self, fork, channels, m, rng, |ot, channel, range, rng| {
+            ot.send_random(channel, range.len(), rng)
+        })
+        .map(flatten)
+    }
+}
+
+/// Trait for oblivious transfer extension receivers that can split a batch of
+/// OTs across several threads, each with its own channel.
+///
+/// Each thread runs on an independent fork of the receiver, so the sender must
+/// use the same number of channels, in the same order.
+pub trait ParallelReceiver: Receiver {
+    /// Fork off an independent receiver. The corresponding sender must be
+    /// forked at the same point in the protocol.
+    fn fork(&mut self) -> Self;
+
+    /// Receives messages, splitting `inputs` into contiguous chunks, one per
+    /// channel in `channels`, which are processed in parallel. The outputs
+    /// are returned in the same order as `inputs`.
+    fn receive_parallel<C, RNG>(
+        &mut self,
+        channels: &mut [C],
+        inputs: &[bool],
+        rng: &mut RNG,
+    ) -> Result<Vec<Self::Msg>, Error>
+    where
+        Self: Send,
+        Self::Msg: Send,
+        C: AbstractChannel + Send,
+        RNG: CryptoRng + Rng,
+    {
+        let fork = <Self as ParallelReceiver>::fork;
+        run_parallel(self, fork, channels, inputs.len(), rng, |ot, channel, range, rng| {
+            ot.receive(channel, &inputs[range], rng)
+        })
+        .map(flatten)
+    }
+
+    /// Correlated oblivious transfer receive, processing chunks of `inputs`
+    /// in parallel.
+    fn receive_correlated_parallel<C, RNG>(
+        &mut self,
+        channels: &mut [C],
+        inputs: &[bool],
+        rng: &mut RNG,
+    ) -> Result<Vec<Self::Msg>, Error>
+    where
+        Self: CorrelatedReceiver + Send,
+        Self::Msg: Send,
+        C: AbstractChannel + Send,
+        RNG: CryptoRng + Rng,
+    {
+        let fork = <Self as ParallelReceiver>::fork;
+        run_parallel(self, fork, channels, inputs.len(), rng, |ot, channel, range, rng| {
+            ot.receive_correlated(channel, &inputs[range], rng)
+        })
+        .map(flatten)
+    }
+
+    /// Random oblivious transfer receive, processing chunks of `deltas` in
+    /// parallel.
+    fn receive_random_parallel<C, RNG>(
+        &mut self,
+        channels: &mut [C],
+        deltas: &[bool],
+        rng: &mut RNG,
+    ) -> Result<Vec<Self::Msg>, Error>
+    where
+        Self: RandomReceiver + Send,
+        Self::Msg: Send,
+        C: AbstractChannel + Send,
+        RNG: CryptoRng + Rng,
+    {
+        let fork = <Self as ParallelReceiver>::fork;
+        run_parallel(self, fork, channels, deltas.len(), rng, |ot, channel, range, rng| {
+            ot.receive_random(channel, &deltas[range], rng)
+        })
+        .map(flatten)
+    }
+}
+
+// Split `m` OTs into contiguous chunks, one per channel in `channels`, and run
+// `f` on each chunk in its own thread, using a fork of `ot` and a fresh
+// `AesRng` seeded from `rng`. Both parties compute the same chunks, so chunk
+// `i` is always run over channel `i`. Channels that would get an empty chunk
+// are left unused, and no fork is made for them. The outputs are returned in
+// chunk order.
+fn run_parallel<OT, C, RNG, U, F>(
+    ot: &mut OT,
+    fork: fn(&mut OT) -> OT,
+    channels: &mut [C],
+    m: usize,
+    rng: &mut RNG,
+    f: F,
+) -> Result<Vec<U>, Error>
+where
+    OT: Send,
+    C: AbstractChannel + Send,
+    RNG: CryptoRng + Rng,
+    U: Send,
+    F: Fn(&mut OT, &mut C, Range<usize>, &mut AesRng) -> Result<U, Error> + Sync,
+{
+    let n = channels.len();
+    if n == 0 {
+        return Err(Error::InvalidInputLength);
+    }
+    let mut jobs = Vec::with_capacity(n);
+    let mut start = 0;
+    for (i, channel) in channels.iter_mut().enumerate() {
+        let size = m / n + if i < m % n { 1 } else { 0 };
+        if size == 0 {
+            break;
+        }
+        let rng = AesRng::from_seed(rng.gen::<Block>());
+        jobs.push((fork(ot), channel, start..start + size, rng));
+        start += size;
+    }
+    let f = &f;
+    std::thread::scope(|s| {
+        let handles = jobs
+            .into_iter()
+            .map(|(mut ot, channel, range, mut rng)| {
+                s.spawn(move || f(&mut ot, channel, range, &mut rng))
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("OT extension thread panicked"))
+            .collect()
+    })
+}
+
+#[inline]
+fn flatten<T>(chunks: Vec<Vec<T>>) -> Vec<T> {
+    chunks.into_iter().flatten().collect()
+}
+
 #[cfg(test)]
 mod tests {
     #[cfg(feature = "nightly")]
@@ -310,6 +525,82 @@ mod tests {
         }
     }
 
+    fn test_parallel_otext<
+        OTSender: ParallelSender<Msg = Block>
+            + CorrelatedSender<Msg = Block>
+            + RandomSender<Msg = Block>
+            + Send,
+        OTReceiver: ParallelReceiver<Msg = Block>
+            + CorrelatedReceiver<Msg = Block>
+            + RandomReceiver<Msg = Block>
+            + Send,
+    >(
+        ninputs: usize,
+        nchannels: usize,
+    ) {
+        fn new_channel(stream: UnixStream) -> Channel<BufReader<UnixStream>, BufWriter<UnixStream>> {
+            let reader = BufReader::new(stream.try_clone().unwrap());
+            let writer = BufWriter::new(stream);
+            Channel::new(reader, writer)
+        }
+        let m0s = rand_block_vec(ninputs);
+        let m1s = rand_block_vec(ninputs);
+        let deltas = rand_block_vec(ninputs);
+        let bs = rand_bool_vec(ninputs);
+        let ms = m0s
+            .iter()
+            .cloned()
+            .zip(m1s.iter().cloned())
+            .collect::<Vec<(Block, Block)>>();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let (senders, receivers): (Vec<UnixStream>, Vec<UnixStream>) = (0..nchannels)
+            .map(|_| UnixStream::pair().unwrap())
+            .unzip();
+        let deltas_ = deltas.clone();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let mut channel = new_channel(sender);
+            let mut channels = senders.into_iter().map(new_channel).collect::<Vec<_>>();
+            let mut otext = OTSender::init(&mut channel, &mut rng).unwrap();
+            otext.send_parallel(&mut channels, &ms, &mut rng).unwrap();
+            let cout = otext
+                .send_correlated_parallel(&mut channels, &deltas_, &mut rng)
+                .unwrap();
+            let rout = otext
+                .send_random_parallel(&mut channels, ninputs, &mut rng)
+                .unwrap();
+            // Check that the extension still works sequentially afterwards.
+            otext.send(&mut channel, &ms, &mut rng).unwrap();
+            (cout, rout)
+        });
+        let mut rng = AesRng::new();
+        let mut channel = new_channel(receiver);
+        let mut channels = receivers.into_iter().map(new_channel).collect::<Vec<_>>();
+        let mut otext = OTReceiver::init(&mut channel, &mut rng).unwrap();
+        let results = otext
+            .receive_parallel(&mut channels, &bs, &mut rng)
+            .unwrap();
+        let cresults = otext
+            .receive_correlated_parallel(&mut channels, &bs, &mut rng)
+            .unwrap();
+        let rresults = otext
+            .receive_random_parallel(&mut channels, &bs, &mut rng)
+            .unwrap();
+        let sresults = otext.receive(&mut channel, &bs, &mut rng).unwrap();
+        let (cout, rout) = handle.join().unwrap();
+        assert_eq!(results.len(), ninputs);
+        assert_eq!(cresults.len(), ninputs);
+        assert_eq!(rresults.len(), ninputs);
+        for j in 0..ninputs {
+            let m = if bs[j] { m1s[j] } else { m0s[j] };
+            assert_eq!(results[j], m);
+            assert_eq!(sresults[j], m);
+            assert_eq!(cout[j].1, cout[j].0 ^ deltas[j]);
+            assert_eq!(cresults[j], if bs[j] { cout[j].1 } else { cout[j].0 });
+            assert_eq!(rresults[j], if bs[j] { rout[j].1 } else { rout[j].0 });
+        }
+    }
+
     #[test]
     fn test_dummy() {
         test_ot::<DummySender, DummyReceiver>();
@@ -348,4 +639,12 @@ mod tests {
         test_cotext::<KosSender, KosReceiver>(ninputs);
         test_rotext::<KosSender, KosReceiver>(ninputs);
     }
+
+    #[test]
+    fn test_parallel() {
+        test_parallel_otext::<AlszSender, AlszReceiver>((1 << 12) + 1, 4);
+        test_parallel_otext::<AlszSender, AlszReceiver>(3, 4);
+        test_parallel_otext::<KosSender, KosReceiver>((1 << 12) + 1, 4);
+        test_parallel_otext::<KosSender, KosReceiver>(3, 4);
+    }
 }
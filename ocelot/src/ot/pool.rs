@@ -0,0 +1,406 @@
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of pools of precomputed random oblivious transfers, which
+//! are later consumed as chosen-message oblivious transfers using Beaver's
+//! derandomization technique (cf. <https://doi.org/10.1007/3-540-44750-4_8>).
+//!
+//! A pool is filled offline using any random OT (e.g., `AlszSender` and
+//! `AlszReceiver`), and can be saved to and loaded from disk in between. In
+//! the online phase, each chosen-message OT consumes one random OT, and costs
+//! one bit from the receiver and two masked messages from the sender.
+//!
+//! Both parties must consume the pools in the same order, and so the pools
+//! should only be used with the party that generated them.
+
+use crate::{
+    errors::Error,
+    ot::{
+        RandomReceiver as OtRandomReceiver,
+        RandomSender as OtRandomSender,
+        Receiver as OtReceiver,
+        Sender as OtSender,
+    },
+    utils,
+};
+use rand::{CryptoRng, Rng};
+use scuttlebutt::{AbstractChannel, Block, SemiHonest};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+// The maximum number of OTs to allocate space for upfront when reading a pool,
+// as the stored length is not trusted. Larger pools grow as they are read.
+const MAX_PREALLOC: usize = 1 << 16;
+
+fn write_u64<W: Write>(writer: &mut W, n: usize) -> Result<(), Error> {
+    writer.write_all(&(n as u64).to_le_bytes())?;
+    Ok(())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<usize, Error> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes) as usize)
+}
+
+fn read_block<R: Read>(reader: &mut R) -> Result<Block, Error> {
+    let mut bytes = [0u8; 16];
+    reader.read_exact(&mut bytes)?;
+    Ok(Block::from(bytes))
+}
+
+// Check that the pool contains at least `m` OTs.
+fn check_len(len: usize, m: usize) -> Result<(), Error> {
+    if len < m {
+        Err(Error::Other(format!(
+            "OT pool contains {} OTs, but {} are needed",
+            len, m
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Oblivious transfer sender backed by a pool of precomputed random OTs.
+#[derive(Default)]
+pub struct Sender {
+    ots: Vec<(Block, Block)>,
+}
+
+impl Sender {
+    /// Create an empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the number of random OTs left in the pool.
+    pub fn len(&self) -> usize {
+        self.ots.len()
+    }
+
+    /// Return whether the pool is empty.
+    pub fn is_empty(&self) -> bool {
+        self.ots.is_empty()
+    }
+
+    /// Add `m` random OTs to the pool, generated using `ot`.
+    pub fn fill<OT, C, RNG>(
+        &mut self,
+        ot: &mut OT,
+        channel: &mut C,
+        m: usize,
+        rng: &mut RNG,
+    ) -> Result<(), Error>
+    where
+        OT: OtRandomSender<Msg = Block>,
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        let ots = ot.send_random(channel, m, rng)?;
+        self.ots.extend(ots);
+        Ok(())
+    }
+
+    /// Write the pool to `writer`.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        write_u64(writer, self.ots.len())?;
+        for (r0, r1) in self.ots.iter() {
+            writer.write_all(r0.as_ref())?;
+            writer.write_all(r1.as_ref())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read a pool from `reader`.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let n = read_u64(reader)?;
+        let mut ots = Vec::with_capacity(n.min(MAX_PREALLOC));
+        for _ in 0..n {
+            let r0 = read_block(reader)?;
+            let r1 = read_block(reader)?;
+            ots.push((r0, r1));
+        }
+        Ok(Self { ots })
+    }
+
+    /// Save the pool to the file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)
+    }
+
+    /// Load a pool from the file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut reader = BufReader::new(File::open(path)?);
+        Self::read(&mut reader)
+    }
+}
+
+impl OtSender for Sender {
+    type Msg = Block;
+
+    /// Create an empty pool. Use `fill` or `load` to add random OTs.
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        _: &mut C,
+        _: &mut RNG,
+    ) -> Result<Self, Error> {
+        Ok(Self::new())
+    }
+
+    fn send<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[(Block, Block)],
+        _: &mut RNG,
+    ) -> Result<(), Error> {
+        let m = inputs.len();
+        check_len(self.ots.len(), m)?;
+        let ds = channel.read_vec(m.div_ceil(8))?;
+        let ds = utils::u8vec_to_boolvec(&ds);
+        for ((d, (m0, m1)), (r0, r1)) in ds
+            .into_iter()
+            .zip(inputs.iter())
+            .zip(self.ots.drain(..m))
+        {
+            // The receiver flipped its random choice bit, so flip the
+            // random messages too.
+            let (r0, r1) = if d { (r1, r0) } else { (r0, r1) };
+            channel.write_block(&(*m0 ^ r0))?;
+            channel.write_block(&(*m1 ^ r1))?;
+        }
+        channel.flush()?;
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Sender {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "OT Pool Sender")
+    }
+}
+
+/// Oblivious transfer receiver backed by a pool of precomputed random OTs.
+#[derive(Default)]
+pub struct Receiver {
+    ots: Vec<(bool, Block)>,
+}
+
+impl Receiver {
+    /// Create an empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the number of random OTs left in the pool.
+    pub fn len(&self) -> usize {
+        self.ots.len()
+    }
+
+    /// Return whether the pool is empty.
+    pub fn is_empty(&self) -> bool {
+        self.ots.is_empty()
+    }
+
+    /// Add `m` random OTs to the pool, generated using `ot` on random choice
+    /// bits.
+    pub fn fill<OT, C, RNG>(
+        &mut self,
+        ot: &mut OT,
+        channel: &mut C,
+        m: usize,
+        rng: &mut RNG,
+    ) -> Result<(), Error>
+    where
+        OT: OtRandomReceiver<Msg = Block>,
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        let cs = (0..m).map(|_| rng.gen::<bool>()).collect::<Vec<bool>>();
+        let rs = ot.receive_random(channel, &cs, rng)?;
+        self.ots.extend(cs.into_iter().zip(rs));
+        Ok(())
+    }
+
+    /// Write the pool to `writer`.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        write_u64(writer, self.ots.len())?;
+        for (c, r) in self.ots.iter() {
+            writer.write_all(&[*c as u8])?;
+            writer.write_all(r.as_ref())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read a pool from `reader`.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let n = read_u64(reader)?;
+        let mut ots = Vec::with_capacity(n.min(MAX_PREALLOC));
+        for _ in 0..n {
+            let mut c = [0u8; 1];
+            reader.read_exact(&mut c)?;
+            let c = match c[0] {
+                0 => false,
+                1 => true,
+                _ => {
+                    return Err(Error::from(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "invalid choice bit in OT pool",
+                    )))
+                }
+            };
+            let r = read_block(reader)?;
+            ots.push((c, r));
+        }
+        Ok(Self { ots })
+    }
+
+    /// Save the pool to the file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)
+    }
+
+    /// Load a pool from the file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut reader = BufReader::new(File::open(path)?);
+        Self::read(&mut reader)
+    }
+}
+
+impl OtReceiver for Receiver {
+    type Msg = Block;
+
+    /// Create an empty pool. Use `fill` or `load` to add random OTs.
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        _: &mut C,
+        _: &mut RNG,
+    ) -> Result<Self, Error> {
+        Ok(Self::new())
+    }
+
+    fn receive<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[bool],
+        _: &mut RNG,
+    ) -> Result<Vec<Block>, Error> {
+        let m = inputs.len();
+        check_len(self.ots.len(), m)?;
+        let ots = self.ots.drain(..m).collect::<Vec<(bool, Block)>>();
+        // Send `d = b ⊕ c` for choice bit `b` and random choice bit `c`.
+        let ds = inputs
+            .iter()
+            .zip(ots.iter())
+            .map(|(b, (c, _))| b ^ c)
+            .collect::<Vec<bool>>();
+        channel.write_all(&utils::boolvec_to_u8vec(&ds))?;
+        channel.flush()?;
+        let mut out = Vec::with_capacity(m);
+        for (b, (_, r)) in inputs.iter().zip(ots) {
+            let y0 = channel.read_block()?;
+            let y1 = channel.read_block()?;
+            out.push(if *b { y1 ^ r } else { y0 ^ r });
+        }
+        Ok(out)
+    }
+}
+
+impl std::fmt::Display for Receiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "OT Pool Receiver")
+    }
+}
+
+impl SemiHonest for Sender {}
+impl SemiHonest for Receiver {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ot::{AlszReceiver, AlszSender};
+    use scuttlebutt::{AesRng, Channel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    fn rand_block_vec(size: usize) -> Vec<Block> {
+        (0..size).map(|_| rand::random::<Block>()).collect()
+    }
+
+    fn rand_bool_vec(size: usize) -> Vec<bool> {
+        (0..size).map(|_| rand::random::<bool>()).collect()
+    }
+
+    #[test]
+    fn test_pool() {
+        let npool = 1 << 10;
+        let ninputs = 300;
+        let m0s = rand_block_vec(ninputs);
+        let m1s = rand_block_vec(ninputs);
+        let bs = rand_bool_vec(ninputs);
+        let ms = m0s
+            .iter()
+            .cloned()
+            .zip(m1s.iter().cloned())
+            .collect::<Vec<(Block, Block)>>();
+        let path = std::env::temp_dir().join(format!("ocelot-pool-{}", rand::random::<u64>()));
+        let path_ = path.clone();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut ot = AlszSender::init(&mut channel, &mut rng).unwrap();
+            let mut pool = Sender::new();
+            pool.fill(&mut ot, &mut channel, npool, &mut rng).unwrap();
+            // Consume part of the pool, then persist and reload the rest.
+            pool.send(&mut channel, &ms, &mut rng).unwrap();
+            pool.save(&path_).unwrap();
+            let mut pool = Sender::load(&path_).unwrap();
+            assert_eq!(pool.len(), npool - ninputs);
+            pool.send(&mut channel, &ms, &mut rng).unwrap();
+        });
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut ot = AlszReceiver::init(&mut channel, &mut rng).unwrap();
+        let mut pool = Receiver::new();
+        pool.fill(&mut ot, &mut channel, npool, &mut rng).unwrap();
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        let first = pool.receive(&mut channel, &bs, &mut rng).unwrap();
+        pool.write(&mut cursor).unwrap();
+        cursor.set_position(0);
+        let mut pool = Receiver::read(&mut cursor).unwrap();
+        let second = pool.receive(&mut channel, &bs, &mut rng).unwrap();
+        handle.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        for results in [first, second].iter() {
+            for j in 0..ninputs {
+                assert_eq!(results[j], if bs[j] { m1s[j] } else { m0s[j] });
+            }
+        }
+        assert!(pool
+            .receive(&mut channel, &rand_bool_vec(npool), &mut rng)
+            .is_err());
+    }
+
+    #[test]
+    fn test_read_truncated() {
+        // A pool claiming to hold `2^64 - 1` OTs, but holding none, is
+        // rejected without allocating space for them.
+        let bytes = u64::MAX.to_le_bytes();
+        assert!(Sender::read(&mut &bytes[..]).is_err());
+        assert!(Receiver::read(&mut &bytes[..]).is_err());
+    }
+}
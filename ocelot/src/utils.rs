@@ -6,6 +6,9 @@
 
 use scuttlebutt::Block;
 
+/// Transpose the `nrows × ncols` bit matrix `m`, stored row by row with the
+/// least significant bit of each byte first.
+#[cfg(not(feature = "portable"))]
 #[inline]
 pub fn transpose(m: &[u8], nrows: usize, ncols: usize) -> Vec<u8> {
     let mut m_ = vec![0u8; nrows * ncols / 8];
@@ -18,6 +21,16 @@ pub fn transpose(m: &[u8], nrows: usize, ncols: usize) -> Vec<u8> {
     m_
 }
 
+/// Transpose the `nrows × ncols` bit matrix `m`, stored row by row with the
+/// least significant bit of each byte first.
+#[cfg(feature = "portable")]
+#[inline]
+pub fn transpose(m: &[u8], nrows: usize, ncols: usize) -> Vec<u8> {
+    assert!(nrows >= 16);
+    transpose_portable(m, nrows, ncols)
+}
+
+#[cfg(not(feature = "portable"))]
 #[inline(always)]
 fn _transpose(out: *mut u8, inp: *const u8, nrows: u64, ncols: u64) {
     assert!(nrows >= 16);
@@ -26,123 +39,45 @@ fn _transpose(out: *mut u8, inp: *const u8, nrows: u64, ncols: u64) {
     unsafe { sse_trans(out, inp, nrows, ncols) }
 }
 
+#[cfg(not(feature = "portable"))]
 #[link(name = "transpose")]
 extern "C" {
     fn sse_trans(out: *mut u8, inp: *const u8, nrows: u64, ncols: u64);
 }
 
-// The hypothesis that a rust implementation of matrix transpose would be faster
-// than the C implementation appears to be false... But let's leave this code
-// here for now just in case.
-
-// union __U128 {
-//     vector: __m128i,
-//     bytes: [u8; 16],
-// }
-
-// impl Default for __U128 {
-//     #[inline]
-//     fn default() -> Self {
-//         __U128 { bytes: [0u8; 16] }
-//     }
-// }
-
-// #[inline]
-// pub fn transpose(input: &[u8], nrows: usize, ncols: usize) -> Vec<u8> {
-//     assert_eq!(nrows % 16, 0);
-//     assert_eq!(ncols % 16, 0);
-//     let mut output = vec![0u8; nrows * ncols / 8];
-//     unsafe {
-//         let mut h: &[u8; 4];
-//         let mut v: __m128i;
-//         let mut rr: usize = 0;
-//         let mut cc: usize;
-//         while rr <= nrows - 16 {
-//             cc = 0;
-//             while cc < ncols {
-//                 v = _mm_set_epi8(
-//                     input[(rr + 15) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 14) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 13) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 12) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 11) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 10) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 9) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 8) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 7) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 6) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 5) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 4) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 3) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 2) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 1) * ncols / 8 + cc / 8] as i8,
-//                     input[(rr + 0) * ncols / 8 + cc / 8] as i8,
-//                 );
-//                 for i in (0..8).rev() {
-//                     h = &*(&_mm_movemask_epi8(v) as *const _ as *const [u8; 4]);
-//                     output[(cc + i) * nrows / 8 + rr / 8] = h[0];
-//                     output[(cc + i) * nrows / 8 + rr / 8 + 1] = h[1];
-//                     v = _mm_slli_epi64(v, 1);
-//                 }
-//                 cc += 8;
-//             }
-//             rr += 16;
-//         }
-//         if rr == nrows {
-//             return output;
-//         }
-
-//         cc = 0;
-//         while cc <= ncols - 16 {
-//             let mut v = _mm_set_epi16(
-//                 input[((rr + 7) * ncols / 8 + cc / 8) / 2] as i16,
-//                 input[((rr + 6) * ncols / 8 + cc / 8) / 2] as i16,
-//                 input[((rr + 5) * ncols / 8 + cc / 8) / 2] as i16,
-//                 input[((rr + 4) * ncols / 8 + cc / 8) / 2] as i16,
-//                 input[((rr + 3) * ncols / 8 + cc / 8) / 2] as i16,
-//                 input[((rr + 2) * ncols / 8 + cc / 8) / 2] as i16,
-//                 input[((rr + 1) * ncols / 8 + cc / 8) / 2] as i16,
-//                 input[((rr + 0) * ncols / 8 + cc / 8) / 2] as i16,
-//             );
-//             for i in (0..8).rev() {
-//                 h = &*(&_mm_movemask_epi8(v) as *const _ as *const [u8; 4]);
-//                 output[(cc + i) * nrows / 8 + rr / 8] = h[0];
-//                 output[(cc + i) * nrows / 8 + rr / 8 + 8] = h[1];
-//                 v = _mm_slli_epi64(v, 1);
-//             }
-//             cc += 16;
-//         }
-//         if cc == ncols {
-//             return output;
-//         }
-//         let mut tmp = __U128 {
-//             bytes: [
-//                 input[(rr + 0) * ncols / 8 + cc / 8],
-//                 input[(rr + 1) * ncols / 8 + cc / 8],
-//                 input[(rr + 2) * ncols / 8 + cc / 8],
-//                 input[(rr + 3) * ncols / 8 + cc / 8],
-//                 input[(rr + 4) * ncols / 8 + cc / 8],
-//                 input[(rr + 5) * ncols / 8 + cc / 8],
-//                 input[(rr + 6) * ncols / 8 + cc / 8],
-//                 input[(rr + 7) * ncols / 8 + cc / 8],
-//                 0u8,
-//                 0u8,
-//                 0u8,
-//                 0u8,
-//                 0u8,
-//                 0u8,
-//                 0u8,
-//                 0u8,
-//             ],
-//         };
-//         for i in (0..8).rev() {
-//             h = &*(&_mm_movemask_epi8(tmp.vector) as *const _ as *const [u8; 4]);
-//             output[(cc + i) * nrows / 8 + rr / 8] = h[0];
-//             tmp.vector = _mm_slli_epi64(tmp.vector, 1);
-//         }
-//     };
-//     output
-// }
+// Transpose an 8 × 8 bit matrix, with row `i` in byte `i` of `x`.
+#[cfg(any(feature = "portable", test))]
+#[inline(always)]
+fn transpose8(mut x: u64) -> u64 {
+    let t = (x ^ (x >> 7)) & 0x00AA_00AA_00AA_00AA;
+    x ^= t ^ (t << 7);
+    let t = (x ^ (x >> 14)) & 0x0000_CCCC_0000_CCCC;
+    x ^= t ^ (t << 14);
+    let t = (x ^ (x >> 28)) & 0x0000_0000_F0F0_F0F0;
+    x ^ t ^ (t << 28)
+}
+
+// A pure-Rust transpose, working on one 8 × 8 block of bits at a time.
+#[cfg(any(feature = "portable", test))]
+fn transpose_portable(m: &[u8], nrows: usize, ncols: usize) -> Vec<u8> {
+    assert_eq!(nrows % 8, 0);
+    assert_eq!(ncols % 8, 0);
+    assert_eq!(m.len(), nrows * ncols / 8);
+    let (rowlen, collen) = (ncols / 8, nrows / 8);
+    let mut m_ = vec![0u8; nrows * ncols / 8];
+    for r in 0..collen {
+        for c in 0..rowlen {
+            let x = (0..8).fold(0u64, |acc, i| {
+                acc | (u64::from(m[(8 * r + i) * rowlen + c]) << (8 * i))
+            });
+            let x = transpose8(x);
+            for i in 0..8 {
+                m_[(8 * c + i) * collen + r] = (x >> (8 * i)) as u8;
+            }
+        }
+    }
+    m_
+}
 
 #[inline]
 pub fn boolvec_to_u8vec(bv: &[bool]) -> Vec<u8> {
@@ -169,6 +104,40 @@ pub fn xor_two_blocks(x: &(Block, Block), y: &(Block, Block)) -> (Block, Block)
     (x.0 ^ y.0, x.1 ^ y.1)
 }
 
+/// The number of threads to use by default in the multi-threaded routines.
+#[inline]
+pub fn default_nthreads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Apply `f` to each element of `xs`, splitting the work into contiguous chunks
+/// across `nthreads` threads. The outputs are returned in the same order as the
+/// inputs.
+pub fn parallel_map<T, U, F>(xs: &[T], nthreads: usize, f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync,
+{
+    if nthreads <= 1 || xs.len() <= 1 {
+        return xs.iter().map(f).collect();
+    }
+    let chunksize = xs.len().div_ceil(nthreads);
+    let f = &f;
+    std::thread::scope(|s| {
+        let handles = xs
+            .chunks(chunksize)
+            .map(|chunk| s.spawn(move || chunk.iter().map(f).collect::<Vec<U>>()))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("worker thread panicked"))
+            .collect()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,6 +152,21 @@ mod tests {
         assert_eq!(m, m_);
     }
 
+    fn _transpose_naive(nrows: usize, ncols: usize) {
+        let m = (0..nrows * ncols / 8)
+            .map(|_| rand::random::<u8>())
+            .collect::<Vec<u8>>();
+        let bits = u8vec_to_boolvec(&m);
+        let m_ = transpose(&m, nrows, ncols);
+        let bits_ = u8vec_to_boolvec(&m_);
+        for r in 0..nrows {
+            for c in 0..ncols {
+                assert_eq!(bits[r * ncols + c], bits_[c * nrows + r]);
+            }
+        }
+        assert_eq!(transpose_portable(&m, nrows, ncols), m_);
+    }
+
     #[test]
     fn test_transpose() {
         _transpose(16, 16);
@@ -198,6 +182,26 @@ mod tests {
         _transpose(64, 32);
     }
 
+    #[test]
+    fn test_transpose_naive() {
+        _transpose_naive(16, 8);
+        _transpose_naive(16, 16);
+        _transpose_naive(24, 16);
+        _transpose_naive(40, 24);
+        _transpose_naive(128, 128);
+        _transpose_naive(128, 1 << 10);
+        _transpose_naive(1 << 10, 128);
+    }
+
+    #[test]
+    fn test_parallel_map() {
+        let xs = (0..1000).collect::<Vec<usize>>();
+        for nthreads in 1..6 {
+            let ys = parallel_map(&xs, nthreads, |x| x * 2);
+            assert_eq!(ys, xs.iter().map(|x| x * 2).collect::<Vec<usize>>());
+        }
+    }
+
     #[test]
     fn test_boolvec_to_u8vec() {
         let v = (0..128)
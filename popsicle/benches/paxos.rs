@@ -0,0 +1,93 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `popsicle`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Private set intersection (PaXoS) benchmarks using `criterion`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use popsicle::{PaxosReceiver, PaxosSender, PaxosVoleReceiver, PaxosVoleSender};
+use scuttlebutt::{AesRng, Channel};
+use std::{
+    io::{BufReader, BufWriter},
+    os::unix::net::UnixStream,
+    time::Duration,
+};
+
+const SIZE: usize = 15;
+
+fn rand_vec(n: usize) -> Vec<u8> {
+    (0..n).map(|_| rand::random::<u8>()).collect()
+}
+
+fn rand_vec_vec(size: usize) -> Vec<Vec<u8>> {
+    (0..size).map(|_| rand_vec(SIZE)).collect()
+}
+
+fn _bench_paxos(inputs1: Vec<Vec<u8>>, inputs2: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    let (sender, receiver) = UnixStream::pair().unwrap();
+    let handle = std::thread::spawn(move || {
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(sender.try_clone().unwrap());
+        let writer = BufWriter::new(sender);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi = PaxosSender::init(&mut channel, &mut rng).unwrap();
+        psi.send(&inputs1, &mut channel, &mut rng).unwrap();
+    });
+    let mut rng = AesRng::new();
+    let reader = BufReader::new(receiver.try_clone().unwrap());
+    let writer = BufWriter::new(receiver);
+    let mut channel = Channel::new(reader, writer);
+    let mut psi = PaxosReceiver::init(&mut channel, &mut rng).unwrap();
+    let intersection = psi.receive(&inputs2, &mut channel, &mut rng).unwrap();
+    handle.join().unwrap();
+    intersection
+}
+
+fn _bench_paxos_vole(inputs1: Vec<Vec<u8>>, inputs2: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    let (sender, receiver) = UnixStream::pair().unwrap();
+    let handle = std::thread::spawn(move || {
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(sender.try_clone().unwrap());
+        let writer = BufWriter::new(sender);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi = PaxosVoleSender::init(&mut channel, &mut rng).unwrap();
+        psi.send(&inputs1, &mut channel, &mut rng).unwrap();
+    });
+    let mut rng = AesRng::new();
+    let reader = BufReader::new(receiver.try_clone().unwrap());
+    let writer = BufWriter::new(receiver);
+    let mut channel = Channel::new(reader, writer);
+    let mut psi = PaxosVoleReceiver::init(&mut channel, &mut rng).unwrap();
+    let intersection = psi.receive(&inputs2, &mut channel, &mut rng).unwrap();
+    handle.join().unwrap();
+    intersection
+}
+
+fn bench_psi(c: &mut Criterion) {
+    for logn in [8, 12, 16, 20].iter() {
+        c.bench_function(&format!("psi::PaXoS (n = 2^{})", logn), move |bench| {
+            let rs = rand_vec_vec(1 << logn);
+            bench.iter(|| {
+                let v = _bench_paxos(rs.clone(), rs.clone());
+                criterion::black_box(v)
+            })
+        });
+        c.bench_function(&format!("psi::PaXoS-VOLE (n = 2^{})", logn), move |bench| {
+            let rs = rand_vec_vec(1 << logn);
+            bench.iter(|| {
+                let v = _bench_paxos_vole(rs.clone(), rs.clone());
+                criterion::black_box(v)
+            })
+        });
+    }
+}
+
+criterion_group! {
+    name = psi;
+    config = Criterion::default().warm_up_time(Duration::from_millis(100)).sample_size(10);
+    targets = bench_psi
+}
+
+criterion_main!(psi);
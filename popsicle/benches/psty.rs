@@ -8,7 +8,13 @@
 
 use criterion::{criterion_group, criterion_main, Criterion};
 use popsicle::psty::{Receiver, Sender};
-use scuttlebutt::{AesRng, Channel};
+use scuttlebutt::{
+    unix_channel_pair,
+    AbstractChannel,
+    AesRng,
+    Channel,
+    SimulatedChannel,
+};
 use std::{
     io::{BufReader, BufWriter},
     os::unix::net::UnixStream,
@@ -33,35 +39,31 @@ fn bench_psty_init() {
         let reader = BufReader::new(sender.try_clone().unwrap());
         let writer = BufWriter::new(sender);
         let mut channel = Channel::new(reader, writer);
-        let _ = Sender::init(&mut channel, &mut rng).unwrap();
+        let _: Sender = Sender::init(&mut channel, &mut rng).unwrap();
     });
 
     let mut rng = AesRng::new();
     let reader = BufReader::new(receiver.try_clone().unwrap());
     let writer = BufWriter::new(receiver);
     let mut channel = Channel::new(reader, writer);
-    let _ = Receiver::init(&mut channel, &mut rng).unwrap();
+    let _: Receiver = Receiver::init(&mut channel, &mut rng).unwrap();
 
     handle.join().unwrap();
 }
 
-fn bench_psty(inputs1: Vec<Vec<u8>>, inputs2: Vec<Vec<u8>>) -> () {
-    let (sender, receiver) = UnixStream::pair().unwrap();
-
+fn bench_psty<C: AbstractChannel + Send + 'static>(
+    (mut sender, mut channel): (C, C),
+    inputs1: Vec<Vec<u8>>,
+    inputs2: Vec<Vec<u8>>,
+) -> () {
     std::thread::spawn(move || {
         let mut rng = AesRng::new();
-        let reader = BufReader::new(sender.try_clone().unwrap());
-        let writer = BufWriter::new(sender);
-        let mut channel = Channel::new(reader, writer);
-        let mut p1 = Sender::init(&mut channel, &mut rng).unwrap();
-        p1.send(&inputs1, &mut channel, &mut rng).unwrap()
+        let mut p1: Sender = Sender::init(&mut sender, &mut rng).unwrap();
+        p1.send(&inputs1, &mut sender, &mut rng).unwrap()
     });
 
     let mut rng = AesRng::new();
-    let reader = BufReader::new(receiver.try_clone().unwrap());
-    let writer = BufWriter::new(receiver);
-    let mut channel = Channel::new(reader, writer);
-    let mut p2 = Receiver::init(&mut channel, &mut rng).unwrap();
+    let mut p2: Receiver = Receiver::init(&mut channel, &mut rng).unwrap();
     p2.receive(&inputs2, &mut channel, &mut rng).unwrap();
 }
 
@@ -75,28 +77,36 @@ fn bench_psi(c: &mut Criterion) {
     c.bench_function("psi::PSTY (n = 2^8)", move |bench| {
         let rs = rand_vec_vec(1 << 8);
         bench.iter(|| {
-            let v = bench_psty(rs.clone(), rs.clone());
+            let v = bench_psty(unix_channel_pair(), rs.clone(), rs.clone());
             criterion::black_box(v)
         })
     });
     c.bench_function("psi::PSTY (n = 2^12)", move |bench| {
         let rs = rand_vec_vec(1 << 12);
         bench.iter(|| {
-            let v = bench_psty(rs.clone(), rs.clone());
+            let v = bench_psty(unix_channel_pair(), rs.clone(), rs.clone());
             criterion::black_box(v)
         })
     });
     c.bench_function("psi::PSTY (n = 2^16)", move |bench| {
         let rs = rand_vec_vec(1 << 16);
         bench.iter(|| {
-            let v = bench_psty(rs.clone(), rs.clone());
+            let v = bench_psty(unix_channel_pair(), rs.clone(), rs.clone());
+            criterion::black_box(v)
+        })
+    });
+    c.bench_function("psi::PSTY (n = 2^16, WAN)", move |bench| {
+        let rs = rand_vec_vec(1 << 16);
+        bench.iter(|| {
+            let channels = SimulatedChannel::wan_pair();
+            let v = bench_psty(channels, rs.clone(), rs.clone());
             criterion::black_box(v)
         })
     });
     // c.bench_function("psi::PSTY (n = 2^20)", move |bench| {
     //     let rs = rand_vec_vec(1 << 20);
     //     bench.iter(|| {
-    //         let v = bench_psty(rs.clone(), rs.clone());
+    //         let v = bench_psty(unix_channel_pair(), rs.clone(), rs.clone());
     //         criterion::black_box(v)
     //     })
     // });
@@ -8,7 +8,13 @@
 
 use criterion::{criterion_group, criterion_main, Criterion};
 use popsicle::psz;
-use scuttlebutt::{AesRng, Channel};
+use scuttlebutt::{
+    unix_channel_pair,
+    AbstractChannel,
+    AesRng,
+    Channel,
+    SimulatedChannel,
+};
 use std::{
     io::{BufReader, BufWriter},
     os::unix::net::UnixStream,
@@ -42,20 +48,17 @@ fn _bench_psz_init() {
     handle.join().unwrap();
 }
 
-fn _bench_psz(inputs1: Vec<Vec<u8>>, inputs2: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
-    let (sender, receiver) = UnixStream::pair().unwrap();
+fn _bench_psz<C: AbstractChannel + Send + 'static>(
+    (mut sender, mut channel): (C, C),
+    inputs1: Vec<Vec<u8>>,
+    inputs2: Vec<Vec<u8>>,
+) -> Vec<Vec<u8>> {
     let handle = std::thread::spawn(move || {
         let mut rng = AesRng::new();
-        let reader = BufReader::new(sender.try_clone().unwrap());
-        let writer = BufWriter::new(sender);
-        let mut channel = Channel::new(reader, writer);
-        let mut psi = psz::Sender::init(&mut channel, &mut rng).unwrap();
-        psi.send(&inputs1, &mut channel, &mut rng).unwrap();
+        let mut psi = psz::Sender::init(&mut sender, &mut rng).unwrap();
+        psi.send(&inputs1, &mut sender, &mut rng).unwrap();
     });
     let mut rng = AesRng::new();
-    let reader = BufReader::new(receiver.try_clone().unwrap());
-    let writer = BufWriter::new(receiver);
-    let mut channel = Channel::new(reader, writer);
     let mut psi = psz::Receiver::init(&mut channel, &mut rng).unwrap();
     let intersection = psi.receive(&inputs2, &mut channel, &mut rng).unwrap();
     handle.join().unwrap();
@@ -72,28 +75,36 @@ fn bench_psi(c: &mut Criterion) {
     c.bench_function("psi::PSZ (n = 2^8)", move |bench| {
         let rs = rand_vec_vec(1 << 8);
         bench.iter(|| {
-            let v = _bench_psz(rs.clone(), rs.clone());
+            let v = _bench_psz(unix_channel_pair(), rs.clone(), rs.clone());
             criterion::black_box(v)
         })
     });
     c.bench_function("psi::PSZ (n = 2^12)", move |bench| {
         let rs = rand_vec_vec(1 << 12);
         bench.iter(|| {
-            let v = _bench_psz(rs.clone(), rs.clone());
+            let v = _bench_psz(unix_channel_pair(), rs.clone(), rs.clone());
             criterion::black_box(v)
         })
     });
     c.bench_function("psi::PSZ (n = 2^16)", move |bench| {
         let rs = rand_vec_vec(1 << 16);
         bench.iter(|| {
-            let v = _bench_psz(rs.clone(), rs.clone());
+            let v = _bench_psz(unix_channel_pair(), rs.clone(), rs.clone());
             criterion::black_box(v)
         })
     });
     c.bench_function("psi::PSZ (n = 2^20)", move |bench| {
         let rs = rand_vec_vec(1 << 20);
         bench.iter(|| {
-            let v = _bench_psz(rs.clone(), rs.clone());
+            let v = _bench_psz(unix_channel_pair(), rs.clone(), rs.clone());
+            criterion::black_box(v)
+        })
+    });
+    c.bench_function("psi::PSZ (n = 2^16, WAN)", move |bench| {
+        let rs = rand_vec_vec(1 << 16);
+        bench.iter(|| {
+            let channels = SimulatedChannel::wan_pair();
+            let v = _bench_psz(channels, rs.clone(), rs.clone());
             criterion::black_box(v)
         })
     });
@@ -5,7 +5,7 @@
 // See LICENSE for licensing information.
 
 use popsicle::psty::{Receiver, Sender};
-use scuttlebutt::{AesRng, TrackChannel};
+use scuttlebutt::{AesRng, Channel, TrackChannel};
 use std::{
     io::{BufReader, BufWriter},
     os::unix::net::UnixStream,
@@ -25,17 +25,16 @@ fn rand_vec_vec(ninputs: usize, nbytes: usize) -> Vec<Vec<u8>> {
 }
 
 fn psty_payload(inputs1: Vec<Vec<u8>>, inputs2: Vec<Vec<u8>>, payloads: Vec<Vec<u8>>) {
-    let payload_size = payloads[0].len();
     let (sender, receiver) = UnixStream::pair().unwrap();
     let total = SystemTime::now();
     let handle = std::thread::spawn(move || {
         let mut rng = AesRng::new();
         let reader = BufReader::new(sender.try_clone().unwrap());
         let writer = BufWriter::new(sender);
-        let mut channel = TrackChannel::new(reader, writer);
+        let mut channel = TrackChannel::new(Channel::new(reader, writer));
 
         let start = SystemTime::now();
-        let mut sender = Sender::init(&mut channel, &mut rng).unwrap();
+        let mut sender: Sender = Sender::init(&mut channel, &mut rng).unwrap();
         println!(
             "Sender :: init time: {} ms",
             start.elapsed().unwrap().as_millis()
@@ -55,7 +54,7 @@ fn psty_payload(inputs1: Vec<Vec<u8>>, inputs2: Vec<Vec<u8>>, payloads: Vec<Vec<
             channel.kilobits_written() / 1000.0
         );
         let start = SystemTime::now();
-        let _ = state.receive_payloads(payload_size, &mut channel).unwrap();
+        let _ = state.receive_payloads(&mut channel).unwrap();
         println!(
             "Sender :: payload intersection time: {} ms",
             start.elapsed().unwrap().as_millis()
@@ -73,10 +72,10 @@ fn psty_payload(inputs1: Vec<Vec<u8>>, inputs2: Vec<Vec<u8>>, payloads: Vec<Vec<
     let mut rng = AesRng::new();
     let reader = BufReader::new(receiver.try_clone().unwrap());
     let writer = BufWriter::new(receiver);
-    let mut channel = TrackChannel::new(reader, writer);
+    let mut channel = TrackChannel::new(Channel::new(reader, writer));
 
     let start = SystemTime::now();
-    let mut receiver = Receiver::init(&mut channel, &mut rng).unwrap();
+    let mut receiver: Receiver = Receiver::init(&mut channel, &mut rng).unwrap();
     println!(
         "Receiver :: init time: {} ms",
         start.elapsed().unwrap().as_millis()
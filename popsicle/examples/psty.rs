@@ -7,7 +7,7 @@
 //! Private set intersection (PSTY) benchmarks using `criterion`.
 
 use popsicle::psty::{Receiver, Sender};
-use scuttlebutt::{AesRng, TrackChannel};
+use scuttlebutt::{AesRng, Channel, TrackChannel};
 use std::{
     io::{BufReader, BufWriter},
     os::unix::net::UnixStream,
@@ -32,10 +32,10 @@ fn psty(inputs1: Vec<Vec<u8>>, inputs2: Vec<Vec<u8>>) {
         let mut rng = AesRng::new();
         let reader = BufReader::new(sender.try_clone().unwrap());
         let writer = BufWriter::new(sender);
-        let mut channel = TrackChannel::new(reader, writer);
+        let mut channel = TrackChannel::new(Channel::new(reader, writer));
 
         let start = SystemTime::now();
-        let mut sender = Sender::init(&mut channel, &mut rng).unwrap();
+        let mut sender: Sender = Sender::init(&mut channel, &mut rng).unwrap();
         println!(
             "Sender :: init time: {} ms",
             start.elapsed().unwrap().as_millis()
@@ -73,10 +73,10 @@ fn psty(inputs1: Vec<Vec<u8>>, inputs2: Vec<Vec<u8>>) {
     let mut rng = AesRng::new();
     let reader = BufReader::new(receiver.try_clone().unwrap());
     let writer = BufWriter::new(receiver);
-    let mut channel = TrackChannel::new(reader, writer);
+    let mut channel = TrackChannel::new(Channel::new(reader, writer));
 
     let start = SystemTime::now();
-    let mut receiver = Receiver::init(&mut channel, &mut rng).unwrap();
+    let mut receiver: Receiver = Receiver::init(&mut channel, &mut rng).unwrap();
     println!(
         "Receiver :: init time: {} ms",
         start.elapsed().unwrap().as_millis()
@@ -9,11 +9,10 @@
 use clap::{App, Arg};
 use itertools::Itertools;
 use popsicle::{MultiPartyReceiver, MultiPartySender};
-use scuttlebutt::{AesRng, Block, TrackChannel};
+use scuttlebutt::{AesRng, Block, Network, TrackChannel};
 use serde::Deserialize;
 use std::{
-    io::{BufRead, BufReader, BufWriter, Read, Write},
-    net::{TcpListener, TcpStream, ToSocketAddrs},
+    io::{BufRead, BufReader, Write},
     time::SystemTime,
 };
 
@@ -79,7 +78,15 @@ fn main() {
         .map(|s| ipv6_to_block(&s.unwrap()))
         .collect_vec();
 
-    let mut cons = connect_to_parties(my_id, &config);
+    let addresses = config
+        .iter()
+        .map(|party| format!("{}:{}", party.address(), party.port()))
+        .collect_vec();
+    println!("[party {}] connecting", my_id);
+    let mut network = Network::connect(my_id, &addresses)
+        .unwrap()
+        .map(TrackChannel::new);
+    let cons = network.channels_mut();
     let mut rng = AesRng::new();
 
     if my_id == 0 {
@@ -87,7 +94,7 @@ fn main() {
 
         println!("[receiver] init");
         let init_time = SystemTime::now();
-        let mut receiver = MultiPartyReceiver::init(&mut cons, &mut rng).unwrap();
+        let mut receiver = MultiPartyReceiver::init(cons, &mut rng).unwrap();
         println!(
             "- init time: {} ms",
             init_time.elapsed().unwrap().as_millis()
@@ -95,7 +102,7 @@ fn main() {
 
         println!("[receiver] receive");
         let receive_time = SystemTime::now();
-        let intersection = receiver.receive(&inputs, &mut cons, &mut rng).unwrap();
+        let intersection = receiver.receive(&inputs, cons, &mut rng).unwrap();
         println!(
             "- receive time: {} ms",
             receive_time.elapsed().unwrap().as_millis()
@@ -104,7 +111,7 @@ fn main() {
 
         println!("[receiver] communication info:");
         let mut total = 0.0;
-        for (id, c) in cons {
+        for (id, c) in cons.iter() {
             println!(
                 "\tparty {:.2}: sent {:.2} mb, received {:.2} mb",
                 id,
@@ -129,79 +136,12 @@ fn main() {
         }
     } else {
         println!("[sender] init");
-        let mut sender = MultiPartySender::init(my_id, &mut cons, &mut rng).unwrap();
+        let mut sender = MultiPartySender::init(my_id, cons, &mut rng).unwrap();
         println!("[sender] send");
-        sender.send(&inputs, &mut cons, &mut rng).unwrap();
+        sender.send(&inputs, cons, &mut rng).unwrap();
     }
 }
 
-fn connect_to_parties(
-    my_id: usize,
-    config: &[PartyConfig],
-) -> Vec<(
-    usize,
-    TrackChannel<BufReader<TcpStream>, BufWriter<TcpStream>>,
-)> {
-    println!("[connect_to_parties party {}]", my_id);
-    // listen for connections from parties with ids less than me
-    // spawn a thread to accept connections
-    let my_config = config[my_id].clone();
-    let nparties = config.len();
-    let listener_thread = std::thread::spawn(move || {
-        let listener = TcpListener::bind(format!("localhost:{}", my_config.port())).unwrap();
-        listener
-            .incoming()
-            .take(my_id)
-            .map(|stream| {
-                let mut stream = stream.unwrap();
-                let id = read_usize(&mut stream);
-                println!("[{}] party {} connected to me", my_id, id);
-                (id, stream)
-            })
-            .collect_vec()
-    });
-
-    // connect to parties with ids greater than me
-    let mut cons = (0..nparties).map(|_| None).collect_vec();
-    for (id, party) in config.iter().enumerate().skip(my_id + 1) {
-        let addr = format!("{}:{}", party.address(), party.port())
-            .to_socket_addrs()
-            .unwrap()
-            .next()
-            .unwrap();
-        // wait for connection
-        let mut stream;
-        loop {
-            if let Ok(s) = TcpStream::connect(&addr) {
-                stream = s;
-                break;
-            } else {
-                std::thread::sleep(std::time::Duration::from_millis(10));
-            }
-        }
-        write_usize(my_id, &mut stream);
-        println!("[{}] connected to party {}", my_id, id);
-        cons[id] = Some((id, stream));
-    }
-
-    for (id, con) in listener_thread.join().unwrap().into_iter() {
-        cons[id] = Some((id, con))
-    }
-
-    cons.into_iter()
-        .flatten()
-        .map(|(id, stream)| {
-            (
-                id,
-                TrackChannel::new(
-                    BufReader::new(stream.try_clone().unwrap()),
-                    BufWriter::new(stream),
-                ),
-            )
-        })
-        .collect()
-}
-
 fn ipv6_to_block(addr: &str) -> Block {
     let mut nums = [0_u8; 16];
     for (i, hex) in addr.split(":").enumerate() {
@@ -219,14 +159,3 @@ fn block_to_ipv6(b: Block) -> String {
         .collect_vec();
     bs.chunks(2).map(|pair| pair.concat()).join(":")
 }
-
-fn read_usize<R: Read>(r: &mut R) -> usize {
-    let mut buf = [0; 8];
-    r.read(&mut buf).unwrap();
-    unsafe { std::mem::transmute(buf) }
-}
-
-fn write_usize<W: Write>(x: usize, w: &mut W) {
-    let buf: [u8; 8] = unsafe { std::mem::transmute(x) };
-    w.write(&buf).unwrap();
-}
@@ -5,43 +5,19 @@
 // See LICENSE for licensing information.
 
 use crate::Error;
-use scuttlebutt::{Aes128, Block};
+use scuttlebutt::{
+    cuckoo::{self, BinHasher, BlockHasher, CuckooItem, Parameters},
+    Block,
+};
 use std::fmt::Debug;
 
-#[derive(Clone, Debug)]
-pub(crate) struct CuckooItem {
-    // The actual value.
-    pub(crate) entry: Block,
-    // The input index associated with the entry.
-    pub(crate) input_index: usize,
-    // The hash index used. None for stash items.
-    pub(crate) hash_index: usize,
-}
+/// The statistical security parameter used to size cuckoo hash tables.
+const SSP: usize = 40;
 
 pub(crate) struct CuckooHash {
+    // The bins of the table, where each entry is XOR-ed with its hash index.
     pub(crate) items: Vec<Option<CuckooItem>>,
     pub(crate) nbins: usize,
-    pub(crate) nhashes: usize,
-}
-
-/// The number of times to loop when trying to place an entry in a bin.
-const NITERS: usize = 1000;
-
-fn compute_nbins(n: usize, nhashes: usize) -> Result<usize, Error> {
-    // Numbers taken from <https://thomaschneider.de/papers/PSZ18.pdf>, §3.2.2.
-    if nhashes == 3 {
-        if n < 1 << 27 {
-            Ok((1.27 * (n as f64)).ceil() as usize) // good up to set size 2^26
-        } else {
-            Ok((1.62 * (n as f64)).ceil() as usize) // required for 2^27
-        }
-    } else if nhashes == 4 {
-        Ok((1.09 * (n as f64)).ceil() as usize)
-    } else if nhashes == 5 {
-        Ok((1.05 * (n as f64)).ceil() as usize)
-    } else {
-        Err(Error::InvalidCuckooParameters { nitems: n, nhashes })
-    }
 }
 
 pub fn compute_masksize(n: usize) -> Result<usize, Error> {
@@ -69,70 +45,33 @@ impl CuckooHash {
     /// Build a new cuckoo hash table, hashing `inputs` in. We require that the
     /// lower-order-bits of the values in `inputs` are zero-ed out, as those
     /// bits will be used to store the hash index.
+    ///
+    /// The table has no stash: it is sized so that inserting all the inputs
+    /// fails with probability at most `2^-40`, in which case an error is
+    /// returned rather than leaking which inputs did not fit.
     pub fn new(inputs: &[Block], nhashes: usize) -> Result<CuckooHash, Error> {
-        let nbins = compute_nbins(inputs.len(), nhashes)?;
-
-        let mut tbl = CuckooHash {
-            items: vec![None; nbins],
-            nbins,
-            nhashes,
-        };
-
-        // Fill table with `inputs`.
-        for (j, input) in inputs.iter().enumerate() {
-            tbl.hash(*input, j)?;
-        }
-
-        Ok(tbl)
-    }
-
-    /// Place `input`, alongside the input index `idx` it corresponds to, in the
-    /// hash table.
-    pub fn hash(&mut self, input: Block, idx: usize) -> Result<(), Error> {
-        let mut item = CuckooItem {
-            entry: input,
-            input_index: idx,
-            hash_index: 0,
-        };
-        let mask = Block::from(0xFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FF00);
-
-        for _ in 0..NITERS {
-            item.entry &= mask;
-            let i = CuckooHash::bin(item.entry, item.hash_index, self.nbins);
-            item.entry ^= Block::from(item.hash_index as u128);
-            let opt_item = self.items[i].replace(item);
-            if let Some(x) = opt_item {
-                // If there is an item already in the bin, keep iterating,
-                // trying to place the new item.
-                item = x;
-                // Bump the hash index.
-                item.hash_index = (item.hash_index + 1) % self.nhashes;
-            } else {
-                return Ok(());
-            }
-        }
-        Err(Error::CuckooHashFull)
+        let params = Parameters::with_statistical_security(inputs.len(), nhashes, SSP)?;
+        let table = cuckoo::CuckooHash::build(inputs, params, BlockHasher)?;
+        let items = table
+            .items
+            .into_iter()
+            .map(|opt_item| {
+                opt_item.map(|item| CuckooItem {
+                    entry: item.entry ^ Block::from(item.hash_index as u128),
+                    ..item
+                })
+            })
+            .collect();
+        Ok(CuckooHash {
+            items,
+            nbins: params.nbins,
+        })
     }
 
     /// Output the bin number for a given hash output `hash` and hash index `hidx`.
+    #[inline]
     pub fn bin(hash: Block, hidx: usize, nbins: usize) -> usize {
-        // The first 15 bytes of `hash` are uniformly(-ish) random, so we use it
-        // directly to determine our bin by indexing the `hidx`th 32 bits of
-        // `hash` then mod-ing it by `nbins`. We can't do this for more than
-        // three hash functions though, as the last byte is *not*
-        // uniformly(-ish) random. Instead, we run it through AES (slow!).
-        if hidx < 3 {
-            let mut array = [0u8; 4];
-            let bytes: [u8; 16] = hash.into();
-            array.copy_from_slice(&bytes[4 * hidx..4 * (hidx + 1)]);
-            let value = u32::from_le_bytes(array);
-            (value as usize) % nbins
-        } else {
-            // In this case, compute `AES_{hash}(hidx)`.
-            let aes = Aes128::new(hash);
-            let h = aes.encrypt(Block::from(hidx as u128));
-            (u128::from(h) % (nbins as u128)) as usize
-        }
+        BlockHasher.bin(hash, hidx, nbins)
     }
 }
 
@@ -28,22 +28,13 @@ pub enum Error {
     PsiProtocolError(String),
     /// Not enough payloads.
     InvalidPayloadsLength,
-    /// SSL Error
-    #[cfg(feature = "psty")]
-    SSLError(openssl::error::ErrorStack),
+    /// Unable to encode the oblivious key-value store.
+    OkvsEncodingFailed,
     /// An error occurred in the underlying 2PC protocol.
     #[cfg(feature = "psty")]
     TwopcError(fancy_garbling::errors::TwopacError),
 }
 
-#[cfg(feature = "psty")]
-impl From<openssl::error::ErrorStack> for Error {
-    #[inline]
-    fn from(e: openssl::error::ErrorStack) -> Error {
-        Error::SSLError(e)
-    }
-}
-
 impl From<std::io::Error> for Error {
     #[inline]
     fn from(e: std::io::Error) -> Error {
@@ -65,6 +56,21 @@ impl From<scuttlebutt::cointoss::Error> for Error {
     }
 }
 
+impl From<scuttlebutt::cuckoo::Error> for Error {
+    #[inline]
+    fn from(e: scuttlebutt::cuckoo::Error) -> Error {
+        match e {
+            scuttlebutt::cuckoo::Error::InvalidParameters { nitems, nhashes } => {
+                Error::InvalidCuckooParameters { nitems, nhashes }
+            }
+            scuttlebutt::cuckoo::Error::CuckooHashFull => Error::CuckooHashFull,
+            scuttlebutt::cuckoo::Error::InvalidFingerprintSize(n) => {
+                Error::PsiProtocolError(format!("invalid fingerprint size: {}", n))
+            }
+        }
+    }
+}
+
 #[cfg(feature = "psty")]
 impl From<fancy_garbling::errors::TwopacError> for Error {
     #[inline]
@@ -90,8 +96,7 @@ impl std::fmt::Display for Error {
             ),
             Error::PsiProtocolError(s) => write!(f, "PSI protocol error: {}", s),
             Error::InvalidPayloadsLength => write!(f, "Invalid length of payloads!"),
-            #[cfg(feature = "psty")]
-            Error::SSLError(e) => write!(f, "SSL Error: {}", e),
+            Error::OkvsEncodingFailed => write!(f, "OKVS error: unable to encode key-value pairs"),
             #[cfg(feature = "psty")]
             Error::TwopcError(e) => write!(f, "2PC protocol error: {}", e),
         }
@@ -13,6 +13,7 @@
 
 mod cuckoo;
 mod errors;
+mod okvs;
 mod psi;
 mod utils;
 
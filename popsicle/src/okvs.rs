@@ -0,0 +1,241 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `popsicle`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of the PaXoS oblivious key-value store (cf.
+//! <https://eprint.iacr.org/2020/193>, §4).
+//!
+//! A key `x` is hashed to two positions `h₁(x)` and `h₂(x)` of a sparse vector
+//! of length `m`, and to a bit vector `d(x)` of length `d`. The encoding is a
+//! vector `P` of length `m + d` such that `P[h₁(x)] ⊕ P[h₂(x)] ⊕ ⟨d(x),
+//! P[m..]⟩` equals the value of `x` for every key. Encoding builds the cuckoo
+//! graph with an edge `(h₁(x), h₂(x))` per key, peels off all edges not on a
+//! cycle, and solves the remaining few equations using the dense part.
+//! Decoding is linear in `P`, which is what the PSI protocol relies on.
+
+use crate::Error;
+use rand::{distributions::Standard, prelude::Distribution, CryptoRng, Rng};
+use scuttlebutt::{Aes128, Block};
+use std::ops::BitXor;
+
+// The statistical security parameter.
+const SSP: usize = 40;
+// The size of the sparse part, relative to the number of keys.
+const EXPANSION: f64 = 2.4;
+
+/// The PaXoS oblivious key-value store.
+pub struct Paxos {
+    // The size of each half of the sparse part.
+    nhalf: usize,
+    // The size of the dense part.
+    ndense: usize,
+    aes: Aes128,
+}
+
+impl Paxos {
+    /// Create a key-value store for up to `n` keys, using the hash key `key`.
+    pub fn new(n: usize, key: Block) -> Self {
+        let nhalf = (EXPANSION * n as f64 / 2.0).ceil() as usize + 1;
+        let ndense = SSP + (n.max(2) as f64).log2().ceil() as usize;
+        Self {
+            nhalf,
+            ndense,
+            aes: Aes128::new(key),
+        }
+    }
+
+    /// Return the length of an encoding.
+    pub fn size(&self) -> usize {
+        2 * self.nhalf + self.ndense
+    }
+
+    // Compute the two sparse positions and the dense bits of `x`. The second
+    // position always lies in the second half, so the cuckoo graph is bipartite
+    // and has no self-loops.
+    #[inline]
+    fn hash(&self, x: Block) -> (usize, usize, u128) {
+        let h = u128::from(self.aes.encrypt(x));
+        let d = u128::from(self.aes.encrypt(x ^ Block::from(1u128)));
+        let reduce = |h: u64| ((h as u128 * self.nhalf as u128) >> 64) as usize;
+        let h1 = reduce(h as u64);
+        let h2 = self.nhalf + reduce((h >> 64) as u64);
+        (h1, h2, d & ((1 << self.ndense) - 1))
+    }
+
+    // Compute `⟨d, P[m..]⟩`.
+    #[inline]
+    fn dense<V: Copy + Default + BitXor<Output = V>>(&self, table: &[V], mut d: u128) -> V {
+        let dense = &table[2 * self.nhalf..];
+        let mut out = V::default();
+        while d != 0 {
+            out = out ^ dense[d.trailing_zeros() as usize];
+            d &= d - 1;
+        }
+        out
+    }
+
+    /// Decode the value of `x` from the encoding `table`.
+    #[inline]
+    pub fn decode<V: Copy + Default + BitXor<Output = V>>(&self, table: &[V], x: Block) -> V {
+        let (h1, h2, d) = self.hash(x);
+        table[h1] ^ table[h2] ^ self.dense(table, d)
+    }
+
+    /// Encode the key-value pairs given by `keys` and `values`. Positions that
+    /// are not determined by the key-value pairs are filled in at random.
+    pub fn encode<V, RNG>(
+        &self,
+        keys: &[Block],
+        values: &[V],
+        rng: &mut RNG,
+    ) -> Result<Vec<V>, Error>
+    where
+        V: Copy + Default + PartialEq + BitXor<Output = V>,
+        Standard: Distribution<V>,
+        RNG: CryptoRng + Rng,
+    {
+        let n = keys.len();
+        let m = 2 * self.nhalf;
+        let hashes = keys.iter().map(|x| self.hash(*x)).collect::<Vec<_>>();
+
+        // Peel the cuckoo graph: repeatedly remove an edge incident to a vertex
+        // of degree one. We keep the XOR of the incident edge indices for each
+        // vertex, which gives the remaining edge when the degree is one.
+        let mut degrees = vec![0usize; m];
+        let mut incident = vec![0usize; m];
+        for (e, (h1, h2, _)) in hashes.iter().enumerate() {
+            for v in [*h1, *h2].iter() {
+                degrees[*v] += 1;
+                incident[*v] ^= e;
+            }
+        }
+        let mut queue = (0..m).filter(|v| degrees[*v] == 1).collect::<Vec<_>>();
+        let mut peeled = Vec::with_capacity(n);
+        let mut removed = vec![false; n];
+        while let Some(v) = queue.pop() {
+            if degrees[v] != 1 {
+                continue;
+            }
+            let e = incident[v];
+            let (h1, h2, _) = hashes[e];
+            peeled.push((e, v));
+            removed[e] = true;
+            for w in [h1, h2].iter() {
+                degrees[*w] -= 1;
+                incident[*w] ^= e;
+                if degrees[*w] == 1 {
+                    queue.push(*w);
+                }
+            }
+        }
+
+        let mut table = (0..self.size()).map(|_| rng.gen()).collect::<Vec<V>>();
+
+        // Solve the equations of the edges left over in the 2-core, over the
+        // sparse positions they touch and the dense part.
+        let core = (0..n).filter(|e| !removed[*e]).collect::<Vec<_>>();
+        if !core.is_empty() {
+            let mut vars = core
+                .iter()
+                .flat_map(|e| vec![hashes[*e].0, hashes[*e].1])
+                .collect::<Vec<_>>();
+            vars.sort_unstable();
+            vars.dedup();
+            vars.extend(m..m + self.ndense);
+            let rows = core
+                .iter()
+                .map(|e| {
+                    let (h1, h2, d) = hashes[*e];
+                    let mut row = vec![false; vars.len()];
+                    row[vars.binary_search(&h1).unwrap()] = true;
+                    row[vars.binary_search(&h2).unwrap()] = true;
+                    for (j, r) in row[vars.len() - self.ndense..].iter_mut().enumerate() {
+                        *r = (d >> j) & 1 == 1;
+                    }
+                    (row, values[*e])
+                })
+                .collect::<Vec<_>>();
+            solve(rows, &vars, &mut table)?;
+        }
+
+        // Assign the peeled edges in reverse order, so that the other endpoint
+        // of each edge is already fixed.
+        for (e, v) in peeled.into_iter().rev() {
+            let (h1, h2, d) = hashes[e];
+            let w = if v == h1 { h2 } else { h1 };
+            table[v] = values[e] ^ table[w] ^ self.dense(&table, d);
+        }
+        Ok(table)
+    }
+}
+
+// Solve the linear system `rows` over `GF(2)` using Gauss-Jordan elimination,
+// where the `j`th column corresponds to `table[vars[j]]`. Free variables keep
+// their (random) values.
+fn solve<V: Copy + Default + PartialEq + BitXor<Output = V>>(
+    mut rows: Vec<(Vec<bool>, V)>,
+    vars: &[usize],
+    table: &mut [V],
+) -> Result<(), Error> {
+    let mut pivots = Vec::with_capacity(rows.len());
+    let mut r = 0;
+    for col in 0..vars.len() {
+        if r == rows.len() {
+            break;
+        }
+        let pivot = match (r..rows.len()).find(|i| rows[*i].0[col]) {
+            Some(pivot) => pivot,
+            None => continue,
+        };
+        rows.swap(r, pivot);
+        let (row, value) = rows[r].clone();
+        for (i, (other, v)) in rows.iter_mut().enumerate() {
+            if i != r && other[col] {
+                for (a, b) in other.iter_mut().zip(row.iter()) {
+                    *a ^= *b;
+                }
+                *v = *v ^ value;
+            }
+        }
+        pivots.push(col);
+        r += 1;
+    }
+    // Any remaining rows are all zero, so the system is only consistent if
+    // their values are zero too, e.g. for duplicate keys with equal values.
+    if rows[r..].iter().any(|(_, value)| *value != V::default()) {
+        return Err(Error::OkvsEncodingFailed);
+    }
+    for ((row, value), col) in rows.into_iter().zip(pivots) {
+        let mut out = value;
+        for (j, b) in row.iter().enumerate() {
+            if *b && j != col {
+                out = out ^ table[vars[j]];
+            }
+        }
+        table[vars[col]] = out;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scuttlebutt::AesRng;
+
+    #[test]
+    fn test_paxos() {
+        let mut rng = AesRng::new();
+        for n in [1, 2, 3, 10, 100, 1000, 10000].iter() {
+            let keys = (0..*n).map(|_| rng.gen()).collect::<Vec<Block>>();
+            let values = (0..*n).map(|_| rng.gen()).collect::<Vec<Block>>();
+            let paxos = Paxos::new(*n, rng.gen());
+            let table = paxos.encode(&keys, &values, &mut rng).unwrap();
+            assert_eq!(table.len(), paxos.size());
+            for (key, value) in keys.iter().zip(values.iter()) {
+                assert_eq!(paxos.decode(&table, *key), *value);
+            }
+        }
+    }
+}
@@ -0,0 +1,403 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `popsicle`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of the classic Diffie-Hellman private set intersection
+//! protocol (cf. Meadows, <https://doi.org/10.1109/SP.1986.10022>, and
+//! Huberman-Franklin-Hogg, <https://doi.org/10.1145/336992.337012>) over the
+//! Ristretto group.
+//!
+//! The receiver sends `H(y)^r` for each of its inputs `y` and a fresh scalar
+//! `r`, and the sender replies with `H(y)^{rk}` for its key `k`, alongside the
+//! tags `H'(x, H(x)^k)` of its own inputs `x`. The receiver removes `r` and
+//! checks which of its tags `H'(y, H(y)^k)` appear in the sender's set.
+//!
+//! Communication is linear in the set sizes, with only a single round trip, so
+//! this protocol is a good fit for small sets and for high-latency,
+//! low-bandwidth links. Since the sender's tags only depend on its key and
+//! inputs, the sender can encode its set once (see `Sender::encode`) and reuse
+//! it across many sessions with different receivers.
+
+use crate::Error;
+use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar};
+use rand::{CryptoRng, Rng, RngCore};
+use scuttlebutt::{AbstractChannel, Block, SemiHonest};
+use sha2::{Digest, Sha512};
+use std::collections::{HashMap, HashSet};
+
+// Domain separator used when hashing inputs into the group.
+const HASH_TO_POINT_PREFIX: &[u8] = b"popsicle::psi::ecdh::H";
+// Domain separator used when hashing group elements into tags and keys.
+const HASH_TO_TAG_PREFIX: &[u8] = b"popsicle::psi::ecdh::H'";
+
+// Hash `input` into the Ristretto group.
+#[inline]
+fn hash_to_point(input: &[u8]) -> RistrettoPoint {
+    let mut h = Sha512::new();
+    h.input(HASH_TO_POINT_PREFIX);
+    h.input(input);
+    RistrettoPoint::from_hash(h)
+}
+
+// Compute the tag and payload key of `input` from the point `H(input)^k`.
+#[inline]
+fn hash_to_tag(input: &[u8], pt: &RistrettoPoint) -> (Block, Block) {
+    let mut h = Sha512::new();
+    h.input(HASH_TO_TAG_PREFIX);
+    h.input((input.len() as u64).to_le_bytes());
+    h.input(input);
+    h.input(pt.compress().as_bytes());
+    let h = h.result();
+    let mut tag = [0u8; 16];
+    let mut key = [0u8; 16];
+    tag.copy_from_slice(&h[0..16]);
+    key.copy_from_slice(&h[16..32]);
+    (Block::from(tag), Block::from(key))
+}
+
+/// A sender's set, encoded under the sender's key.
+///
+/// The encoding reveals nothing about the inputs without the key, and can be
+/// reused across any number of sessions run with the same key.
+#[derive(Clone, Debug, Default)]
+pub struct EncodedSet {
+    // The tags, sorted so as to hide the order of the inputs.
+    tags: Vec<Block>,
+    // The encrypted payloads, in the same order as the tags, if any.
+    payloads: Option<Vec<Block>>,
+}
+
+impl EncodedSet {
+    /// Return the number of encoded inputs.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Return whether the encoded set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Return whether the encoded set contains payloads.
+    pub fn has_payloads(&self) -> bool {
+        self.payloads.is_some()
+    }
+}
+
+/// Private set intersection sender.
+pub struct Sender {
+    key: Scalar,
+}
+
+/// Private set intersection receiver.
+pub struct Receiver {}
+
+impl Sender {
+    /// Initialize the PSI sender with a random key.
+    pub fn init<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        _: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        Ok(Self::from_key(Scalar::random(rng)))
+    }
+
+    /// Create a PSI sender using the key `key`. Sets encoded under the same key
+    /// can be reused across senders.
+    pub fn from_key(key: Scalar) -> Self {
+        Self { key }
+    }
+
+    /// Return the sender's key.
+    pub fn key(&self) -> &Scalar {
+        &self.key
+    }
+
+    /// Encode `inputs` under the sender's key.
+    pub fn encode(&self, inputs: &[Vec<u8>]) -> EncodedSet {
+        let mut tags = inputs
+            .iter()
+            .map(|x| hash_to_tag(x, &(hash_to_point(x) * self.key)).0)
+            .collect::<Vec<Block>>();
+        tags.sort();
+        EncodedSet {
+            tags,
+            payloads: None,
+        }
+    }
+
+    /// Encode `inputs` under the sender's key, alongside the associated
+    /// `payloads`, each encrypted under a key derived from its input.
+    pub fn encode_payloads(
+        &self,
+        inputs: &[Vec<u8>],
+        payloads: &[Block],
+    ) -> Result<EncodedSet, Error> {
+        if inputs.len() != payloads.len() {
+            return Err(Error::InvalidPayloadsLength);
+        }
+        let mut entries = inputs
+            .iter()
+            .zip(payloads.iter())
+            .map(|(x, payload)| {
+                let (tag, key) = hash_to_tag(x, &(hash_to_point(x) * self.key));
+                (tag, *payload ^ key)
+            })
+            .collect::<Vec<(Block, Block)>>();
+        entries.sort();
+        let (tags, payloads) = entries.into_iter().unzip();
+        Ok(EncodedSet {
+            tags,
+            payloads: Some(payloads),
+        })
+    }
+
+    /// Run the PSI protocol over `inputs`.
+    pub fn send<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<(), Error> {
+        let set = self.encode(inputs);
+        self.send_encoded(&set, channel, rng)
+    }
+
+    /// Run the PSI protocol over `inputs`. Returns a random payload for each
+    /// input, which the receiver learns for each input in the intersection.
+    pub fn send_payloads<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Vec<Block>, Error> {
+        let payloads = (0..inputs.len())
+            .map(|_| rng.gen::<Block>())
+            .collect::<Vec<Block>>();
+        let set = self.encode_payloads(inputs, &payloads)?;
+        self.send_encoded(&set, channel, rng)?;
+        Ok(payloads)
+    }
+
+    /// Run the PSI protocol over a set previously encoded using `encode` or
+    /// `encode_payloads`. If the set contains payloads, the receiver must use
+    /// `Receiver::receive_payloads`.
+    pub fn send_encoded<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        set: &EncodedSet,
+        channel: &mut C,
+        _: &mut RNG,
+    ) -> Result<(), Error> {
+        let n = channel.read_usize()?;
+        let pts = (0..n)
+            .map(|_| channel.read_pt())
+            .collect::<Result<Vec<RistrettoPoint>, std::io::Error>>()?;
+        for pt in pts.iter() {
+            channel.write_pt(&(pt * self.key))?;
+        }
+        channel.write_usize(set.tags.len())?;
+        for tag in set.tags.iter() {
+            channel.write_block(tag)?;
+        }
+        if let Some(payloads) = &set.payloads {
+            for payload in payloads.iter() {
+                channel.write_block(payload)?;
+            }
+        }
+        channel.flush()?;
+        Ok(())
+    }
+}
+
+impl Receiver {
+    /// Initialize the PSI receiver.
+    pub fn init<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        _: &mut C,
+        _: &mut RNG,
+    ) -> Result<Self, Error> {
+        Ok(Self {})
+    }
+
+    /// Run the PSI protocol over `inputs`.
+    pub fn receive<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let tags = self.compute_tags(inputs, channel, rng)?;
+        let m = channel.read_usize()?;
+        let mut set = HashSet::with_capacity(m);
+        for _ in 0..m {
+            set.insert(channel.read_block()?);
+        }
+        Ok(inputs
+            .iter()
+            .zip(tags)
+            .filter(|(_, (tag, _))| set.contains(tag))
+            .map(|(x, _)| x.clone())
+            .collect())
+    }
+
+    /// Run the PSI protocol over `inputs`, receiving a map from the
+    /// intersection items to their associated payloads.
+    pub fn receive_payloads<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<HashMap<Vec<u8>, Block>, Error> {
+        let tags = self.compute_tags(inputs, channel, rng)?;
+        let m = channel.read_usize()?;
+        let tags_ = (0..m)
+            .map(|_| channel.read_block())
+            .collect::<Result<Vec<Block>, std::io::Error>>()?;
+        let mut set = HashMap::with_capacity(m);
+        for tag in tags_.into_iter() {
+            set.insert(tag, channel.read_block()?);
+        }
+        Ok(inputs
+            .iter()
+            .zip(tags)
+            .filter_map(|(x, (tag, key))| set.get(&tag).map(|ct| (x.clone(), *ct ^ key)))
+            .collect())
+    }
+
+    // Helper to blind `inputs`, send them to the sender, and compute the tags
+    // and payload keys from the sender's response.
+    fn compute_tags<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Vec<(Block, Block)>, Error> {
+        let r = Scalar::random(rng);
+        channel.write_usize(inputs.len())?;
+        for x in inputs.iter() {
+            channel.write_pt(&(hash_to_point(x) * r))?;
+        }
+        channel.flush()?;
+        let r_inv = r.invert();
+        inputs
+            .iter()
+            .map(|x| {
+                let pt = channel.read_pt()?;
+                Ok(hash_to_tag(x, &(pt * r_inv)))
+            })
+            .collect()
+    }
+}
+
+impl SemiHonest for Sender {}
+impl SemiHonest for Receiver {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::rand_vec_vec;
+    use scuttlebutt::{AesRng, Channel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    const ITEM_SIZE: usize = 8;
+    const SET_SIZE: usize = 1 << 8;
+
+    #[test]
+    fn test_psi() {
+        let mut rng = AesRng::new();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let intersection = rand_vec_vec(SET_SIZE / 2, ITEM_SIZE, &mut rng);
+        let mut sender_inputs = rand_vec_vec(SET_SIZE / 2, ITEM_SIZE, &mut rng);
+        // Use a differently sized set for the receiver.
+        let mut receiver_inputs = rand_vec_vec(SET_SIZE, 2 * ITEM_SIZE, &mut rng);
+        sender_inputs.extend(intersection.clone());
+        receiver_inputs.extend(intersection.clone());
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut psi = Sender::init(&mut channel, &mut rng).unwrap();
+            psi.send(&sender_inputs, &mut channel, &mut rng).unwrap();
+        });
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi = Receiver::init(&mut channel, &mut rng).unwrap();
+        let result = psi
+            .receive(&receiver_inputs, &mut channel, &mut rng)
+            .unwrap();
+        handle.join().unwrap();
+        assert_eq!(result, intersection);
+    }
+
+    #[test]
+    fn test_payloads() {
+        let mut rng = AesRng::new();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let intersection = rand_vec_vec(SET_SIZE / 2, ITEM_SIZE, &mut rng);
+        let mut sender_inputs = rand_vec_vec(SET_SIZE / 2, ITEM_SIZE, &mut rng);
+        let mut receiver_inputs = rand_vec_vec(SET_SIZE / 2, ITEM_SIZE, &mut rng);
+        sender_inputs.extend(intersection.clone());
+        receiver_inputs.extend(intersection.clone());
+        let sender_inputs_ = sender_inputs.clone();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut psi = Sender::init(&mut channel, &mut rng).unwrap();
+            psi.send_payloads(&sender_inputs_, &mut channel, &mut rng)
+                .unwrap()
+        });
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi = Receiver::init(&mut channel, &mut rng).unwrap();
+        let result = psi
+            .receive_payloads(&receiver_inputs, &mut channel, &mut rng)
+            .unwrap();
+        let payloads = handle.join().unwrap();
+        assert_eq!(result.len(), intersection.len());
+        for (x, payload) in sender_inputs.iter().zip(payloads.iter()) {
+            if let Some(payload_) = result.get(x) {
+                assert_eq!(payload, payload_);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reuse_encoded_set() {
+        let mut rng = AesRng::new();
+        let sender_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        let key = Scalar::random(&mut rng);
+        let set = Sender::from_key(key).encode(&sender_inputs);
+        assert_eq!(set.len(), SET_SIZE);
+        for nshared in [0, 1, SET_SIZE / 4].iter() {
+            let (sender, receiver) = UnixStream::pair().unwrap();
+            let set_ = set.clone();
+            let handle = std::thread::spawn(move || {
+                let mut rng = AesRng::new();
+                let reader = BufReader::new(sender.try_clone().unwrap());
+                let writer = BufWriter::new(sender);
+                let mut channel = Channel::new(reader, writer);
+                let mut psi = Sender::from_key(key);
+                psi.send_encoded(&set_, &mut channel, &mut rng).unwrap();
+            });
+            let mut receiver_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+            receiver_inputs.extend(sender_inputs[0..*nshared].iter().cloned());
+            let reader = BufReader::new(receiver.try_clone().unwrap());
+            let writer = BufWriter::new(receiver);
+            let mut channel = Channel::new(reader, writer);
+            let mut psi = Receiver::init(&mut channel, &mut rng).unwrap();
+            let result = psi
+                .receive(&receiver_inputs, &mut channel, &mut rng)
+                .unwrap();
+            handle.join().unwrap();
+            assert_eq!(result, sender_inputs[0..*nshared].to_vec());
+        }
+    }
+}
@@ -6,30 +6,85 @@
 
 //! Implementation of the "Kolesnikov-Matania-Pinkas-Rosulek-Trieu" multi-party private
 //! set intersection protocol (cf. <https://eprint.iacr.org/2017/799.pdf>).
+//!
+//! By default, party 0 learns the intersection, and the protocol is secure
+//! against any number of colluding parties. The output party can be changed
+//! using `Receiver::init_with_id` and `Sender::set_output_party`. With a
+//! collusion threshold `t` (see `set_threshold`), only parties `0, ..., t`
+//! deal secret shares of zero, as in KMPRT's augmented semi-honest protocol,
+//! which reduces the number of OPPRF instances from quadratic to `O(nt)`.
+//!
+//! With the `psty` feature, the parties can instead compute the cardinality of
+//! the intersection (see `Receiver::receive_cardinality`). The final
+//! reconstruction with the lowest-numbered party other than the output party
+//! (the "helper") then happens inside a garbled circuit, so that the output
+//! party only learns the count, unless it colludes with the helper.
 
 use crate::Error;
 use itertools::Itertools;
-use ocelot::oprf::{KmprtReceiver, KmprtSender};
-use rand::{CryptoRng, Rng, RngCore, SeedableRng};
-use scuttlebutt::{AbstractChannel, Block, Block512};
+use ocelot::oprf::{KmprtReceiver, KmprtSender, ProgrammableReceiver, ProgrammableSender};
+use rand::{CryptoRng, RngCore, SeedableRng};
+use scuttlebutt::{sharing::additive, AbstractChannel, Block, Block512};
+#[cfg(feature = "psty")]
+use {
+    crate::cuckoo::CuckooHash,
+    fancy_garbling::{
+        twopac::semihonest::{Evaluator, Garbler, PartyId as TwopacPartyId},
+        BinaryBundle,
+        BinaryGadgets,
+        BundleGadgets,
+        Fancy,
+        FancyInput,
+    },
+    ocelot::ot::{AlszReceiver as OtReceiver, AlszSender as OtSender},
+    rand::Rng,
+    scuttlebutt::AesHash,
+};
+
+// The number of hash functions used when hashing the output party's inputs
+// into bins for computing the cardinality.
+#[cfg(feature = "psty")]
+const NHASHES: usize = 3;
+// The number of keys to try when cuckoo hashing.
+#[cfg(feature = "psty")]
+const CUCKOO_NTRIES: usize = 8;
+// How many bytes of the reconstructed values to compare inside the garbled
+// circuit when computing the cardinality.
+#[cfg(feature = "psty")]
+const EQ_SIZE: usize = 8;
 
 /// The party number for each party.
 pub type PartyId = usize;
 
 /// Base KMPRT Party.
-struct Party {
+struct Party<OPPRFS, OPPRFR> {
     id: PartyId,
-    opprf_senders: Vec<KmprtSender>,
-    opprf_receivers: Vec<KmprtReceiver>,
+    nparties: usize,
+    // The party learning the output.
+    output: PartyId,
+    // The maximum number of colluding parties.
+    threshold: usize,
+    opprf_senders: Vec<OPPRFS>,
+    opprf_receivers: Vec<OPPRFR>,
 }
 
 /// KMPRT sender - there can be many of these.
-pub struct Sender(Party);
+///
+/// The parties are generic over the OPPRF used between each pair of parties,
+/// and use the KMPRT hashing-based OPPRF by default.
+pub struct Sender<OPPRFS = KmprtSender, OPPRFR = KmprtReceiver>(Party<OPPRFS, OPPRFR>);
 
 /// KMPRT receiver - there can only be one of these.
-pub struct Receiver(Party);
-
-impl Sender {
+///
+/// The parties are generic over the OPPRF used between each pair of parties,
+/// and use the KMPRT hashing-based OPPRF by default.
+pub struct Receiver<OPPRFS = KmprtSender, OPPRFR = KmprtReceiver>(Party<OPPRFS, OPPRFR>);
+
+impl<OPPRFS, OPPRFR> Sender<OPPRFS, OPPRFR>
+where
+    OPPRFS: ProgrammableSender<Input = Block, Output = Block512>,
+    OPPRFR: ProgrammableReceiver<Input = Block, Output = Block512>,
+{
     /// Initialize a PSI sender.
     pub fn init<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
         me: PartyId,
@@ -39,6 +94,19 @@ impl Sender {
         Party::init(me, channels, rng).map(Self)
     }
 
+    /// Set the party learning the output, which is party 0 by default. All
+    /// senders must agree on the output party.
+    pub fn set_output_party(&mut self, output: PartyId) -> Result<(), Error> {
+        self.0.set_output_party(output)
+    }
+
+    /// Set the maximum number of colluding parties `threshold`, which is the
+    /// number of parties minus one by default. All parties must use the same
+    /// threshold.
+    pub fn set_threshold(&mut self, threshold: usize) -> Result<(), Error> {
+        self.0.set_threshold(threshold)
+    }
+
     /// Send inputs to all parties and particpate in one party receiving the output.
     pub fn send<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
         &mut self,
@@ -46,19 +114,78 @@ impl Sender {
         channels: &mut [(PartyId, C)],
         rng: &mut RNG,
     ) -> Result<(), Error> {
-        assert!(self.0.id != 0);
+        let output = self.0.channel_index(self.0.output, channels)?;
 
         let s_hat = self.0.conditional_secret_sharing(inputs, channels, rng)?;
 
         // conditional reconstruction
         let points = inputs.iter().cloned().zip(s_hat.into_iter()).collect_vec();
-        self.0.opprf_senders[0].send(&mut channels[0].1, &points, inputs.len(), rng)?;
+        self.0.opprf_senders[output].send(&mut channels[output].1, &points, inputs.len(), rng)?;
+
+        Ok(())
+    }
+
+    /// Send inputs to all parties and participate in the output party
+    /// learning the cardinality of the intersection.
+    #[cfg(feature = "psty")]
+    pub fn send_cardinality<C, RNG>(
+        &mut self,
+        inputs: &[Block],
+        channels: &mut [(PartyId, C)],
+        rng: &mut RNG,
+    ) -> Result<(), Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+    {
+        if self.0.id != self.0.helper() {
+            return self.send(inputs, channels, rng);
+        }
+        let output = self.0.channel_index(self.0.output, channels)?;
+
+        let s_hat = self.0.conditional_secret_sharing(inputs, channels, rng)?;
 
+        // conditional reconstruction, where the output party hashes its inputs
+        // into bins and each bin gets a random target value
+        let channel = &mut channels[output].1;
+        let key = channel.read_block()?;
+        let nbins = channel.read_usize()?;
+        let hashes = hash_inputs(inputs, key);
+        let ts = (0..nbins).map(|_| rng.gen::<Block512>()).collect_vec();
+        let mut points = Vec::with_capacity(NHASHES * inputs.len());
+        for (x, s) in hashes.into_iter().zip(s_hat) {
+            let mut bins = Vec::with_capacity(NHASHES);
+            for h in 0..NHASHES {
+                let bin = CuckooHash::bin(x, h, nbins);
+                points.push((x ^ Block::from(h as u128), s ^ ts[bin]));
+                bins.push(bin);
+            }
+            // if all hashes map to the same bin, add a random element to it so
+            // as not to reveal this
+            if bins.iter().skip(1).all(|&b| b == bins[0]) {
+                points.push((rng.gen(), rng.gen()));
+            }
+        }
+        self.0.opprf_senders[output].send(channel, &points, nbins, rng)?;
+
+        // check which bins match inside a garbled circuit
+        let mut gb = Garbler::<&mut C, RNG, OtSender>::new(channel, RNG::from_seed(rng.gen()))?;
+        let bits = encode_targets(&ts);
+        let mods = vec![2; bits.len()];
+        let xs = gb.encode_many(&bits, &mods)?;
+        let ys = gb.receive_many(TwopacPartyId::Evaluator, &mods)?;
+        let outs = fancy_compute_cardinality(&mut gb, &xs, &ys, nbins)?;
+        gb.outputs(&outs)?;
+        channel.flush()?;
         Ok(())
     }
 }
 
-impl Receiver {
+impl<OPPRFS, OPPRFR> Receiver<OPPRFS, OPPRFR>
+where
+    OPPRFS: ProgrammableSender<Input = Block, Output = Block512>,
+    OPPRFR: ProgrammableReceiver<Input = Block, Output = Block512>,
+{
     /// Initialize the PSI receiver.
     pub fn init<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
         channels: &mut [(PartyId, C)],
@@ -67,6 +194,26 @@ impl Receiver {
         Party::init(0, channels, rng).map(Self)
     }
 
+    /// Initialize the PSI receiver as party `me`, which then learns the
+    /// output instead of party 0. The senders must call `set_output_party`
+    /// with `me`.
+    pub fn init_with_id<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
+        me: PartyId,
+        channels: &mut [(PartyId, C)],
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let mut party = Party::init(me, channels, rng)?;
+        party.output = me;
+        Ok(Self(party))
+    }
+
+    /// Set the maximum number of colluding parties `threshold`, which is the
+    /// number of parties minus one by default. All parties must use the same
+    /// threshold.
+    pub fn set_threshold(&mut self, threshold: usize) -> Result<(), Error> {
+        self.0.set_threshold(threshold)
+    }
+
     /// Send inputs and receive result - only one party should call this.
     pub fn receive<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
         &mut self,
@@ -75,14 +222,7 @@ impl Receiver {
         rng: &mut RNG,
     ) -> Result<Vec<Block>, Error> {
         let mut s_hat = self.0.conditional_secret_sharing(inputs, channels, rng)?;
-
-        // conditional reconstruction
-        for (channel_num, (_, channel)) in channels.iter_mut().enumerate() {
-            let shares = self.0.opprf_receivers[channel_num].receive(channel, inputs, rng)?;
-            for (i, share) in shares.into_iter().enumerate() {
-                s_hat[i] ^= share;
-            }
-        }
+        self.0.reconstruct(&mut s_hat, inputs, None, channels, rng)?;
 
         let intersection = inputs
             .iter()
@@ -98,9 +238,74 @@ impl Receiver {
 
         Ok(intersection)
     }
+
+    /// Send inputs and receive the cardinality of the intersection - only one
+    /// party should call this, and the others must call
+    /// `Sender::send_cardinality`.
+    #[cfg(feature = "psty")]
+    pub fn receive_cardinality<C, RNG>(
+        &mut self,
+        inputs: &[Block],
+        channels: &mut [(PartyId, C)],
+        rng: &mut RNG,
+    ) -> Result<usize, Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+    {
+        let helper = self.0.channel_index(self.0.helper(), channels)?;
+
+        let mut s_hat = self.0.conditional_secret_sharing(inputs, channels, rng)?;
+
+        // conditional reconstruction, except for the helper's shares, which
+        // are programmed per cuckoo bin
+        let (key, cuckoo) = cuckoo_hash(inputs, rng)?;
+        let table = cuckoo
+            .items
+            .iter()
+            .map(|opt_item| match opt_item {
+                Some(item) => item.entry,
+                None => rng.gen(),
+            })
+            .collect_vec();
+        let helper_id = self.0.helper();
+        self.0.reconstruct(&mut s_hat, inputs, Some(helper_id), channels, rng)?;
+        let channel = &mut channels[helper].1;
+        channel.write_block(&key)?;
+        channel.write_usize(cuckoo.nbins)?;
+        channel.flush()?;
+        let outputs = self.0.opprf_receivers[helper].receive(channel, &table, rng)?;
+
+        // the value of each bin matches the helper's target value if and only
+        // if the bin's element is in the intersection
+        let zs = cuckoo
+            .items
+            .iter()
+            .zip_eq(outputs)
+            .map(|(opt_item, output)| match opt_item {
+                Some(item) => s_hat[item.input_index] ^ output,
+                None => rng.gen(),
+            })
+            .collect_vec();
+
+        let mut ev = Evaluator::<&mut C, RNG, OtReceiver>::new(channel, RNG::from_seed(rng.gen()))?;
+        let bits = encode_targets(&zs);
+        let mods = vec![2; bits.len()];
+        let xs = ev.receive_many(TwopacPartyId::Garbler, &mods)?;
+        let ys = ev.encode_many(&bits, &mods)?;
+        let outs = fancy_compute_cardinality(&mut ev, &xs, &ys, cuckoo.nbins)?;
+        let outs = ev
+            .outputs(&outs)?
+            .expect("evaluator should produce outputs");
+        Ok(fancy_garbling::util::u128_from_bits(&outs) as usize)
+    }
 }
 
-impl Party {
+impl<OPPRFS, OPPRFR> Party<OPPRFS, OPPRFR>
+where
+    OPPRFS: ProgrammableSender<Input = Block, Output = Block512>,
+    OPPRFR: ProgrammableReceiver<Input = Block, Output = Block512>,
+{
     fn init<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
         me: PartyId,
         channels: &mut [(PartyId, C)],
@@ -112,21 +317,94 @@ impl Party {
         for (them, c) in channels.iter_mut() {
             // the party with the lowest PID gets to initialize their OPPRF sender first
             if me < *them {
-                opprf_senders.push(KmprtSender::init(c, rng)?);
-                opprf_receivers.push(KmprtReceiver::init(c, rng)?);
+                opprf_senders.push(OPPRFS::init(c, rng)?);
+                opprf_receivers.push(OPPRFR::init(c, rng)?);
             } else {
-                opprf_receivers.push(KmprtReceiver::init(c, rng)?);
-                opprf_senders.push(KmprtSender::init(c, rng)?);
+                opprf_receivers.push(OPPRFR::init(c, rng)?);
+                opprf_senders.push(OPPRFS::init(c, rng)?);
             }
         }
 
         Ok(Self {
             id: me,
+            nparties: channels.len() + 1,
+            output: 0,
+            threshold: channels.len(),
             opprf_senders,
             opprf_receivers,
         })
     }
 
+    fn set_output_party(&mut self, output: PartyId) -> Result<(), Error> {
+        if output == self.id || output >= self.nparties {
+            return Err(Error::PsiProtocolError(format!(
+                "invalid output party: {}",
+                output
+            )));
+        }
+        self.output = output;
+        Ok(())
+    }
+
+    fn set_threshold(&mut self, threshold: usize) -> Result<(), Error> {
+        if threshold == 0 || threshold >= self.nparties {
+            return Err(Error::PsiProtocolError(format!(
+                "invalid collusion threshold {} for {} parties",
+                threshold, self.nparties
+            )));
+        }
+        self.threshold = threshold;
+        Ok(())
+    }
+
+    // Whether `id` deals secret shares of zero. As at most `threshold` parties
+    // collude, at least one of the dealers is honest.
+    #[inline]
+    fn is_dealer(&self, id: PartyId) -> bool {
+        id <= self.threshold
+    }
+
+    // The party helping the output party compute the cardinality.
+    #[cfg(feature = "psty")]
+    #[inline]
+    fn helper(&self) -> PartyId {
+        if self.output == 0 {
+            1
+        } else {
+            0
+        }
+    }
+
+    // Find the index of the channel to party `id`.
+    fn channel_index<C>(&self, id: PartyId, channels: &[(PartyId, C)]) -> Result<usize, Error> {
+        channels
+            .iter()
+            .position(|(them, _)| *them == id)
+            .ok_or_else(|| Error::PsiProtocolError(format!("no channel to party {}", id)))
+    }
+
+    /// Receive the shares of all other parties except `skip` for `inputs` and
+    /// add them to `s_hat` - this phase is run by the output party.
+    fn reconstruct<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
+        &mut self,
+        s_hat: &mut [Block512],
+        inputs: &[Block],
+        skip: Option<PartyId>,
+        channels: &mut [(PartyId, C)],
+        rng: &mut RNG,
+    ) -> Result<(), Error> {
+        for (channel_num, (them, channel)) in channels.iter_mut().enumerate() {
+            if Some(*them) == skip {
+                continue;
+            }
+            let shares = self.opprf_receivers[channel_num].receive(channel, inputs, rng)?;
+            for (i, share) in shares.into_iter().enumerate() {
+                s_hat[i] ^= share;
+            }
+        }
+        Ok(())
+    }
+
     /// Share secret shares of zero using OPPRF, returning the xor of the OPPRF outputs -
     /// this phase is common to both the senders and the receiver.
     fn conditional_secret_sharing<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
@@ -135,33 +413,50 @@ impl Party {
         channels: &mut [(PartyId, C)],
         rng: &mut RNG,
     ) -> Result<Vec<Block512>, Error> {
-        let nparties = channels.len() + 1;
         let ninputs = inputs.len();
+        let dealer = self.is_dealer(self.id);
 
         let mut s_hat = vec![Block512::default(); ninputs];
 
-        let s = (0..ninputs)
-            .map(|i| {
-                let shares = secret_sharing_of_zero(nparties, rng);
-                s_hat[i] = shares[self.id];
-                shares
-            })
-            .collect_vec();
+        let s = if dealer {
+            (0..ninputs)
+                .map(|i| {
+                    let shares = additive::zero_sharing::<Block512, _>(self.nparties, rng);
+                    s_hat[i] = shares[self.id];
+                    shares
+                })
+                .collect_vec()
+        } else {
+            vec![]
+        };
 
         for (channel_num, (other_id, channel)) in channels.iter_mut().enumerate() {
-            let points = inputs
-                .iter()
-                .enumerate()
-                .map(|(i, x)| (*x, s[i][*other_id]))
-                .collect_vec();
+            let other_dealer = self.is_dealer(*other_id);
+            let points = if dealer {
+                inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, x)| (*x, s[i][*other_id]))
+                    .collect_vec()
+            } else {
+                vec![]
+            };
 
-            let bs;
+            let mut bs = vec![];
             if self.id < *other_id {
-                self.opprf_senders[channel_num].send(channel, &points, inputs.len(), rng)?;
-                bs = self.opprf_receivers[channel_num].receive(channel, inputs, rng)?;
+                if dealer {
+                    self.opprf_senders[channel_num].send(channel, &points, inputs.len(), rng)?;
+                }
+                if other_dealer {
+                    bs = self.opprf_receivers[channel_num].receive(channel, inputs, rng)?;
+                }
             } else {
-                bs = self.opprf_receivers[channel_num].receive(channel, inputs, rng)?;
-                self.opprf_senders[channel_num].send(channel, &points, inputs.len(), rng)?;
+                if other_dealer {
+                    bs = self.opprf_receivers[channel_num].receive(channel, inputs, rng)?;
+                }
+                if dealer {
+                    self.opprf_senders[channel_num].send(channel, &points, inputs.len(), rng)?;
+                }
             }
 
             for (i, b) in bs.into_iter().enumerate() {
@@ -173,22 +468,79 @@ impl Party {
     }
 }
 
-fn secret_sharing_of_zero<R: Rng>(nparties: usize, rng: &mut R) -> Vec<Block512> {
-    let mut sum = Block512::default();
-    let mut shares = (0..nparties - 1)
-        .map(|_| {
-            let b = rng.gen();
-            sum ^= b;
-            b
+// Hash `inputs` using `key`, leaving the lower-order bits zero for the hash
+// index.
+#[cfg(feature = "psty")]
+fn hash_inputs(inputs: &[Block], key: Block) -> Vec<Block> {
+    let aes = AesHash::new(key);
+    let mask = Block::from(0xFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FF00);
+    inputs
+        .iter()
+        .map(|x| aes.cr_hash(Block::default(), *x) & mask)
+        .collect()
+}
+
+// Cuckoo hash `inputs` under a random key. As the key is only revealed
+// afterwards, we can retry with a fresh key if hashing fails, which happens
+// with non-negligible probability for small sets.
+#[cfg(feature = "psty")]
+fn cuckoo_hash<RNG: RngCore + CryptoRng>(
+    inputs: &[Block],
+    rng: &mut RNG,
+) -> Result<(Block, CuckooHash), Error> {
+    let mut result = Err(Error::CuckooHashFull);
+    for _ in 0..CUCKOO_NTRIES {
+        let key = rng.gen();
+        result = CuckooHash::new(&hash_inputs(inputs, key), NHASHES).map(|cuckoo| (key, cuckoo));
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+// Encode the first `EQ_SIZE` bytes of each value as bits.
+#[cfg(feature = "psty")]
+fn encode_targets(values: &[Block512]) -> Vec<u16> {
+    values
+        .iter()
+        .flat_map(|v| {
+            v.prefix(EQ_SIZE)
+                .iter()
+                .flat_map(|byte| (0..8).map(move |i| u16::from((byte >> i) & 1)))
+                .collect_vec()
         })
-        .collect_vec();
-    shares.push(sum);
-    shares
+        .collect()
+}
+
+// Fancy function to count the bins in which `xs` and `ys` match, outputting
+// the count in binary.
+#[cfg(feature = "psty")]
+fn fancy_compute_cardinality<F: Fancy>(
+    f: &mut F,
+    xs: &[F::Item],
+    ys: &[F::Item],
+    nbins: usize,
+) -> Result<Vec<F::Item>, F::Error> {
+    let nbits = (usize::BITS - nbins.leading_zeros()) as usize;
+    let zero = f.constant(0, 2)?;
+    let mut acc = f.bin_constant_bundle(0, nbits)?;
+    for (x, y) in xs.chunks(EQ_SIZE * 8).zip_eq(ys.chunks(EQ_SIZE * 8)) {
+        let eq = f.eq_bundles(
+            &BinaryBundle::new(x.to_vec()),
+            &BinaryBundle::new(y.to_vec()),
+        )?;
+        let mut ws = vec![eq];
+        ws.resize(nbits, zero.clone());
+        acc = f.bin_addition_no_carry(&acc, &BinaryBundle::new(ws))?;
+    }
+    Ok(acc.wires().to_vec())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ocelot::oprf::{KmprtPolyReceiver, KmprtPolySender, KmprtTableReceiver, KmprtTableSender};
     use rand::Rng;
     use scuttlebutt::{AesRng, Channel};
     use std::{
@@ -196,36 +548,10 @@ mod tests {
         os::unix::net::UnixStream,
     };
 
-    #[test]
-    fn test_secret_sharing_of_zero() {
-        let mut rng = AesRng::new();
-        let nparties = (rng.gen::<usize>() % 98) + 2;
-        let shares = secret_sharing_of_zero(nparties, &mut rng);
-        assert!(shares.len() == nparties);
-        let mut sum = Block512::default();
-        for b in shares.into_iter() {
-            assert!(b != Block512::default());
-            sum ^= b;
-        }
-        assert_eq!(sum, Block512::default());
-    }
-
-    #[test]
-    fn test_protocol() {
-        let mut rng = AesRng::new();
+    type TestChannel = Channel<BufReader<UnixStream>, BufWriter<UnixStream>>;
 
-        let nparties = 3;
-        let set_size = 1 << 6;
-        let intersection_size = rng.gen::<usize>() % set_size;
-        let intersection = (0..intersection_size)
-            .map(|_| rng.gen::<Block>())
-            .collect_vec();
-        let mut set1 = intersection.clone();
-        let mut set2 = intersection.clone();
-        set1.extend((intersection_size..set_size).map(|_| rng.gen::<Block>()));
-        set2.extend((intersection_size..set_size).map(|_| rng.gen::<Block>()));
-
-        // create channels
+    // Create a channel between each pair of parties.
+    fn create_channels(nparties: usize) -> Vec<Vec<(PartyId, TestChannel)>> {
         let mut channels = (0..nparties)
             .map(|_| (0..nparties).map(|_| None).collect_vec())
             .collect_vec();
@@ -242,30 +568,142 @@ mod tests {
                 }
             }
         }
-        let mut channels = channels
+        channels
             .into_iter()
             .map(|cs| cs.into_iter().flatten().collect_vec())
+            .collect_vec()
+    }
+
+    // Create the sets of the output party and the other parties, alongside
+    // their intersection.
+    fn create_sets(set_size: usize) -> (Vec<Block>, Vec<Block>, Vec<Block>) {
+        let mut rng = AesRng::new();
+        let intersection_size = rng.gen::<usize>() % set_size;
+        let intersection = (0..intersection_size)
+            .map(|_| rng.gen::<Block>())
             .collect_vec();
+        let mut set1 = intersection.clone();
+        let mut set2 = intersection.clone();
+        set1.extend((intersection_size..set_size).map(|_| rng.gen::<Block>()));
+        set2.extend((intersection_size..set_size).map(|_| rng.gen::<Block>()));
+        (set1, set2, intersection)
+    }
 
-        let mut receiver_channels = channels.remove(0);
+    fn _test_protocol<OPPRFS, OPPRFR>(nparties: usize, output: PartyId, threshold: Option<usize>)
+    where
+        OPPRFS: ProgrammableSender<Input = Block, Output = Block512> + 'static,
+        OPPRFR: ProgrammableReceiver<Input = Block, Output = Block512> + 'static,
+    {
+        let mut rng = AesRng::new();
+        let (set1, set2, intersection) = create_sets(1 << 6);
+        let mut channels = create_channels(nparties);
+        let mut receiver_channels = channels.remove(output);
 
+        let mut handles = vec![];
         for (i, mut channels) in channels.into_iter().enumerate() {
             // create and fork senders
-            let pid = i + 1;
+            let pid = if i < output { i } else { i + 1 };
             let my_set = set1.clone();
-            std::thread::spawn(move || {
+            handles.push(std::thread::spawn(move || {
                 let mut rng = AesRng::new();
-                let mut sender = Sender::init(pid, &mut channels, &mut rng).unwrap();
+                let mut sender =
+                    Sender::<OPPRFS, OPPRFR>::init(pid, &mut channels, &mut rng).unwrap();
+                if output != 0 {
+                    sender.set_output_party(output).unwrap();
+                }
+                if let Some(threshold) = threshold {
+                    sender.set_threshold(threshold).unwrap();
+                }
                 sender.send(&my_set, &mut channels, &mut rng).unwrap();
-            });
+            }));
         }
 
         // create and run receiver
-        let mut receiver = Receiver::init(&mut receiver_channels, &mut rng).unwrap();
+        let mut receiver =
+            Receiver::<OPPRFS, OPPRFR>::init_with_id(output, &mut receiver_channels, &mut rng)
+                .unwrap();
+        if let Some(threshold) = threshold {
+            receiver.set_threshold(threshold).unwrap();
+        }
         let res = receiver
             .receive(&set2, &mut receiver_channels, &mut rng)
             .unwrap();
+        for handle in handles {
+            handle.join().unwrap();
+        }
 
         assert_eq!(res, intersection);
     }
+
+    #[test]
+    fn test_protocol() {
+        _test_protocol::<KmprtSender, KmprtReceiver>(3, 0, None);
+    }
+
+    #[test]
+    fn test_protocol_table() {
+        _test_protocol::<KmprtTableSender, KmprtTableReceiver>(3, 0, None);
+    }
+
+    #[test]
+    fn test_protocol_poly() {
+        _test_protocol::<KmprtPolySender, KmprtPolyReceiver>(3, 0, None);
+    }
+
+    #[test]
+    fn test_protocol_output_party() {
+        _test_protocol::<KmprtSender, KmprtReceiver>(4, 2, None);
+    }
+
+    #[test]
+    fn test_protocol_threshold() {
+        _test_protocol::<KmprtSender, KmprtReceiver>(5, 3, Some(1));
+        _test_protocol::<KmprtSender, KmprtReceiver>(5, 0, Some(2));
+    }
+
+    #[cfg(feature = "psty")]
+    #[test]
+    fn test_protocol_cardinality() {
+        for (nparties, output) in [(3, 0), (4, 1)].iter().cloned() {
+            let mut rng = AesRng::new();
+            let (set1, set2, intersection) = create_sets(1 << 6);
+            let mut channels = create_channels(nparties);
+            let mut receiver_channels = channels.remove(output);
+
+            let mut handles = vec![];
+            for (i, mut channels) in channels.into_iter().enumerate() {
+                let pid = if i < output { i } else { i + 1 };
+                let my_set = set1.clone();
+                handles.push(std::thread::spawn(move || {
+                    let mut rng = AesRng::new();
+                    let mut sender = Sender::<KmprtSender, KmprtReceiver>::init(
+                        pid,
+                        &mut channels,
+                        &mut rng,
+                    )
+                    .unwrap();
+                    if output != 0 {
+                        sender.set_output_party(output).unwrap();
+                    }
+                    sender
+                        .send_cardinality(&my_set, &mut channels, &mut rng)
+                        .unwrap();
+                }));
+            }
+
+            let mut receiver = Receiver::<KmprtSender, KmprtReceiver>::init_with_id(
+                output,
+                &mut receiver_channels,
+                &mut rng,
+            )
+            .unwrap();
+            let cardinality = receiver
+                .receive_cardinality(&set2, &mut receiver_channels, &mut rng)
+                .unwrap();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+            assert_eq!(cardinality, intersection.len());
+        }
+    }
 }
@@ -4,10 +4,14 @@
 // Copyright © 2019 Galois, Inc.
 // See LICENSE for licensing information.
 
+pub mod ecdh;
 pub mod kmprt;
+pub mod paxos;
 #[cfg(feature = "psty")]
 pub mod psty;
+pub mod psu;
 pub mod psz;
+pub mod unbalanced;
 
 /// Private set intersection sender.
 pub type Sender = psz::Sender;
@@ -25,3 +29,25 @@ pub type ExtendedReceiver = psty::Receiver;
 pub type MultiPartySender = kmprt::Sender;
 /// Multi-party private set intersection receiver.
 pub type MultiPartyReceiver = kmprt::Receiver;
+
+/// PaXoS-based private set intersection sender, using the OT-based OOS OPRF.
+pub type PaxosSender = paxos::Sender<ocelot::oprf::OosSender>;
+/// PaXoS-based private set intersection receiver, using the OT-based OOS OPRF.
+pub type PaxosReceiver = paxos::Receiver<ocelot::oprf::OosReceiver>;
+/// PaXoS-based private set intersection sender, using the VOLE-based OPRF.
+///
+/// This has much higher communication than `PaxosSender`, and should not be
+/// used for large sets.
+pub type PaxosVoleSender = paxos::Sender<ocelot::oprf::VoleSender>;
+/// PaXoS-based private set intersection receiver, using the VOLE-based OPRF.
+pub type PaxosVoleReceiver = paxos::Receiver<ocelot::oprf::VoleReceiver>;
+
+/// Unbalanced private set intersection sender.
+pub type UnbalancedSender = unbalanced::Sender;
+/// Unbalanced private set intersection receiver.
+pub type UnbalancedReceiver = unbalanced::Receiver;
+
+/// Private set union sender.
+pub type UnionSender = psu::Sender;
+/// Private set union receiver.
+pub type UnionReceiver = psu::Receiver;
@@ -0,0 +1,321 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `popsicle`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of the PaXoS-based private set intersection protocol of
+//! Pinkas, Rosulek, Trieu, and Yanai (cf. <https://eprint.iacr.org/2020/193>,
+//! §5).
+//!
+//! The receiver encodes its set `Y` into an oblivious key-value store `P` such
+//! that `Decode(P, y) = y` for all `y ∈ Y`, and inputs the rows of `P` into a
+//! linear OPRF, so that it learns `T` and the sender learns `Q = T ⊕ Δ(P)`.
+//! Because decoding and `Δ` are both linear, `Decode(Q, x) ⊕ Δ(x)` equals
+//! `Decode(T, x)` whenever `x ∈ Y`, and is pseudorandom to the receiver
+//! otherwise. The sender sends `H(x, Decode(Q, x) ⊕ Δ(x))` for each `x ∈ X`,
+//! and the receiver compares these against `H(y, Decode(T, y))`.
+//!
+//! Unlike `psz` and `psty`, this protocol does not use cuckoo hashing into
+//! bins, so communication and computation are linear in the set sizes with
+//! small constants, which makes it suitable for very large sets. It can be
+//! instantiated with any linear OPRF: see `PaxosSender` (OT-based) and
+//! `PaxosVoleSender` (VOLE-based). Use the OT-based variant for large sets;
+//! the VOLE-based OPRF currently sends 128 field elements per input, which
+//! makes it about thirty times as expensive and comparable to `psty`, and it
+//! does not scale to sets of `2^24` elements or more.
+
+use crate::{okvs::Paxos, utils, Error};
+use ocelot::oprf::{LinearReceiver, LinearSender};
+use rand::{seq::SliceRandom, CryptoRng, Rng, RngCore};
+use scuttlebutt::{cointoss, AbstractChannel, Block, SemiHonest};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+
+// The statistical security parameter.
+const SSP: usize = 40;
+
+// Compute the number of bytes of each tag needed so that a false positive
+// occurs with probability at most `2^{-SSP}`.
+#[inline]
+fn compute_masksize(nsender: usize, nreceiver: usize) -> usize {
+    let log2 = |n: usize| (n.max(2) as f64).log2().ceil() as usize;
+    (SSP + log2(nsender) + log2(nreceiver)).div_ceil(8)
+}
+
+// Hash the input `x` with the OPRF output `output`. The first `masksize`
+// bytes are used as the tag, and the next 16 bytes as the payload key.
+#[inline]
+fn hash_output(x: Block, output: &[u8]) -> [u8; 32] {
+    let mut h = Sha256::new();
+    h.input(x.as_ref());
+    h.input(output);
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&h.result());
+    out
+}
+
+/// Private set intersection sender.
+pub struct Sender<OPRF: LinearSender> {
+    oprf: OPRF,
+}
+
+/// Private set intersection receiver.
+pub struct Receiver<OPRF: LinearReceiver> {
+    oprf: OPRF,
+}
+
+impl<OPRF: LinearSender> Sender<OPRF> {
+    /// Initialize the PSI sender.
+    pub fn init<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let oprf = OPRF::init(channel, rng)?;
+        Ok(Self { oprf })
+    }
+
+    /// Run the PSI protocol over `inputs`.
+    pub fn send<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<(), Error> {
+        let (masksize, hs) = self.perform_oprfs(inputs, channel, rng)?;
+        for i in Self::shuffled(hs.len(), rng) {
+            channel.write_all(&hs[i][0..masksize])?;
+        }
+        channel.flush()?;
+        Ok(())
+    }
+
+    /// Run the PSI protocol over `inputs`. Returns a random payload for each
+    /// input, which the receiver learns for each input in the intersection.
+    pub fn send_payloads<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Vec<Block>, Error> {
+        let (masksize, hs) = self.perform_oprfs(inputs, channel, rng)?;
+        let payloads = (0..hs.len())
+            .map(|_| rng.gen::<Block>())
+            .collect::<Vec<Block>>();
+        for i in Self::shuffled(hs.len(), rng) {
+            let tag = &hs[i][0..masksize];
+            let key = Block::try_from_slice(&hs[i][masksize..masksize + 16]).unwrap();
+            channel.write_all(tag)?;
+            channel.write_block(&(payloads[i] ^ key))?;
+        }
+        channel.flush()?;
+        Ok(payloads)
+    }
+
+    // Return the indices `0..n` in random order, so that the receiver does not
+    // learn which inputs are in the intersection.
+    fn shuffled<RNG: CryptoRng + RngCore>(n: usize, rng: &mut RNG) -> Vec<usize> {
+        let mut indices = (0..n).collect::<Vec<usize>>();
+        indices.shuffle(rng);
+        indices
+    }
+
+    // Run the linear OPRF on the receiver's encoded set, and compute the hashed
+    // OPRF outputs of the sender's inputs.
+    fn perform_oprfs<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<(usize, Vec<[u8; 32]>), Error> {
+        let keys = cointoss::send(channel, &[rng.gen(), rng.gen()], rng)?;
+        let inputs = utils::compress_and_hash_inputs(inputs, keys[0]);
+        let nreceiver = channel.read_usize()?;
+        channel.write_usize(inputs.len())?;
+        channel.flush()?;
+        let masksize = compute_masksize(inputs.len(), nreceiver);
+        let paxos = Paxos::new(nreceiver, keys[1]);
+        let qs = self.oprf.send(channel, paxos.size(), rng)?;
+        let hs = inputs
+            .iter()
+            .map(|x| {
+                let output = paxos.decode(&qs, *x) ^ self.oprf.correlate(*x);
+                hash_output(*x, output.as_ref())
+            })
+            .collect();
+        Ok((masksize, hs))
+    }
+}
+
+impl<OPRF: LinearReceiver> Receiver<OPRF> {
+    /// Initialize the PSI receiver.
+    pub fn init<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let oprf = OPRF::init(channel, rng)?;
+        Ok(Self { oprf })
+    }
+
+    /// Run the PSI protocol over `inputs`.
+    pub fn receive<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let (nsender, masksize, hs) = self.perform_oprfs(inputs, channel, rng)?;
+        let mut tags = HashSet::with_capacity(nsender);
+        for _ in 0..nsender {
+            tags.insert(channel.read_vec(masksize)?);
+        }
+        Ok(inputs
+            .iter()
+            .zip(hs)
+            .filter(|(_, h)| tags.contains(&h[0..masksize]))
+            .map(|(x, _)| x.clone())
+            .collect())
+    }
+
+    /// Run the PSI protocol over `inputs`, receiving a map from the
+    /// intersection items to their associated payloads.
+    pub fn receive_payloads<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<HashMap<Vec<u8>, Block>, Error> {
+        let (nsender, masksize, hs) = self.perform_oprfs(inputs, channel, rng)?;
+        let mut tags = HashMap::with_capacity(nsender);
+        for _ in 0..nsender {
+            let tag = channel.read_vec(masksize)?;
+            let ct = channel.read_block()?;
+            tags.insert(tag, ct);
+        }
+        Ok(inputs
+            .iter()
+            .zip(hs)
+            .filter_map(|(x, h)| {
+                tags.get(&h[0..masksize]).map(|ct| {
+                    let key = Block::try_from_slice(&h[masksize..masksize + 16]).unwrap();
+                    (x.clone(), *ct ^ key)
+                })
+            })
+            .collect())
+    }
+
+    // Encode the receiver's inputs, run the linear OPRF on the encoding, and
+    // compute the hashed OPRF outputs of the receiver's inputs.
+    fn perform_oprfs<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<(usize, usize, Vec<[u8; 32]>), Error> {
+        let keys = cointoss::receive(channel, &[rng.gen(), rng.gen()])?;
+        let inputs = utils::compress_and_hash_inputs(inputs, keys[0]);
+        channel.write_usize(inputs.len())?;
+        channel.flush()?;
+        let nsender = channel.read_usize()?;
+        let masksize = compute_masksize(nsender, inputs.len());
+        let paxos = Paxos::new(inputs.len(), keys[1]);
+        let table = paxos.encode(&inputs, &inputs, rng)?;
+        let ts = self.oprf.receive(channel, &table, rng)?;
+        let hs = inputs
+            .iter()
+            .map(|y| hash_output(*y, paxos.decode(&ts, *y).as_ref()))
+            .collect();
+        Ok((nsender, masksize, hs))
+    }
+}
+
+impl<OPRF: LinearSender + SemiHonest> SemiHonest for Sender<OPRF> {}
+impl<OPRF: LinearReceiver + SemiHonest> SemiHonest for Receiver<OPRF> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{utils::rand_vec_vec, PaxosReceiver, PaxosSender};
+    use ocelot::oprf::{OosReceiver, OosSender, VoleReceiver, VoleSender};
+    use scuttlebutt::{AesRng, Channel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    const ITEM_SIZE: usize = 8;
+    const SET_SIZE: usize = 1 << 8;
+
+    fn test_psi<OPRFS, OPRFR>(nsender: usize, nreceiver: usize)
+    where
+        OPRFS: LinearSender + 'static,
+        OPRFR: LinearReceiver,
+    {
+        let mut rng = AesRng::new();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let intersection = rand_vec_vec(nsender / 2, ITEM_SIZE, &mut rng);
+        let mut sender_inputs = rand_vec_vec(nsender - nsender / 2, ITEM_SIZE, &mut rng);
+        // Use longer inputs for the receiver, which get hashed.
+        let mut receiver_inputs = rand_vec_vec(nreceiver - nsender / 2, 2 * ITEM_SIZE, &mut rng);
+        sender_inputs.extend(intersection.clone());
+        receiver_inputs.extend(intersection.clone());
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut psi = Sender::<OPRFS>::init(&mut channel, &mut rng).unwrap();
+            psi.send(&sender_inputs, &mut channel, &mut rng).unwrap();
+        });
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi = Receiver::<OPRFR>::init(&mut channel, &mut rng).unwrap();
+        let result = psi
+            .receive(&receiver_inputs, &mut channel, &mut rng)
+            .unwrap();
+        handle.join().unwrap();
+        assert_eq!(result, intersection);
+    }
+
+    #[test]
+    fn test_psi_oos() {
+        test_psi::<OosSender, OosReceiver>(SET_SIZE, SET_SIZE);
+        test_psi::<OosSender, OosReceiver>(SET_SIZE, 4 * SET_SIZE);
+        test_psi::<OosSender, OosReceiver>(2, 1);
+    }
+
+    #[test]
+    fn test_psi_vole() {
+        test_psi::<VoleSender, VoleReceiver>(SET_SIZE, SET_SIZE / 2);
+    }
+
+    #[test]
+    fn test_payloads() {
+        let mut rng = AesRng::new();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let sender_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        let receiver_inputs = sender_inputs.clone();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut psi = PaxosSender::init(&mut channel, &mut rng).unwrap();
+            psi.send_payloads(&sender_inputs, &mut channel, &mut rng)
+                .unwrap()
+        });
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi = PaxosReceiver::init(&mut channel, &mut rng).unwrap();
+        let result = psi
+            .receive_payloads(&receiver_inputs, &mut channel, &mut rng)
+            .unwrap();
+        let payloads = handle.join().unwrap();
+        assert_eq!(result.len(), SET_SIZE);
+        for (x, payload) in receiver_inputs.iter().zip(payloads.iter()) {
+            assert_eq!(result[x], *payload);
+        }
+    }
+}
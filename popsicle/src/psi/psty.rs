@@ -6,53 +6,95 @@
 
 //! Implementation of the Pinkas-Schneider-Tkachenko-Yanai "extended" private
 //! set intersection protocol (cf. <https://eprint.iacr.org/2019/241>).
+//!
+//! Besides computing the intersection and its cardinality, the parties can
+//! run an arbitrary function over the intersection using `compute_circuit`.
+//! This hands a `BinWires` for each bin of the receiver's cuckoo table to a
+//! caller-provided function, which is typically written once, generic over
+//! `Fancy`, and passed to both the sender and the receiver. When running the
+//! protocol using `send_with_payloads` and/or `receive_with_payloads`, each
+//! bin also carries the payloads associated with the elements in the bin.
+//! Either party may supply payloads independently of the other.
+//!
+//! The intersection-sum mode (`compute_intersection_sum`) builds on this to
+//! reveal only the sum of the sender's payloads over the intersection, and
+//! optionally its cardinality, as in Google's Private Join and Compute.
+//! Similarly, the threshold mode (`compute_threshold_intersection`) reveals
+//! the intersection only if its cardinality reaches a public threshold.
+//!
+//! Instead of revealing the outputs of a circuit, `compute_shared_circuit`
+//! leaves them secret-shared between the two parties, where the sender's share
+//! is derived from its output zero labels and the receiver's share from the
+//! colors of its output labels. The resulting `SharedOutputs` can be exported
+//! as bytes, or fed into a later `twopac` session using `SharedOutputs::input`.
+//!
+//! Alternatively, the receiver can send byte-string payloads to the sender
+//! using `ReceiverState::send_payloads`, which the sender learns for the
+//! elements in the intersection. Payloads are encrypted with AES-CTR and
+//! authenticated with CBC-MAC under keys derived from each bin's OPPRF output,
+//! and padded to a common length so that only a bucketed bound on the payload
+//! lengths is revealed.
 
 use crate::{cuckoo::CuckooHash, errors::Error, utils};
 use fancy_garbling::{
-    twopac::semihonest::{Evaluator, Garbler},
+    errors::TwopacError,
+    twopac::semihonest::{Evaluator, Garbler, PartyId},
     BinaryBundle,
+    BinaryGadgets,
     BundleGadgets,
     CrtBundle,
     CrtGadgets,
     Fancy,
     FancyInput,
+    HasModulus,
     Wire,
 };
 use itertools::Itertools;
 use ocelot::{
-    oprf::{KmprtReceiver, KmprtSender},
+    oprf::{KmprtReceiver, KmprtSender, ProgrammableReceiver, ProgrammableSender},
     ot::{AlszReceiver as OtReceiver, AlszSender as OtSender},
 };
-use openssl::symm::{decrypt, encrypt, Cipher};
 use rand::{CryptoRng, Rng, RngCore, SeedableRng};
-use scuttlebutt::{AbstractChannel, Block, Block512, SemiHonest};
+use scuttlebutt::{AbstractChannel, Aes128, Block, Block512, SemiHonest};
 
 const NHASHES: usize = 3;
 // How many bytes of the hash to use for the equality tests. This affects
 // correctness, with a lower value increasing the likelihood of a false
 // positive.
 const HASH_SIZE: usize = 4;
+// The bit width of the cardinality output.
+const CARDINALITY_WIDTH: u32 = 16;
 
-// How many bytes to use to determine whether decryption succeeded in the send/recv
-// payload methods.
-const PAD_LEN: usize = 16;
+/// The default bucket size (in bytes) that encrypted payloads are padded to
+/// a multiple of.
+pub const DEFAULT_PAYLOAD_BUCKET_SIZE: usize = 16;
 
 /// The type of values in the sender and receiver's sets.
 pub type Msg = Vec<u8>;
 
 /// Private set intersection sender.
-pub struct Sender {
-    opprf: KmprtSender,
+///
+/// The sender is generic over the OPPRF used to compare set elements, and uses
+/// the KMPRT hashing-based OPPRF by default.
+pub struct Sender<OPPRF = KmprtSender> {
+    opprf: OPPRF,
 }
 
 /// State of the sender.
 pub struct SenderState {
     opprf_outputs: Vec<Block512>,
+    payloads: Option<PayloadInfo>,
+    // The mask of the sender's payload in each bin, if the sender has
+    // payloads.
+    masks: Vec<u128>,
 }
 
 /// Private set intersection receiver.
-pub struct Receiver {
-    opprf: KmprtReceiver,
+///
+/// The receiver is generic over the OPPRF used to compare set elements, and
+/// uses the KMPRT hashing-based OPPRF by default.
+pub struct Receiver<OPPRF = KmprtReceiver> {
+    opprf: OPPRF,
 }
 
 /// State of the receiver.
@@ -60,15 +102,220 @@ pub struct ReceiverState {
     opprf_outputs: Vec<Block512>,
     cuckoo: CuckooHash,
     inputs: Vec<Msg>,
+    payloads: Option<PayloadInfo>,
+    // The receiver's payloads, if it has any.
+    receiver_payloads: Vec<u128>,
 }
 
-impl Sender {
+// Which parties supply payloads, and their modulus.
+#[derive(Clone, Copy, Debug)]
+struct PayloadInfo {
+    modulus: u128,
+    sender: bool,
+    receiver: bool,
+}
+
+impl PayloadInfo {
+    // Combine the payload moduli of the sender and receiver, where a modulus of
+    // zero denotes that the party has no payloads.
+    fn new(sender_modulus: u128, receiver_modulus: u128) -> Result<Option<Self>, Error> {
+        let modulus = match (sender_modulus, receiver_modulus) {
+            (0, 0) => return Ok(None),
+            (q, 0) | (0, q) => q,
+            (q1, q2) if q1 == q2 => q1,
+            _ => {
+                return Err(Error::PsiProtocolError(
+                    "sender and receiver payload moduli differ".to_string(),
+                ))
+            }
+        };
+        Ok(Some(Self {
+            modulus,
+            sender: sender_modulus != 0,
+            receiver: receiver_modulus != 0,
+        }))
+    }
+}
+
+/// The wires associated with a single bin of the receiver's cuckoo table, as
+/// handed to the function run by `compute_circuit`. Payloads are CRT bundles
+/// modulo the payload modulus, and are `None` if the corresponding party has
+/// no payloads.
+#[derive(Clone)]
+pub struct BinWires<W> {
+    /// Whether the receiver's element in this bin is in the intersection, as a
+    /// wire of modulus two.
+    pub eq: W,
+    /// The sender's payload associated with the receiver's element in this
+    /// bin. This is only meaningful if `eq` is one.
+    pub sender_payload: Option<CrtBundle<W>>,
+    /// The receiver's payload associated with its element in this bin, or zero
+    /// if the bin is empty.
+    pub receiver_payload: Option<CrtBundle<W>>,
+}
+
+// The garbler of a circuit, alongside the wires of each bin.
+type SenderBins<'a, C, RNG> = (Garbler<&'a mut C, RNG, OtSender>, Vec<BinWires<Wire>>);
+// The evaluator of a circuit, alongside the wires of each bin.
+type ReceiverBins<'a, C, RNG> = (Evaluator<&'a mut C, RNG, OtReceiver>, Vec<BinWires<Wire>>);
+
+/// A party's shares of the outputs of a circuit run with
+/// `compute_shared_circuit`.
+///
+/// The `i`th output has modulus `moduli()[i]`, and its value is the sum of the
+/// sender's and the receiver's `i`th shares modulo `moduli()[i]`. For binary
+/// outputs, this is an XOR sharing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SharedOutputs {
+    shares: Vec<u16>,
+    moduli: Vec<u16>,
+}
+
+impl SharedOutputs {
+    fn new(shares: Vec<u16>, moduli: Vec<u16>) -> Self {
+        Self { shares, moduli }
+    }
+
+    /// Return the shares.
+    pub fn shares(&self) -> &[u16] {
+        &self.shares
+    }
+
+    /// Return the modulus of each share.
+    pub fn moduli(&self) -> &[u16] {
+        &self.moduli
+    }
+
+    /// Reconstruct the outputs from our shares and the other party's shares
+    /// `other`.
+    pub fn reconstruct(&self, other: &SharedOutputs) -> Result<Vec<u16>, Error> {
+        if self.moduli != other.moduli {
+            return Err(Error::PsiProtocolError(
+                "shares have different moduli".to_string(),
+            ));
+        }
+        Ok(self
+            .shares
+            .iter()
+            .zip(other.shares.iter())
+            .zip(self.moduli.iter())
+            .map(|((x, y), q)| ((u32::from(*x) + u32::from(*y)) % u32::from(*q)) as u16)
+            .collect())
+    }
+
+    /// Export the shares as bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + 4 * self.shares.len());
+        bytes.extend(&(self.shares.len() as u64).to_le_bytes());
+        for (share, q) in self.shares.iter().zip(self.moduli.iter()) {
+            bytes.extend(&q.to_le_bytes());
+            bytes.extend(&share.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Import shares exported by `to_bytes`. Returns `None` if `bytes` is
+    /// malformed.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 8 {
+            return None;
+        }
+        let mut n = [0u8; 8];
+        n.copy_from_slice(&bytes[0..8]);
+        let n = u64::from_le_bytes(n) as usize;
+        if bytes.len() != 8 + 4 * n {
+            return None;
+        }
+        let (mut shares, mut moduli) = (Vec::with_capacity(n), Vec::with_capacity(n));
+        for chunk in bytes[8..].chunks(4) {
+            let q = u16::from_le_bytes([chunk[0], chunk[1]]);
+            let share = u16::from_le_bytes([chunk[2], chunk[3]]);
+            if q < 2 || share >= q {
+                return None;
+            }
+            moduli.push(q);
+            shares.push(share);
+        }
+        Some(Self { shares, moduli })
+    }
+
+    /// Input the shares into a later two-party session, where `party` is our
+    /// role in that session, returning wires holding the reconstructed outputs.
+    /// The other party must call this with its own shares and role.
+    pub fn input<F>(&self, f: &mut F, party: PartyId) -> Result<Vec<Wire>, Error>
+    where
+        F: Fancy<Item = Wire, Error = TwopacError>
+            + FancyInput<Item = Wire, Error = TwopacError, PartyId = PartyId>,
+    {
+        let (mine, theirs) = match party {
+            PartyId::Garbler => {
+                let mine = f.encode_many(&self.shares, &self.moduli)?;
+                let theirs = f.receive_many(PartyId::Evaluator, &self.moduli)?;
+                (mine, theirs)
+            }
+            PartyId::Evaluator => {
+                let theirs = f.receive_many(PartyId::Garbler, &self.moduli)?;
+                let mine = f.encode_many(&self.shares, &self.moduli)?;
+                (mine, theirs)
+            }
+        };
+        Ok(mine
+            .iter()
+            .zip(theirs.iter())
+            .map(|(x, y)| f.add(x, y))
+            .collect::<Result<Vec<Wire>, TwopacError>>()?)
+    }
+}
+
+// Compute `(a + b) mod q` for `a, b < q`, without overflowing.
+#[inline]
+fn add_mod(a: u128, b: u128, q: u128) -> u128 {
+    if a >= q - b {
+        a - (q - b)
+    } else {
+        a + b
+    }
+}
+
+// Extract the (masked) payload from an OPPRF output.
+#[inline]
+fn payload_of(opprf_output: &Block512) -> u128 {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&opprf_output.as_ref()[HASH_SIZE..HASH_SIZE + 16]);
+    u128::from_le_bytes(bytes)
+}
+
+// Check that there is a payload in `[0, modulus)` for each input, and that
+// `modulus` is a product of distinct primes supported by fancy garbling.
+fn check_payloads(inputs: &[Msg], payloads: &[u128], modulus: u128) -> Result<(), Error> {
+    if payloads.len() != inputs.len() {
+        return Err(Error::InvalidPayloadsLength);
+    }
+    let rest = fancy_garbling::util::PRIMES
+        .iter()
+        .map(|p| u128::from(*p))
+        .fold(modulus, |q, p| if q % p == 0 { q / p } else { q });
+    if modulus < 2 || rest != 1 {
+        return Err(Error::PsiProtocolError(format!(
+            "invalid payload modulus: {}",
+            modulus
+        )));
+    }
+    if payloads.iter().any(|p| *p >= modulus) {
+        return Err(Error::PsiProtocolError(
+            "payload exceeds the payload modulus".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+impl<OPPRF: ProgrammableSender<Input = Block, Output = Block512>> Sender<OPPRF> {
     /// Initialize the PSI sender.
     pub fn init<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
         channel: &mut C,
         rng: &mut RNG,
     ) -> Result<Self, Error> {
-        let opprf = KmprtSender::init(channel, rng)?;
+        let opprf = OPPRF::init(channel, rng)?;
         Ok(Self { opprf })
     }
 
@@ -78,6 +325,33 @@ impl Sender {
         inputs: &[Msg],
         channel: &mut C,
         rng: &mut RNG,
+    ) -> Result<SenderState, Error> {
+        self.send_internal(inputs, None, channel, rng)
+    }
+
+    /// Run the PSI protocol over `inputs`, where the `i`th input is associated
+    /// with the payload `payloads[i]`, an integer modulo `modulus`. The modulus
+    /// must be a CRT modulus, e.g., as given by
+    /// `fancy_garbling::util::modulus_with_width`, and if the receiver also has
+    /// payloads, it must use the same modulus.
+    pub fn send_with_payloads<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
+        &mut self,
+        inputs: &[Msg],
+        payloads: &[u128],
+        modulus: u128,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<SenderState, Error> {
+        check_payloads(inputs, payloads, modulus)?;
+        self.send_internal(inputs, Some((payloads, modulus)), channel, rng)
+    }
+
+    fn send_internal<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
+        &mut self,
+        inputs: &[Msg],
+        payloads: Option<(&[u128], u128)>,
+        channel: &mut C,
+        rng: &mut RNG,
     ) -> Result<SenderState, Error> {
         // receive cuckoo hash info from sender
         let key = channel.read_block()?;
@@ -85,114 +359,292 @@ impl Sender {
 
         // map inputs to table using all hash functions
         let nbins = channel.read_usize()?;
+
+        // exchange payload moduli
+        let receiver_modulus = u128::from(channel.read_block()?);
+        let sender_modulus = payloads.map_or(0, |(_, modulus)| modulus);
+        channel.write_block(&Block::from(sender_modulus))?;
+        channel.flush()?;
+        let info = PayloadInfo::new(sender_modulus, receiver_modulus)?;
+
         let mut table = vec![Vec::new(); nbins];
 
-        for &x in &hashes {
+        for (i, &x) in hashes.iter().enumerate() {
             let mut bins = Vec::with_capacity(NHASHES);
             for h in 0..NHASHES {
                 let bin = CuckooHash::bin(x, h, nbins);
-                table[bin].push(x ^ Block::from(h as u128));
+                table[bin].push((x ^ Block::from(h as u128), Some(i)));
                 bins.push(bin);
             }
             // if j = H1(y) = H2(y) for some y, then P2 adds a uniformly random element to
             // table2[j].
             if bins.iter().skip(1).all(|&x| x == bins[0]) {
-                table[bins[0]].push(rng.gen());
+                table[bins[0]].push((rng.gen(), None));
             }
         }
 
         // select the target values
         let ts = (0..nbins).map(|_| rng.gen::<Block512>()).collect_vec();
 
+        // select the payload masks, if any
+        let masks = match payloads {
+            Some((_, modulus)) => (0..nbins).map(|_| rng.gen::<u128>() % modulus).collect(),
+            None => vec![],
+        };
+
         let points = table
             .into_iter()
-            .zip_eq(ts.iter())
-            .flat_map(|(bin, t)| {
-                // map all the points in a bin to the same tag
-                bin.into_iter().map(move |item| (item, *t))
+            .enumerate()
+            .flat_map(|(bin, items)| {
+                let t = ts[bin];
+                let mask = masks.get(bin).cloned();
+                // map all the points in a bin to the same tag, alongside the
+                // item's masked payload if running with payloads
+                items.into_iter().map(move |(item, i)| {
+                    let mut t = t;
+                    if let (Some((payloads, modulus)), Some(i), Some(mask)) = (payloads, i, mask) {
+                        let payload = add_mod(payloads[i], mask, modulus);
+                        t.as_mut()[HASH_SIZE..HASH_SIZE + 16]
+                            .copy_from_slice(&payload.to_le_bytes());
+                    }
+                    (item, t)
+                })
             })
             .collect_vec();
 
         self.opprf.send(channel, &points, nbins, rng)?;
 
-        Ok(SenderState { opprf_outputs: ts })
+        Ok(SenderState {
+            opprf_outputs: ts,
+            payloads: info,
+            masks,
+        })
     }
 }
 
 impl SenderState {
     /// Run the setup phase, producing a garbler for the next stage.
-    pub fn compute_setup<C, RNG>(
+    pub fn compute_setup<'a, C, RNG>(
         &self,
-        channel: &mut C,
+        channel: &'a mut C,
         rng: &mut RNG,
-    ) -> Result<(Garbler<C, RNG, OtSender>, Vec<Wire>, Vec<Wire>), Error>
+    ) -> Result<(Garbler<&'a mut C, RNG, OtSender>, Vec<Wire>, Vec<Wire>), Error>
     where
         C: AbstractChannel,
         RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
     {
-        let mut gb = Garbler::<C, RNG, OtSender>::new(channel.clone(), RNG::from_seed(rng.gen()))?;
+        let mut gb = Garbler::<&mut C, RNG, OtSender>::new(channel, RNG::from_seed(rng.gen()))?;
         let my_input_bits = encode_inputs(&self.opprf_outputs);
         let mods = vec![2; my_input_bits.len()]; // all binary moduli
         let sender_inputs = gb.encode_many(&my_input_bits, &mods)?;
-        let receiver_inputs = gb.receive_many(&mods)?;
+        let receiver_inputs = gb.receive_many(PartyId::Evaluator, &mods)?;
         Ok((gb, sender_inputs, receiver_inputs))
     }
 
-    /// Compute the intersection.
-    pub fn compute_intersection<C, RNG>(&self, channel: &mut C, rng: &mut RNG) -> Result<(), Error>
+    // Run the setup phase, and compute the wires of each bin.
+    fn compute_bins<'a, C, RNG>(
+        &self,
+        channel: &'a mut C,
+        rng: &mut RNG,
+    ) -> Result<SenderBins<'a, C, RNG>, Error>
     where
         C: AbstractChannel,
         RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
     {
         let (mut gb, x, y) = self.compute_setup(channel, rng)?;
-        let outs = fancy_compute_intersection(&mut gb, &x, &y)?;
+        let eqs = fancy_compute_equalities(&mut gb, &x, &y)?;
+        let nbins = eqs.len();
+        let bins = match self.payloads {
+            Some(info) => {
+                let sender_payloads = if info.sender {
+                    // We subtract our masks from the masked payloads the
+                    // receiver got from the OPPRF.
+                    let masks = self
+                        .masks
+                        .iter()
+                        .map(|mask| (info.modulus - mask) % info.modulus)
+                        .collect_vec();
+                    let masks = gb.crt_encode_many(&masks, info.modulus)?;
+                    let payloads = gb.crt_receive_many(PartyId::Evaluator, nbins, info.modulus)?;
+                    let payloads = payloads
+                        .iter()
+                        .zip_eq(masks.iter())
+                        .map(|(payload, mask)| gb.crt_add(payload, mask))
+                        .collect::<Result<Vec<_>, TwopacError>>()?;
+                    Some(payloads)
+                } else {
+                    None
+                };
+                let receiver_payloads = if info.receiver {
+                    Some(gb.crt_receive_many(PartyId::Evaluator, nbins, info.modulus)?)
+                } else {
+                    None
+                };
+                bin_wires(eqs, sender_payloads, receiver_payloads)
+            }
+            None => bin_wires(eqs, None, None),
+        };
+        Ok((gb, bins))
+    }
+
+    /// Run the function `f` over the wires of each bin, revealing its output
+    /// wires to the receiver. The receiver must call
+    /// `ReceiverState::compute_circuit` with the same function.
+    pub fn compute_circuit<'a, C, RNG, Func>(
+        &self,
+        channel: &'a mut C,
+        rng: &mut RNG,
+        f: Func,
+    ) -> Result<(), Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+        Func: FnOnce(
+            &mut Garbler<&'a mut C, RNG, OtSender>,
+            &[BinWires<Wire>],
+        ) -> Result<Vec<Wire>, TwopacError>,
+    {
+        let (mut gb, bins) = self.compute_bins(channel, rng)?;
+        let outs = f(&mut gb, &bins)?;
         gb.outputs(&outs)?;
+        // Flush the output decoding information, so that the receiver can
+        // finish before we run another protocol.
+        gb.get_channel().flush()?;
         Ok(())
     }
 
+    /// Run the function `f` over the wires of each bin, like `compute_circuit`,
+    /// but leave its outputs secret-shared between the sender and the
+    /// receiver instead of revealing them. The receiver must call
+    /// `ReceiverState::compute_shared_circuit` with the same function.
+    pub fn compute_shared_circuit<'a, C, RNG, Func>(
+        &self,
+        channel: &'a mut C,
+        rng: &mut RNG,
+        f: Func,
+    ) -> Result<SharedOutputs, Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+        Func: FnOnce(
+            &mut Garbler<&'a mut C, RNG, OtSender>,
+            &[BinWires<Wire>],
+        ) -> Result<Vec<Wire>, TwopacError>,
+    {
+        let (mut gb, bins) = self.compute_bins(channel, rng)?;
+        let outs = f(&mut gb, &bins)?;
+        gb.get_channel().flush()?;
+        // Our wires are the zero labels, whose color is the negation of our
+        // share, as the color of each delta is one.
+        Ok(SharedOutputs::new(
+            outs.iter()
+                .map(|w| (w.modulus() - w.color()) % w.modulus())
+                .collect(),
+            outs.iter().map(|w| w.modulus()).collect(),
+        ))
+    }
+
+    /// Compute the per-bin match bits and payloads as laid out by
+    /// `fancy_compute_bins`, leaving them secret-shared between the sender and
+    /// the receiver.
+    pub fn compute_shared_intersection<C, RNG>(
+        &self,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<SharedOutputs, Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+    {
+        self.compute_shared_circuit(channel, rng, fancy_compute_bins)
+    }
+
+    /// Compute the intersection.
+    pub fn compute_intersection<C, RNG>(&self, channel: &mut C, rng: &mut RNG) -> Result<(), Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+    {
+        self.compute_circuit(channel, rng, fancy_compute_intersection)
+    }
+
     /// Compute the cardinality of the intersection.
     pub fn compute_cardinality<C, RNG>(&self, channel: &mut C, rng: &mut RNG) -> Result<(), Error>
     where
         C: AbstractChannel,
         RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
     {
-        let (mut gb, x, y) = self.compute_setup(channel, rng)?;
-        let (outs, _) = fancy_compute_cardinality(&mut gb, &x, &y)?;
-        gb.outputs(&outs)?;
-        Ok(())
+        self.compute_circuit(channel, rng, fancy_compute_cardinality)
+    }
+
+    /// Compute the intersection, revealing it to the receiver only if its
+    /// cardinality is at least the public `threshold`. If `reveal_cardinality`
+    /// is set, the receiver also learns the cardinality of the intersection,
+    /// even when it is below the threshold.
+    pub fn compute_threshold_intersection<C, RNG>(
+        &self,
+        threshold: usize,
+        reveal_cardinality: bool,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<(), Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+    {
+        self.compute_circuit(channel, rng, |f, bins| {
+            fancy_compute_threshold_intersection(f, bins, threshold, reveal_cardinality)
+        })
     }
 
-    /// Receive encrypted payloads from the Sender.
-    pub fn receive_payloads<C>(
+    /// Compute the sum of the sender's payloads over the intersection, modulo
+    /// the payload modulus, revealing it to the receiver. If `with_cardinality`
+    /// is set, the receiver also learns the cardinality of the intersection.
+    /// This requires the sender to have run `send_with_payloads`.
+    pub fn compute_intersection_sum<C, RNG>(
         &self,
-        payload_len: usize,
+        with_cardinality: bool,
         channel: &mut C,
-    ) -> Result<Vec<Vec<u8>>, Error>
+        rng: &mut RNG,
+    ) -> Result<(), Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+    {
+        let q = sender_payload_modulus(self.payloads)?;
+        self.compute_circuit(channel, rng, |f, bins| {
+            fancy_compute_intersection_sum(f, bins, q, with_cardinality)
+        })
+    }
+
+    /// Receive encrypted payloads from the receiver, as sent by
+    /// `ReceiverState::send_payloads`, returning those payloads whose
+    /// associated element is in the intersection.
+    pub fn receive_payloads<C>(&self, channel: &mut C) -> Result<Vec<Vec<u8>>, Error>
     where
         C: AbstractChannel,
     {
+        let padded_len = channel.read_usize()?;
         let mut payloads = Vec::new();
         for opprf_output in self.opprf_outputs.iter() {
-            let iv = channel.read_vec(16)?;
-            let ct = channel.read_vec(payload_len + PAD_LEN)?;
-            let key = opprf_output.prefix(16);
-            let mut dec = decrypt(Cipher::aes_128_ctr(), key, Some(&iv), &ct)?;
-            let payload = dec.split_off(PAD_LEN);
-            if dec.into_iter().all(|x| x == 0) {
-                payloads.push(payload)
+            let iv = channel.read_block()?;
+            let ct = channel.read_vec(8 + padded_len)?;
+            let tag = channel.read_block()?;
+            if let Some(payload) = decrypt_payload(opprf_output, iv, &ct, tag)? {
+                payloads.push(payload);
             }
         }
         Ok(payloads)
     }
 }
 
-impl Receiver {
+impl<OPPRF: ProgrammableReceiver<Input = Block, Output = Block512>> Receiver<OPPRF> {
     /// Initialize the PSI receiver.
     pub fn init<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
         channel: &mut C,
         rng: &mut RNG,
     ) -> Result<Self, Error> {
-        let opprf = KmprtReceiver::init(channel, rng)?;
+        let opprf = OPPRF::init(channel, rng)?;
         Ok(Self { opprf })
     }
 
@@ -202,6 +654,33 @@ impl Receiver {
         inputs: &[Msg],
         channel: &mut C,
         rng: &mut RNG,
+    ) -> Result<ReceiverState, Error> {
+        self.receive_internal(inputs, None, channel, rng)
+    }
+
+    /// Run the PSI protocol over `inputs`, where the `i`th input is associated
+    /// with the payload `payloads[i]`, an integer modulo `modulus`. The modulus
+    /// must be a CRT modulus, e.g., as given by
+    /// `fancy_garbling::util::modulus_with_width`, and if the sender also has
+    /// payloads, it must use the same modulus.
+    pub fn receive_with_payloads<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
+        &mut self,
+        inputs: &[Msg],
+        payloads: &[u128],
+        modulus: u128,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<ReceiverState, Error> {
+        check_payloads(inputs, payloads, modulus)?;
+        self.receive_internal(inputs, Some((payloads, modulus)), channel, rng)
+    }
+
+    fn receive_internal<C: AbstractChannel, RNG: RngCore + CryptoRng + SeedableRng>(
+        &mut self,
+        inputs: &[Msg],
+        payloads: Option<(&[u128], u128)>,
+        channel: &mut C,
+        rng: &mut RNG,
     ) -> Result<ReceiverState, Error> {
         let key = rng.gen();
         let hashed_inputs = utils::compress_and_hash_inputs(inputs, key);
@@ -210,7 +689,13 @@ impl Receiver {
         // Send cuckoo hash info to receiver.
         channel.write_block(&key)?;
         channel.write_usize(cuckoo.nbins)?;
+
+        // Exchange payload moduli.
+        let receiver_modulus = payloads.map_or(0, |(_, modulus)| modulus);
+        channel.write_block(&Block::from(receiver_modulus))?;
         channel.flush()?;
+        let sender_modulus = u128::from(channel.read_block()?);
+        let info = PayloadInfo::new(sender_modulus, receiver_modulus)?;
 
         // Build `table` to include a cuckoo hash entry xored with its hash
         // index, if such a entry exists, or a random value.
@@ -229,17 +714,19 @@ impl Receiver {
             opprf_outputs,
             cuckoo,
             inputs: inputs.to_vec(),
+            payloads: info,
+            receiver_payloads: payloads.map_or(vec![], |(payloads, _)| payloads.to_vec()),
         })
     }
 }
 
 impl ReceiverState {
     /// Run the setup phase, producing an evaluator for the next stage.
-    pub fn compute_setup<C, RNG>(
+    pub fn compute_setup<'a, C, RNG>(
         &self,
-        channel: &mut C,
+        channel: &'a mut C,
         rng: &mut RNG,
-    ) -> Result<(Evaluator<C, RNG, OtReceiver>, Vec<Wire>, Vec<Wire>), Error>
+    ) -> Result<(Evaluator<&'a mut C, RNG, OtReceiver>, Vec<Wire>, Vec<Wire>), Error>
     where
         C: AbstractChannel,
         RNG: CryptoRng + RngCore + SeedableRng<Seed = Block>,
@@ -247,40 +734,203 @@ impl ReceiverState {
         let nbins = self.cuckoo.nbins;
         let my_input_bits = encode_inputs(&self.opprf_outputs);
 
-        let mut ev =
-            Evaluator::<C, RNG, OtReceiver>::new(channel.clone(), RNG::from_seed(rng.gen()))?;
+        let mut ev = Evaluator::<&mut C, RNG, OtReceiver>::new(channel, RNG::from_seed(rng.gen()))?;
 
         let mods = vec![2; nbins * HASH_SIZE * 8];
-        let sender_inputs = ev.receive_many(&mods)?;
+        let sender_inputs = ev.receive_many(PartyId::Garbler, &mods)?;
         let receiver_inputs = ev.encode_many(&my_input_bits, &mods)?;
         Ok((ev, sender_inputs, receiver_inputs))
     }
 
-    /// Compute the intersection.
-    pub fn compute_intersection<C, RNG>(
+    /// Return, for each bin of the cuckoo table, the index of the input in
+    /// that bin, or `None` if the bin is empty. This allows mapping the
+    /// per-bin outputs of `compute_circuit` back to the inputs.
+    pub fn input_indices(&self) -> Vec<Option<usize>> {
+        self.cuckoo
+            .items
+            .iter()
+            .map(|opt_item| opt_item.as_ref().map(|item| item.input_index))
+            .collect()
+    }
+
+    // Run the setup phase, and compute the wires of each bin.
+    fn compute_bins<'a, C, RNG>(
         &self,
-        channel: &mut C,
+        channel: &'a mut C,
         rng: &mut RNG,
-    ) -> Result<Vec<Msg>, Error>
+    ) -> Result<ReceiverBins<'a, C, RNG>, Error>
     where
         C: AbstractChannel,
         RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
     {
         let (mut ev, x, y) = self.compute_setup(channel, rng)?;
-        let outs = fancy_compute_intersection(&mut ev, &x, &y)?;
+        let eqs = fancy_compute_equalities(&mut ev, &x, &y)?;
+        let nbins = eqs.len();
+        let bins = match self.payloads {
+            Some(info) => {
+                let sender_payloads = if info.sender {
+                    let masks = ev.crt_receive_many(PartyId::Garbler, nbins, info.modulus)?;
+                    let payloads = self
+                        .opprf_outputs
+                        .iter()
+                        .map(|output| payload_of(output) % info.modulus)
+                        .collect_vec();
+                    let payloads = ev.crt_encode_many(&payloads, info.modulus)?;
+                    let payloads = payloads
+                        .iter()
+                        .zip_eq(masks.iter())
+                        .map(|(payload, mask)| ev.crt_add(payload, mask))
+                        .collect::<Result<Vec<_>, TwopacError>>()?;
+                    Some(payloads)
+                } else {
+                    None
+                };
+                let receiver_payloads = if info.receiver {
+                    let payloads = self
+                        .cuckoo
+                        .items
+                        .iter()
+                        .map(|opt_item| match opt_item {
+                            Some(item) => self.receiver_payloads[item.input_index],
+                            None => 0,
+                        })
+                        .collect_vec();
+                    Some(ev.crt_encode_many(&payloads, info.modulus)?)
+                } else {
+                    None
+                };
+                bin_wires(eqs, sender_payloads, receiver_payloads)
+            }
+            None => bin_wires(eqs, None, None),
+        };
+        Ok((ev, bins))
+    }
+
+    /// Run the function `f` over the wires of each bin, returning the values of
+    /// its output wires. The sender must call `SenderState::compute_circuit`
+    /// with the same function.
+    pub fn compute_circuit<'a, C, RNG, Func>(
+        &self,
+        channel: &'a mut C,
+        rng: &mut RNG,
+        f: Func,
+    ) -> Result<Vec<u16>, Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+        Func: FnOnce(
+            &mut Evaluator<&'a mut C, RNG, OtReceiver>,
+            &[BinWires<Wire>],
+        ) -> Result<Vec<Wire>, TwopacError>,
+    {
+        let (mut ev, bins) = self.compute_bins(channel, rng)?;
+        let outs = f(&mut ev, &bins)?;
         let mpc_outs = ev
             .outputs(&outs)?
             .expect("evaluator should produce outputs");
+        Ok(mpc_outs)
+    }
+
+    /// Run the function `f` over the wires of each bin, like `compute_circuit`,
+    /// but leave its outputs secret-shared between the sender and the
+    /// receiver instead of revealing them. The sender must call
+    /// `SenderState::compute_shared_circuit` with the same function.
+    pub fn compute_shared_circuit<'a, C, RNG, Func>(
+        &self,
+        channel: &'a mut C,
+        rng: &mut RNG,
+        f: Func,
+    ) -> Result<SharedOutputs, Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+        Func: FnOnce(
+            &mut Evaluator<&'a mut C, RNG, OtReceiver>,
+            &[BinWires<Wire>],
+        ) -> Result<Vec<Wire>, TwopacError>,
+    {
+        let (mut ev, bins) = self.compute_bins(channel, rng)?;
+        let outs = f(&mut ev, &bins)?;
+        // The color of each of our output labels is our share.
+        Ok(SharedOutputs::new(
+            outs.iter().map(|w| w.color()).collect(),
+            outs.iter().map(|w| w.modulus()).collect(),
+        ))
+    }
 
+    /// Compute the per-bin match bits and payloads as laid out by
+    /// `fancy_compute_bins`, leaving them secret-shared between the sender and
+    /// the receiver. Use `input_indices` to map bins to inputs.
+    pub fn compute_shared_intersection<C, RNG>(
+        &self,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<SharedOutputs, Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+    {
+        self.compute_shared_circuit(channel, rng, fancy_compute_bins)
+    }
+
+    /// Compute the intersection.
+    pub fn compute_intersection<C, RNG>(
+        &self,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Vec<Msg>, Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+    {
+        let mpc_outs = self.compute_circuit(channel, rng, fancy_compute_intersection)?;
+        Ok(self.intersection_of(&mpc_outs))
+    }
+
+    // Collect the inputs whose bin has its bit in `bits` set.
+    fn intersection_of(&self, bits: &[u16]) -> Vec<Msg> {
         let mut intersection = Vec::new();
-        for (opt_item, in_intersection) in self.cuckoo.items.iter().zip_eq(mpc_outs.into_iter()) {
+        for (opt_item, in_intersection) in self.cuckoo.items.iter().zip_eq(bits.iter()) {
             if let Some(item) = opt_item {
-                if in_intersection == 1_u16 {
+                if *in_intersection == 1_u16 {
                     intersection.push(self.inputs[item.input_index].clone());
                 }
             }
         }
-        Ok(intersection)
+        intersection
+    }
+
+    /// Compute the intersection, revealing it only if its cardinality is at
+    /// least the public `threshold`, and returning `None` otherwise. If
+    /// `reveal_cardinality` is set, this also returns the cardinality of the
+    /// intersection, even when it is below the threshold. The sender must use
+    /// the same `threshold` and `reveal_cardinality`.
+    pub fn compute_threshold_intersection<C, RNG>(
+        &self,
+        threshold: usize,
+        reveal_cardinality: bool,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<(Option<Vec<Msg>>, Option<usize>), Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+    {
+        let nbins = self.cuckoo.nbins;
+        let mpc_outs = self.compute_circuit(channel, rng, |f, bins| {
+            fancy_compute_threshold_intersection(f, bins, threshold, reveal_cardinality)
+        })?;
+        let intersection = if mpc_outs[0] == 1 {
+            Some(self.intersection_of(&mpc_outs[1..1 + nbins]))
+        } else {
+            None
+        };
+        let cardinality = if reveal_cardinality {
+            Some(fancy_garbling::util::u128_from_bits(&mpc_outs[1 + nbins..]) as usize)
+        } else {
+            None
+        };
+        Ok((intersection, cardinality))
     }
 
     /// Compute the cardinality of the intersection.
@@ -293,18 +943,45 @@ impl ReceiverState {
         C: AbstractChannel,
         RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
     {
-        let (mut ev, x, y) = self.compute_setup(channel, rng)?;
-        let (outs, mods) = fancy_compute_cardinality(&mut ev, &x, &y)?;
-        let mpc_outs = ev
-            .outputs(&outs)?
-            .expect("evaluator should produce outputs");
-
+        let mpc_outs = self.compute_circuit(channel, rng, fancy_compute_cardinality)?;
+        let mods = fancy_garbling::util::primes_with_width(CARDINALITY_WIDTH);
         let cardinality = fancy_garbling::util::crt_inv(&mpc_outs, &mods);
         Ok(cardinality as usize)
     }
 
-    /// Send encrypted payloads to the Receiver, who can only decrypt a payload if they
-    /// share the associated element in the intersection.
+    /// Compute the sum of the sender's payloads over the intersection, modulo
+    /// the payload modulus. If `with_cardinality` is set, this also returns the
+    /// cardinality of the intersection. This requires the sender to have run
+    /// `send_with_payloads`.
+    pub fn compute_intersection_sum<C, RNG>(
+        &self,
+        with_cardinality: bool,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<(u128, Option<usize>), Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+    {
+        let q = sender_payload_modulus(self.payloads)?;
+        let mpc_outs = self.compute_circuit(channel, rng, |f, bins| {
+            fancy_compute_intersection_sum(f, bins, q, with_cardinality)
+        })?;
+        let qs = fancy_garbling::util::factor(q);
+        let sum = fancy_garbling::util::crt_inv(&mpc_outs[0..qs.len()], &qs);
+        let cardinality = if with_cardinality {
+            let mods = fancy_garbling::util::primes_with_width(CARDINALITY_WIDTH);
+            Some(fancy_garbling::util::crt_inv(&mpc_outs[qs.len()..], &mods) as usize)
+        } else {
+            None
+        };
+        Ok((sum, cardinality))
+    }
+
+    /// Send encrypted payloads to the sender, who can only decrypt a payload
+    /// if they share the associated element in the intersection. Payloads may
+    /// be of different lengths, and are padded as in
+    /// `send_payloads_with_bucket_size` using `DEFAULT_PAYLOAD_BUCKET_SIZE`.
     pub fn send_payloads<C, RNG>(
         &self,
         payloads: &[Vec<u8>],
@@ -313,35 +990,137 @@ impl ReceiverState {
     ) -> Result<(), Error>
     where
         C: AbstractChannel,
-        RNG: RngCore + CryptoRng + SeedableRng<Seed = Block>,
+        RNG: RngCore + CryptoRng,
     {
-        let payload_len = payloads[0].len();
-        if !(payloads.iter().all(|p| p.len() == payload_len)) {
-            return Err(Error::InvalidPayloadsLength);
+        self.send_payloads_with_bucket_size(payloads, DEFAULT_PAYLOAD_BUCKET_SIZE, channel, rng)
+    }
+
+    /// Send encrypted payloads to the sender, like `send_payloads`. Every
+    /// payload is padded to the length of the longest payload, rounded up to a
+    /// multiple of `bucket_size`, so that the sender only learns this padded
+    /// length.
+    pub fn send_payloads_with_bucket_size<C, RNG>(
+        &self,
+        payloads: &[Vec<u8>],
+        bucket_size: usize,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<(), Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng,
+    {
+        if bucket_size == 0 {
+            return Err(Error::PsiProtocolError(
+                "payload bucket size must be nonzero".to_string(),
+            ));
         }
-        let dummy_payload = vec![0; payload_len];
+        let max_len = payloads.iter().map(|p| p.len()).max().unwrap_or(0);
+        let padded_len = max_len.div_ceil(bucket_size).max(1) * bucket_size;
 
+        channel.write_usize(padded_len)?;
         for (opt_item, opprf_output) in self.cuckoo.items.iter().zip_eq(self.opprf_outputs.iter()) {
-            let mut payload = vec![0; PAD_LEN];
-            if let Some(item) = opt_item {
-                if item.input_index >= payloads.len() {
-                    return Err(Error::InvalidPayloadsLength);
-                }
-                payload.extend_from_slice(&payloads[item.input_index]);
-            } else {
-                payload.extend_from_slice(&dummy_payload);
+            let payload = match opt_item {
+                Some(item) => payloads
+                    .get(item.input_index)
+                    .ok_or(Error::InvalidPayloadsLength)?
+                    .as_slice(),
+                None => &[],
             };
-            let iv: [u8; 16] = rng.gen();
-            let key = opprf_output.prefix(16);
-            let ct = encrypt(Cipher::aes_128_ctr(), &key, Some(&iv), &payload)?;
-            channel.write_bytes(&iv)?;
-            channel.write_bytes(&ct)?;
+            let iv = rng.gen::<Block>();
+            let (ct, tag) = encrypt_payload(opprf_output, iv, payload, padded_len);
+            channel.write_block(&iv)?;
+            channel.write_all(&ct)?;
+            channel.write_block(&tag)?;
         }
         channel.flush()?;
         Ok(())
     }
 }
 
+// Derive the encryption and authentication keys of a bin's payload from the
+// bin's OPPRF output. These are disjoint from the bytes used for the equality
+// tests and circuit payloads.
+#[inline]
+fn payload_keys(opprf_output: &Block512) -> (Aes128, Aes128) {
+    let bytes = opprf_output.as_ref();
+    let mut enc = [0u8; 16];
+    let mut mac = [0u8; 16];
+    enc.copy_from_slice(&bytes[32..48]);
+    mac.copy_from_slice(&bytes[48..64]);
+    (Aes128::new(Block::from(enc)), Aes128::new(Block::from(mac)))
+}
+
+// Apply the AES-CTR keystream starting at counter `iv` to `bytes`.
+fn ctr_xor(aes: &Aes128, iv: Block, bytes: &mut [u8]) {
+    let iv = u128::from(iv);
+    for (i, chunk) in bytes.chunks_mut(16).enumerate() {
+        let ks = aes.encrypt(Block::from(iv.wrapping_add(i as u128)));
+        for (b, k) in chunk.iter_mut().zip(ks.as_ref().iter()) {
+            *b ^= *k;
+        }
+    }
+}
+
+// Compute the CBC-MAC of `iv` and `ct`. Prepending the length makes the
+// encoding prefix-free, which is what CBC-MAC needs to be secure for
+// variable-length messages.
+fn cbc_mac(aes: &Aes128, iv: Block, ct: &[u8]) -> Block {
+    let mut tag = aes.encrypt(Block::from(ct.len() as u128));
+    tag = aes.encrypt(tag ^ iv);
+    for chunk in ct.chunks(16) {
+        let mut block = [0u8; 16];
+        block[0..chunk.len()].copy_from_slice(chunk);
+        tag = aes.encrypt(tag ^ Block::from(block));
+    }
+    tag
+}
+
+// Encrypt-then-MAC `payload` under the keys derived from `opprf_output`,
+// padding the plaintext `len(payload) || payload` to `8 + padded_len` bytes.
+fn encrypt_payload(
+    opprf_output: &Block512,
+    iv: Block,
+    payload: &[u8],
+    padded_len: usize,
+) -> (Vec<u8>, Block) {
+    let (enc, mac) = payload_keys(opprf_output);
+    let mut ct = Vec::with_capacity(8 + padded_len);
+    ct.extend(&(payload.len() as u64).to_le_bytes());
+    ct.extend(payload);
+    ct.resize(8 + padded_len, 0);
+    ctr_xor(&enc, iv, &mut ct);
+    let tag = cbc_mac(&mac, iv, &ct);
+    (ct, tag)
+}
+
+// Decrypt a payload encrypted by `encrypt_payload`. Returns `None` if the tag
+// does not verify, meaning the bin's element is not in the intersection, and
+// an error if the tag verifies but the plaintext is malformed.
+fn decrypt_payload(
+    opprf_output: &Block512,
+    iv: Block,
+    ct: &[u8],
+    tag: Block,
+) -> Result<Option<Vec<u8>>, Error> {
+    let (enc, mac) = payload_keys(opprf_output);
+    if cbc_mac(&mac, iv, ct) != tag {
+        return Ok(None);
+    }
+    let mut pt = ct.to_vec();
+    ctr_xor(&enc, iv, &mut pt);
+    let mut len = [0u8; 8];
+    len.copy_from_slice(&pt[0..8]);
+    let len = u64::from_le_bytes(len) as usize;
+    if len > pt.len() - 8 || pt[8 + len..].iter().any(|b| *b != 0) {
+        return Err(Error::PsiProtocolError(
+            "malformed payload padding".to_string(),
+        ));
+    }
+    pt.truncate(8 + len);
+    Ok(Some(pt.split_off(8)))
+}
+
 fn encode_inputs(opprf_outputs: &[Block512]) -> Vec<u16> {
     opprf_outputs
         .iter()
@@ -353,8 +1132,9 @@ fn encode_inputs(opprf_outputs: &[Block512]) -> Vec<u16> {
         .collect()
 }
 
-/// Fancy function to compute the intersection and return encoded vector of 0/1 masks.
-fn fancy_compute_intersection<F: Fancy>(
+// Fancy function to compute whether the sender's and receiver's OPPRF outputs
+// match in each bin.
+fn fancy_compute_equalities<F: Fancy>(
     f: &mut F,
     sender_inputs: &[F::Item],
     receiver_inputs: &[F::Item],
@@ -372,53 +1152,175 @@ fn fancy_compute_intersection<F: Fancy>(
         .collect()
 }
 
-/// Fancy function to compute the cardinaility and return CRT value containing the result
-/// along with the moduli of that value.
-fn fancy_compute_cardinality<F: Fancy>(
+// Assemble the wires of each bin.
+fn bin_wires<W>(
+    eqs: Vec<W>,
+    sender_payloads: Option<Vec<CrtBundle<W>>>,
+    receiver_payloads: Option<Vec<CrtBundle<W>>>,
+) -> Vec<BinWires<W>> {
+    let mut sender_payloads = sender_payloads.map(|ps| ps.into_iter());
+    let mut receiver_payloads = receiver_payloads.map(|ps| ps.into_iter());
+    eqs.into_iter()
+        .map(|eq| BinWires {
+            eq,
+            sender_payload: sender_payloads.as_mut().and_then(|ps| ps.next()),
+            receiver_payload: receiver_payloads.as_mut().and_then(|ps| ps.next()),
+        })
+        .collect()
+}
+
+/// Fancy function which outputs, for each bin, the equality bit, followed by
+/// the sender's payload if the bin's element is in the intersection (and zero
+/// otherwise), followed by the receiver's payload, where payloads are only
+/// present if the corresponding party has payloads.
+pub fn fancy_compute_bins<F: Fancy>(
     f: &mut F,
-    sender_inputs: &[F::Item],
-    receiver_inputs: &[F::Item],
-) -> Result<(Vec<F::Item>, Vec<u16>), F::Error> {
-    assert_eq!(sender_inputs.len(), receiver_inputs.len());
+    bins: &[BinWires<F::Item>],
+) -> Result<Vec<F::Item>, F::Error> {
+    let mut outs = Vec::new();
+    for bin in bins.iter() {
+        outs.push(bin.eq.clone());
+        if let Some(payload) = bin.sender_payload.as_ref() {
+            for w in payload.iter() {
+                outs.push(f.mul(w, &bin.eq)?);
+            }
+        }
+        if let Some(payload) = bin.receiver_payload.as_ref() {
+            outs.extend(payload.iter().cloned());
+        }
+    }
+    Ok(outs)
+}
 
-    let eqs = sender_inputs
-        .chunks(HASH_SIZE * 8)
-        .zip_eq(receiver_inputs.chunks(HASH_SIZE * 8))
-        .map(|(xs, ys)| {
-            f.eq_bundles(
-                &BinaryBundle::new(xs.to_vec()),
-                &BinaryBundle::new(ys.to_vec()),
-            )
-        })
-        .collect::<Result<Vec<F::Item>, F::Error>>()?;
+/// Fancy function to compute the intersection and return encoded vector of 0/1 masks.
+fn fancy_compute_intersection<F: Fancy>(
+    _: &mut F,
+    bins: &[BinWires<F::Item>],
+) -> Result<Vec<F::Item>, F::Error> {
+    Ok(bins.iter().map(|bin| bin.eq.clone()).collect())
+}
 
-    let qs = fancy_garbling::util::primes_with_width(16);
-    let q = fancy_garbling::util::product(&qs);
+// Fancy function to compute `∑ eqᵢ ⋅ vᵢ` modulo `q` over the bins, where
+// `value` maps each bin to its CRT value `vᵢ` modulo `q`, with `None` denoting
+// the constant one.
+fn fancy_accumulate<F, V>(
+    f: &mut F,
+    bins: &[BinWires<F::Item>],
+    q: u128,
+    value: V,
+) -> Result<CrtBundle<F::Item>, F::Error>
+where
+    F: Fancy,
+    V: Fn(&BinWires<F::Item>) -> Option<&CrtBundle<F::Item>>,
+{
     let mut acc = f.crt_constant_bundle(0, q)?;
     let one = f.crt_constant_bundle(1, q)?;
 
-    for b in eqs.into_iter() {
-        let b_ws = one
+    for bin in bins.iter() {
+        let b_ws = value(bin)
+            .unwrap_or(&one)
             .iter()
-            .map(|w| f.mul(w, &b))
+            .map(|w| f.mul(w, &bin.eq))
             .collect::<Result<Vec<F::Item>, F::Error>>()?;
         let b_crt = CrtBundle::new(b_ws);
         acc = f.crt_add(&acc, &b_crt)?;
     }
 
-    Ok((acc.wires().to_vec(), qs))
+    Ok(acc)
 }
 
-impl SemiHonest for Sender {}
-impl SemiHonest for Receiver {}
+/// Fancy function to compute the cardinality and return CRT value containing the result,
+/// whose moduli are given by `primes_with_width(CARDINALITY_WIDTH)`.
+fn fancy_compute_cardinality<F: Fancy>(
+    f: &mut F,
+    bins: &[BinWires<F::Item>],
+) -> Result<Vec<F::Item>, F::Error> {
+    let qs = fancy_garbling::util::primes_with_width(CARDINALITY_WIDTH);
+    let q = fancy_garbling::util::product(&qs);
+    let acc = fancy_accumulate(f, bins, q, |_| None)?;
+    Ok(acc.wires().to_vec())
+}
+
+/// Fancy function to compute the sum of the sender's payloads over the
+/// intersection modulo `q`, optionally followed by the cardinality of the
+/// intersection.
+fn fancy_compute_intersection_sum<F: Fancy>(
+    f: &mut F,
+    bins: &[BinWires<F::Item>],
+    q: u128,
+    with_cardinality: bool,
+) -> Result<Vec<F::Item>, F::Error> {
+    let sum = fancy_accumulate(f, bins, q, |bin| bin.sender_payload.as_ref())?;
+    let mut outs = sum.wires().to_vec();
+    if with_cardinality {
+        outs.extend(fancy_compute_cardinality(f, bins)?);
+    }
+    Ok(outs)
+}
+
+// Fancy function to count the bins whose equality bit is one, as a binary
+// bundle of `nbits` bits.
+fn fancy_count<F: Fancy>(
+    f: &mut F,
+    bins: &[BinWires<F::Item>],
+    nbits: usize,
+) -> Result<BinaryBundle<F::Item>, F::Error> {
+    let zero = f.constant(0, 2)?;
+    let mut acc = f.bin_constant_bundle(0, nbits)?;
+    for bin in bins.iter() {
+        let mut b_ws = vec![bin.eq.clone()];
+        b_ws.resize(nbits, zero.clone());
+        acc = f.bin_addition_no_carry(&acc, &BinaryBundle::new(b_ws))?;
+    }
+    Ok(acc)
+}
+
+/// Fancy function to compute the intersection only if its cardinality is at
+/// least `threshold`. Returns the result of the comparison, followed by the
+/// encoded vector of 0/1 masks (which is all zero if the comparison fails),
+/// optionally followed by the cardinality in binary.
+fn fancy_compute_threshold_intersection<F: Fancy>(
+    f: &mut F,
+    bins: &[BinWires<F::Item>],
+    threshold: usize,
+    reveal_cardinality: bool,
+) -> Result<Vec<F::Item>, F::Error> {
+    // Use enough bits to represent both the cardinality and the threshold,
+    // plus one so that the comparison does not overflow.
+    let nbits = (usize::BITS - bins.len().max(threshold).leading_zeros()) as usize + 1;
+    let cardinality = fancy_count(f, bins, nbits)?;
+    let threshold = f.bin_constant_bundle(threshold as u128, nbits)?;
+    let geq = f.bin_geq(&cardinality, &threshold)?;
+    let mut outs = vec![geq.clone()];
+    for bin in bins.iter() {
+        outs.push(f.and(&bin.eq, &geq)?);
+    }
+    if reveal_cardinality {
+        outs.extend(cardinality.wires().iter().cloned());
+    }
+    Ok(outs)
+}
+
+// Return the payload modulus, checking that the sender has payloads.
+fn sender_payload_modulus(info: Option<PayloadInfo>) -> Result<u128, Error> {
+    match info {
+        Some(info) if info.sender => Ok(info.modulus),
+        _ => Err(Error::PsiProtocolError(
+            "intersection-sum requires sender payloads".to_string(),
+        )),
+    }
+}
+
+impl<OPPRF> SemiHonest for Sender<OPPRF> {}
+impl<OPPRF> SemiHonest for Receiver<OPPRF> {}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::rand_vec_vec;
+    use crate::utils::{rand_vec, rand_vec_vec};
     use scuttlebutt::{AesRng, Channel};
     use std::{
-        io::{BufReader, BufWriter},
+        io::{BufReader, BufWriter, Write},
         os::unix::net::UnixStream,
     };
 
@@ -437,7 +1339,7 @@ mod tests {
             let reader = BufReader::new(sender.try_clone().unwrap());
             let writer = BufWriter::new(sender);
             let mut channel = Channel::new(reader, writer);
-            let mut psi = Sender::init(&mut channel, &mut rng).unwrap();
+            let mut psi: Sender = Sender::init(&mut channel, &mut rng).unwrap();
 
             let state = psi.send(&sender_inputs, &mut channel, &mut rng).unwrap();
             state.compute_cardinality(&mut channel, &mut rng).unwrap();
@@ -447,7 +1349,7 @@ mod tests {
         let reader = BufReader::new(receiver.try_clone().unwrap());
         let writer = BufWriter::new(receiver);
         let mut channel = Channel::new(reader, writer);
-        let mut psi = Receiver::init(&mut channel, &mut rng).unwrap();
+        let mut psi: Receiver = Receiver::init(&mut channel, &mut rng).unwrap();
 
         let state = psi
             .receive(&receiver_inputs, &mut channel, &mut rng)
@@ -458,42 +1360,328 @@ mod tests {
     }
 
     #[test]
-    fn payloads() {
-        let payload_size = 16;
+    fn circuit_payloads() {
+        let mut rng = AesRng::new();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let qs = fancy_garbling::util::primes_with_width(32);
+        let modulus = fancy_garbling::util::product(&qs);
+        let sender_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        let mut receiver_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        receiver_inputs[0..SET_SIZE / 2].clone_from_slice(&sender_inputs[0..SET_SIZE / 2]);
+        let sender_payloads = (0..SET_SIZE)
+            .map(|_| rng.gen::<u128>() % modulus)
+            .collect_vec();
+        let receiver_payloads = (0..SET_SIZE)
+            .map(|_| rng.gen::<u128>() % modulus)
+            .collect_vec();
+        let sender_inputs_ = sender_inputs.clone();
+        let sender_payloads_ = sender_payloads.clone();
+
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut psi: Sender = Sender::init(&mut channel, &mut rng).unwrap();
+            let state = psi
+                .send_with_payloads(
+                    &sender_inputs_,
+                    &sender_payloads_,
+                    modulus,
+                    &mut channel,
+                    &mut rng,
+                )
+                .unwrap();
+            state
+                .compute_circuit(&mut channel, &mut rng, fancy_compute_bins)
+                .unwrap();
+        });
+
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi: Receiver = Receiver::init(&mut channel, &mut rng).unwrap();
+        let state = psi
+            .receive_with_payloads(
+                &receiver_inputs,
+                &receiver_payloads,
+                modulus,
+                &mut channel,
+                &mut rng,
+            )
+            .unwrap();
+        let outs = state
+            .compute_circuit(&mut channel, &mut rng, fancy_compute_bins)
+            .unwrap();
+        handle.join().unwrap();
+
+        let mut nfound = 0;
+        for (i, out) in state.input_indices().into_iter().zip_eq(outs.chunks(1 + 2 * qs.len())) {
+            let sender_payload = fancy_garbling::util::crt_inv(&out[1..1 + qs.len()], &qs);
+            let receiver_payload = fancy_garbling::util::crt_inv(&out[1 + qs.len()..], &qs);
+            match i {
+                Some(i) => {
+                    let j = sender_inputs.iter().position(|x| *x == receiver_inputs[i]);
+                    assert_eq!(out[0] == 1, j.is_some());
+                    assert_eq!(sender_payload, j.map_or(0, |j| sender_payloads[j]));
+                    assert_eq!(receiver_payload, receiver_payloads[i]);
+                    nfound += out[0] as usize;
+                }
+                None => {
+                    assert_eq!(out[0], 0);
+                    assert_eq!(receiver_payload, 0);
+                }
+            }
+        }
+        assert_eq!(nfound, SET_SIZE / 2);
+    }
 
+    #[test]
+    fn shared_outputs() {
         let mut rng = AesRng::new();
         let (sender, receiver) = UnixStream::pair().unwrap();
+        let qs = fancy_garbling::util::primes_with_width(16);
+        let modulus = fancy_garbling::util::product(&qs);
         let sender_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
-        let receiver_inputs = sender_inputs.clone();
-        let payloads = rand_vec_vec(SET_SIZE, payload_size, &mut rng);
+        let mut receiver_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        receiver_inputs[0..SET_SIZE / 2].clone_from_slice(&sender_inputs[0..SET_SIZE / 2]);
+        let sender_payloads = (0..SET_SIZE)
+            .map(|_| rng.gen::<u128>() % modulus)
+            .collect_vec();
+        let sender_inputs_ = sender_inputs.clone();
+        let sender_payloads_ = sender_payloads.clone();
+
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut psi: Sender = Sender::init(&mut channel, &mut rng).unwrap();
+            let state = psi
+                .send_with_payloads(
+                    &sender_inputs_,
+                    &sender_payloads_,
+                    modulus,
+                    &mut channel,
+                    &mut rng,
+                )
+                .unwrap();
+            let shares = state
+                .compute_shared_intersection(&mut channel, &mut rng)
+                .unwrap();
+            // Consume the shares in a later session, revealing them.
+            let mut gb =
+                Garbler::<_, AesRng, OtSender>::new(&mut channel, AesRng::from_seed(rng.gen()))
+                    .unwrap();
+            let outs = shares.input(&mut gb, PartyId::Garbler).unwrap();
+            gb.outputs(&outs).unwrap();
+            channel.flush().unwrap();
+            shares.to_bytes()
+        });
+
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi: Receiver = Receiver::init(&mut channel, &mut rng).unwrap();
+        let state = psi
+            .receive(&receiver_inputs, &mut channel, &mut rng)
+            .unwrap();
+        let shares = state
+            .compute_shared_intersection(&mut channel, &mut rng)
+            .unwrap();
+        let mut ev =
+            Evaluator::<_, AesRng, OtReceiver>::new(&mut channel, AesRng::from_seed(rng.gen()))
+                .unwrap();
+        let outs = shares.input(&mut ev, PartyId::Evaluator).unwrap();
+        let revealed = ev.outputs(&outs).unwrap().unwrap();
+        let sender_shares = SharedOutputs::from_bytes(&handle.join().unwrap()).unwrap();
+        let outs = shares.reconstruct(&sender_shares).unwrap();
+        assert_eq!(outs, revealed);
+
+        let mut nfound = 0;
+        for (i, out) in state.input_indices().into_iter().zip_eq(outs.chunks(1 + qs.len())) {
+            let sender_payload = fancy_garbling::util::crt_inv(&out[1..], &qs);
+            match i {
+                Some(i) => {
+                    let j = sender_inputs.iter().position(|x| *x == receiver_inputs[i]);
+                    assert_eq!(out[0] == 1, j.is_some());
+                    assert_eq!(sender_payload, j.map_or(0, |j| sender_payloads[j]));
+                    nfound += out[0] as usize;
+                }
+                None => assert_eq!(out[0], 0),
+            }
+        }
+        assert_eq!(nfound, SET_SIZE / 2);
+    }
+
+    #[test]
+    fn intersection_sum() {
+        let mut rng = AesRng::new();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let modulus = fancy_garbling::util::modulus_with_width(40);
+        let sender_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        let mut receiver_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        receiver_inputs[0..SET_SIZE / 4].clone_from_slice(&sender_inputs[0..SET_SIZE / 4]);
+        let values = (0..SET_SIZE)
+            .map(|_| u128::from(rng.gen::<u32>()))
+            .collect_vec();
+        let expected = values[0..SET_SIZE / 4].iter().sum::<u128>();
 
         let handle = std::thread::spawn(move || {
             let mut rng = AesRng::new();
             let reader = BufReader::new(sender.try_clone().unwrap());
             let writer = BufWriter::new(sender);
             let mut channel = Channel::new(reader, writer);
-            let mut psi = Sender::init(&mut channel, &mut rng).unwrap();
+            let mut psi: Sender = Sender::init(&mut channel, &mut rng).unwrap();
+            let state = psi
+                .send_with_payloads(&sender_inputs, &values, modulus, &mut channel, &mut rng)
+                .unwrap();
+            state
+                .compute_intersection_sum(false, &mut channel, &mut rng)
+                .unwrap();
+            state
+                .compute_intersection_sum(true, &mut channel, &mut rng)
+                .unwrap();
+        });
+
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi: Receiver = Receiver::init(&mut channel, &mut rng).unwrap();
+        let state = psi
+            .receive(&receiver_inputs, &mut channel, &mut rng)
+            .unwrap();
+        let result = state
+            .compute_intersection_sum(false, &mut channel, &mut rng)
+            .unwrap();
+        assert_eq!(result, (expected, None));
+        let result = state
+            .compute_intersection_sum(true, &mut channel, &mut rng)
+            .unwrap();
+        assert_eq!(result, (expected, Some(SET_SIZE / 4)));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn threshold_intersection() {
+        let mut rng = AesRng::new();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let sender_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        let mut receiver_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        let n = SET_SIZE / 4;
+        receiver_inputs[0..n].clone_from_slice(&sender_inputs[0..n]);
+        // Thresholds just above and at the cardinality, alongside whether to
+        // reveal the cardinality.
+        let params = [(n + 1, true), (n + 1, false), (n, true), (n, false), (0, false)];
+
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut psi: Sender = Sender::init(&mut channel, &mut rng).unwrap();
             let state = psi.send(&sender_inputs, &mut channel, &mut rng).unwrap();
-            state.receive_payloads(payload_size, &mut channel).unwrap()
+            for (threshold, reveal_cardinality) in params.iter() {
+                state
+                    .compute_threshold_intersection(
+                        *threshold,
+                        *reveal_cardinality,
+                        &mut channel,
+                        &mut rng,
+                    )
+                    .unwrap();
+            }
+        });
+
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi: Receiver = Receiver::init(&mut channel, &mut rng).unwrap();
+        let state = psi
+            .receive(&receiver_inputs, &mut channel, &mut rng)
+            .unwrap();
+        for (threshold, reveal_cardinality) in params.iter() {
+            let (intersection, cardinality) = state
+                .compute_threshold_intersection(
+                    *threshold,
+                    *reveal_cardinality,
+                    &mut channel,
+                    &mut rng,
+                )
+                .unwrap();
+            if *threshold <= n {
+                let mut intersection = intersection.unwrap();
+                intersection.sort();
+                let mut expected = receiver_inputs[0..n].to_vec();
+                expected.sort();
+                assert_eq!(intersection, expected);
+            } else {
+                assert!(intersection.is_none());
+            }
+            assert_eq!(cardinality, if *reveal_cardinality { Some(n) } else { None });
+        }
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn payloads() {
+        let mut rng = AesRng::new();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let sender_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        let mut receiver_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        receiver_inputs[0..SET_SIZE / 2].clone_from_slice(&sender_inputs[0..SET_SIZE / 2]);
+        let payloads = (0..SET_SIZE)
+            .map(|i| rand_vec(i % 40, &mut rng))
+            .collect_vec();
+
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut psi: Sender = Sender::init(&mut channel, &mut rng).unwrap();
+            let state = psi.send(&sender_inputs, &mut channel, &mut rng).unwrap();
+            state.receive_payloads(&mut channel).unwrap()
         });
 
         let mut rng = AesRng::new();
         let reader = BufReader::new(receiver.try_clone().unwrap());
         let writer = BufWriter::new(receiver);
         let mut channel = Channel::new(reader, writer);
-        let mut psi = Receiver::init(&mut channel, &mut rng).unwrap();
+        let mut psi: Receiver = Receiver::init(&mut channel, &mut rng).unwrap();
 
         let state = psi
             .receive(&receiver_inputs, &mut channel, &mut rng)
             .unwrap();
         state
-            .send_payloads(&payloads, &mut channel, &mut rng)
+            .send_payloads_with_bucket_size(&payloads, 32, &mut channel, &mut rng)
             .unwrap();
 
-        let received_payloads = handle.join().unwrap();
+        let mut received_payloads = handle.join().unwrap();
+        let mut expected = payloads[0..SET_SIZE / 2].to_vec();
+        received_payloads.sort();
+        expected.sort();
+        assert_eq!(received_payloads, expected);
+    }
 
-        for payload in payloads.iter() {
-            assert!(received_payloads.contains(payload));
-        }
+    #[test]
+    fn payload_encryption() {
+        let mut rng = AesRng::new();
+        let key = rng.gen::<Block512>();
+        let iv = rng.gen::<Block>();
+        let payload = rand_vec(21, &mut rng);
+        let (ct, tag) = encrypt_payload(&key, iv, &payload, 32);
+        assert_eq!(ct.len(), 8 + 32);
+        assert_eq!(
+            decrypt_payload(&key, iv, &ct, tag).unwrap(),
+            Some(payload)
+        );
+        // Any modification is detected.
+        let mut ct_ = ct.clone();
+        ct_[9] ^= 1;
+        assert_eq!(decrypt_payload(&key, iv, &ct_, tag).unwrap(), None);
+        assert_eq!(decrypt_payload(&key, iv, &ct[1..], tag).unwrap(), None);
+        assert_eq!(decrypt_payload(&key, iv ^ Block::from(1), &ct, tag).unwrap(), None);
+        assert_eq!(decrypt_payload(&rng.gen(), iv, &ct, tag).unwrap(), None);
     }
 }
@@ -0,0 +1,335 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `popsicle`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of the Kolesnikov-Rosulek-Trieu-Wang private set union
+//! protocol (cf. <https://eprint.iacr.org/2019/776>).
+//!
+//! The receiver learns `X ∪ Y` and the size of `X`, and the sender learns
+//! nothing. The sender places its set `X` in a cuckoo hash table, and the
+//! receiver places each element of its set `Y` in every bin it may hash to.
+//! For each bin `i`, the parties run a "reverse" private membership test: the
+//! receiver programs the OPPRF to map every element of its bin to a random
+//! target `tᵢ`, the sender queries it on the element `xᵢ` in its bin, and
+//! evaluates the KKRT OPRF on its output, while the receiver evaluates it on
+//! `tᵢ`.
+//!
+//! The sender then sends its OPRF outputs, and the encryptions of the `xᵢ`, in
+//! a random order. The receiver checks each output against the set of its own
+//! OPRF outputs, which tells it whether `xᵢ ∈ Y` without telling it `i`, and
+//! uses the negation of this bit as its choice bit in an oblivious transfer,
+//! which gives it the key to decrypt `xᵢ` exactly when `xᵢ ∉ Y`. As the order
+//! is random, the receiver does not learn which bins of the sender's cuckoo
+//! table hold an element of the intersection.
+
+use crate::{
+    cuckoo::{compute_masksize, CuckooHash},
+    utils,
+    Error,
+};
+use itertools::Itertools;
+use ocelot::{
+    oprf::{
+        self,
+        ProgrammableReceiver,
+        ProgrammableSender,
+        Receiver as OprfReceiver,
+        Sender as OprfSender,
+    },
+    ot::{self, Receiver as OtReceiver, Sender as OtSender},
+};
+use rand::{seq::SliceRandom, CryptoRng, Rng, RngCore, SeedableRng};
+use scuttlebutt::{cointoss, AbstractChannel, AesRng, Block, Block512, SemiHonest};
+use std::collections::HashSet;
+
+const NHASHES: usize = 3;
+
+// The length prefix of an encrypted dummy element, i.e., of an empty bin.
+const DUMMY: u64 = u64::MAX;
+
+/// Private set union sender.
+pub struct Sender {
+    opprf: oprf::KmprtReceiver,
+    oprf: oprf::KkrtSender,
+    ot: ot::AlszSender,
+}
+
+/// Private set union receiver.
+pub struct Receiver {
+    opprf: oprf::KmprtSender,
+    oprf: oprf::KkrtReceiver,
+    ot: ot::AlszReceiver,
+}
+
+// Truncate an OPPRF output to a `Block`.
+#[inline]
+fn block_of(output: &Block512) -> Block {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(output.prefix(16));
+    Block::from(bytes)
+}
+
+// Encrypt `input`, padded to `len` bytes, using the keystream given by `key`.
+// An `input` of `None` denotes a dummy element.
+fn encrypt(input: Option<&[u8]>, len: usize, key: Block) -> Vec<u8> {
+    let mut pt = Vec::with_capacity(8 + len);
+    match input {
+        Some(input) => {
+            pt.extend(&(input.len() as u64).to_le_bytes());
+            pt.extend(input);
+        }
+        None => pt.extend(&DUMMY.to_le_bytes()),
+    }
+    pt.resize(8 + len, 0);
+    let mut rng = AesRng::from_seed(key);
+    pt.iter().map(|b| b ^ rng.gen::<u8>()).collect()
+}
+
+// Decrypt ciphertext `ct` using the keystream given by `key`, returning `None`
+// for a dummy element.
+fn decrypt(ct: &[u8], key: Block) -> Result<Option<Vec<u8>>, Error> {
+    let mut rng = AesRng::from_seed(key);
+    let pt = ct.iter().map(|b| b ^ rng.gen::<u8>()).collect::<Vec<u8>>();
+    let mut len = [0u8; 8];
+    len.copy_from_slice(&pt[0..8]);
+    let len = u64::from_le_bytes(len);
+    if len == DUMMY {
+        Ok(None)
+    } else if len as usize > pt.len() - 8 {
+        Err(Error::PsiProtocolError(
+            "invalid length of decrypted element".to_string(),
+        ))
+    } else {
+        Ok(Some(pt[8..8 + len as usize].to_vec()))
+    }
+}
+
+impl Sender {
+    /// Initialize the PSU sender.
+    pub fn init<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let opprf = oprf::KmprtReceiver::init(channel, rng)?;
+        let oprf = oprf::KkrtSender::init(channel, rng)?;
+        let ot = ot::AlszSender::init(channel, rng)?;
+        Ok(Self { opprf, oprf, ot })
+    }
+
+    /// Run the PSU protocol over `inputs`.
+    pub fn send<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<(), Error> {
+        let key = cointoss::send(channel, &[rng.gen()], rng)?[0];
+        let hashed_inputs = utils::compress_and_hash_inputs(inputs, key);
+        let cuckoo = CuckooHash::new(&hashed_inputs, NHASHES)?;
+        let nbins = cuckoo.nbins;
+        channel.write_usize(nbins)?;
+        channel.flush()?;
+
+        // Query the OPPRF on each cuckoo hash entry, or a random value for an
+        // empty bin.
+        let table = cuckoo
+            .items
+            .iter()
+            .map(|opt_item| match opt_item {
+                Some(item) => item.entry,
+                None => rng.gen(),
+            })
+            .collect::<Vec<Block>>();
+        let opprf_outputs = self.opprf.receive(channel, &table, rng)?;
+
+        // Run the private equality tests, by sending `F(k_i, u_i)` for each
+        // OPPRF output `u_i`, with the bins in random order so that the
+        // receiver does not learn which bins hold an element of its set.
+        let masksize = compute_masksize(nbins)?;
+        let seeds = self.oprf.send(channel, nbins, rng)?;
+        let encoded = seeds
+            .into_iter()
+            .zip_eq(opprf_outputs)
+            .map(|(seed, output)| self.oprf.compute(seed, block_of(&output)))
+            .collect::<Vec<Block512>>();
+        let mut order = (0..nbins).collect::<Vec<usize>>();
+        order.shuffle(rng);
+        for &i in order.iter() {
+            channel.write_all(encoded[i].prefix(masksize))?;
+        }
+
+        // Encrypt each element under a key the receiver only learns if the
+        // element is not in its set, in the same order.
+        let len = inputs.iter().map(|x| x.len()).max().unwrap_or(0);
+        channel.write_usize(len)?;
+        channel.flush()?;
+        let keys = (0..nbins)
+            .map(|_| (rng.gen::<Block>(), rng.gen::<Block>()))
+            .collect::<Vec<(Block, Block)>>();
+        self.ot.send(channel, &keys, rng)?;
+        for (&i, (_, key)) in order.iter().zip_eq(keys) {
+            let input = cuckoo.items[i]
+                .as_ref()
+                .map(|item| inputs[item.input_index].as_slice());
+            channel.write_all(&encrypt(input, len, key))?;
+        }
+        channel.flush()?;
+        Ok(())
+    }
+}
+
+impl Receiver {
+    /// Initialize the PSU receiver.
+    pub fn init<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let opprf = oprf::KmprtSender::init(channel, rng)?;
+        let oprf = oprf::KkrtReceiver::init(channel, rng)?;
+        let ot = ot::AlszReceiver::init(channel, rng)?;
+        Ok(Self { opprf, oprf, ot })
+    }
+
+    /// Run the PSU protocol over `inputs`, returning the union of `inputs` and
+    /// the sender's set. The output starts with `inputs`, followed by the
+    /// sender's elements not in `inputs`, in no particular order.
+    pub fn receive<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let key = cointoss::receive(channel, &[rng.gen()])?[0];
+        let hashes = utils::compress_and_hash_inputs(inputs, key);
+        let nbins = channel.read_usize()?;
+
+        // Map inputs to the table using all hash functions.
+        let mut table = vec![Vec::new(); nbins];
+        for &x in &hashes {
+            let mut bins = Vec::with_capacity(NHASHES);
+            for h in 0..NHASHES {
+                let bin = CuckooHash::bin(x, h, nbins);
+                table[bin].push(x ^ Block::from(h as u128));
+                bins.push(bin);
+            }
+            // If all hash functions map `x` to the same bin, add a random
+            // element to that bin so that its size does not reveal this.
+            if bins.iter().skip(1).all(|&x| x == bins[0]) {
+                table[bins[0]].push(rng.gen());
+            }
+        }
+
+        // Program the OPPRF to map every element in bin `i` to target `t_i`.
+        let ts = (0..nbins).map(|_| rng.gen::<Block512>()).collect_vec();
+        let points = table
+            .into_iter()
+            .zip_eq(ts.iter())
+            .flat_map(|(bin, t)| bin.into_iter().map(move |item| (item, *t)))
+            .collect_vec();
+        self.opprf.send(channel, &points, nbins, rng)?;
+
+        // Run the private equality tests, learning whether each of the
+        // sender's elements is in our set, but not which bin it is in, as the
+        // sender shuffles the bins.
+        let masksize = compute_masksize(nbins)?;
+        let ts = ts.iter().map(block_of).collect_vec();
+        let outputs = self.oprf.receive(channel, &ts, rng)?;
+        let outputs = outputs
+            .iter()
+            .map(|output| output.prefix(masksize).to_vec())
+            .collect::<HashSet<Vec<u8>>>();
+        let mut choices = Vec::with_capacity(nbins);
+        for _ in 0..nbins {
+            let encoded = channel.read_vec(masksize)?;
+            choices.push(!outputs.contains(&encoded));
+        }
+
+        // Decrypt the sender's elements not in our set.
+        let len = channel.read_usize()?;
+        let keys = self.ot.receive(channel, &choices, rng)?;
+        let mut union = inputs.to_vec();
+        for (choice, key) in choices.into_iter().zip_eq(keys) {
+            let ct = channel.read_vec(8 + len)?;
+            if choice {
+                if let Some(x) = decrypt(&ct, key)? {
+                    union.push(x);
+                }
+            }
+        }
+        Ok(union)
+    }
+}
+
+impl SemiHonest for Sender {}
+impl SemiHonest for Receiver {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{rand_vec, rand_vec_vec};
+    use scuttlebutt::{AesRng, Channel};
+    use std::{
+        collections::HashSet,
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    const ITEM_SIZE: usize = 8;
+    const SET_SIZE: usize = 1 << 8;
+
+    fn test_psu(sender_inputs: Vec<Vec<u8>>, receiver_inputs: Vec<Vec<u8>>) {
+        let expected = sender_inputs
+            .iter()
+            .chain(receiver_inputs.iter())
+            .cloned()
+            .collect::<HashSet<Vec<u8>>>();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut psu = Sender::init(&mut channel, &mut rng).unwrap();
+            psu.send(&sender_inputs, &mut channel, &mut rng).unwrap();
+        });
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psu = Receiver::init(&mut channel, &mut rng).unwrap();
+        let union = psu
+            .receive(&receiver_inputs, &mut channel, &mut rng)
+            .unwrap();
+        handle.join().unwrap();
+        assert_eq!(union.len(), expected.len());
+        assert_eq!(union.into_iter().collect::<HashSet<Vec<u8>>>(), expected);
+    }
+
+    #[test]
+    fn test_psu_random() {
+        let mut rng = AesRng::new();
+        let mut sender_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        let receiver_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        // Make a quarter of the sender's elements overlap with the receiver's.
+        for (x, y) in sender_inputs.iter_mut().zip(receiver_inputs.iter()) {
+            if rng.gen::<u8>() < 64 {
+                *x = y.clone();
+            }
+        }
+        test_psu(sender_inputs, receiver_inputs);
+    }
+
+    #[test]
+    fn test_psu_variable_length() {
+        let mut rng = AesRng::new();
+        let sender_inputs = (1..SET_SIZE)
+            .map(|i| rand_vec(i % 40, &mut rng))
+            .collect::<HashSet<Vec<u8>>>()
+            .into_iter()
+            .collect::<Vec<Vec<u8>>>();
+        let receiver_inputs = sender_inputs[0..SET_SIZE / 2].to_vec();
+        test_psu(sender_inputs, receiver_inputs);
+    }
+}
@@ -49,7 +49,7 @@ impl Sender {
         channel: &mut C,
         rng: &mut RNG,
     ) -> Result<(), Error> {
-        let key = cointoss::send(channel, &[rng.gen()])?[0];
+        let key = cointoss::send(channel, &[rng.gen()], rng)?[0];
         let inputs = utils::compress_and_hash_inputs(inputs, key);
         let masksize = compute_masksize(inputs.len())?;
         let nbins = channel.read_usize()?;
@@ -87,7 +87,7 @@ impl Sender {
         channel: &mut C,
         rng: &mut RNG,
     ) -> Result<Vec<Block>, Error> {
-        let key = cointoss::send(channel, &[rng.gen()])?[0];
+        let key = cointoss::send(channel, &[rng.gen()], rng)?[0];
         let masksize = compute_masksize(inputs.len())?;
         let inputs = utils::compress_and_hash_inputs(inputs, key);
         let nbins = channel.read_usize()?;
@@ -0,0 +1,482 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `popsicle`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of unbalanced private set intersection with a reusable
+//! encoding of the large set, following Kiss, Liu, Schneider, Asokan, and
+//! Pinkas (cf. <https://eprint.iacr.org/2017/670>).
+//!
+//! The sender holds a large, slowly-changing set `X`, and the receiver issues
+//! queries with small sets `Y`. The sender evaluates the Diffie-Hellman OPRF
+//! `F_k` on its own set locally, and inserts the outputs into a Bloom filter.
+//! This encoding is computed once (see `Sender::encode`), can be persisted (see
+//! `EncodedSet::write` and `EncodedSet::read`), and is sent to the receiver
+//! once. Each query then only runs the OPRF on the receiver's set, after which
+//! the receiver looks up `F_k(y)` in the filter. Since the filter only holds
+//! hashes of OPRF outputs, it reveals nothing about `X` beyond its size.
+//!
+//! Alternatively, the sender can encode its set as a cuckoo filter, which is
+//! smaller for the same false positive rate (see `Sender::encode_cuckoo` and
+//! `CuckooEncodedSet`). The outputs are inserted in random order, so that the
+//! position of a fingerprint in the filter does not depend on the order of
+//! `X`.
+//!
+//! Because the OPRF key `k` must remain the same for the encoding to be
+//! reused, the sender needs to persist its key as well (see `Sender::key` and
+//! `Sender::from_key`).
+
+use crate::{utils, Error};
+use curve25519_dalek::scalar::Scalar;
+use ocelot::oprf::{self, Receiver as OprfReceiver, Sender as OprfSender};
+use rand::{seq::SliceRandom, CryptoRng, RngCore};
+use scuttlebutt::{
+    bloomfilter::BloomFilter,
+    cuckoofilter::CuckooFilter,
+    AbstractChannel,
+    Block,
+    Block512,
+    SemiHonest,
+};
+use std::io::{ErrorKind, Read, Write};
+
+// Compress `inputs` into blocks. This uses a fixed public key, so that the
+// encoding does not depend on the session.
+#[inline]
+fn compress_inputs(inputs: &[Vec<u8>]) -> Vec<Block> {
+    utils::compress_and_hash_inputs(inputs, Block::default())
+}
+
+// The tags written at the start of each encoding, so that an encoding cannot
+// be read as the wrong kind of filter.
+const BLOOM_FORMAT: u64 = 1;
+const CUCKOO_FORMAT: u64 = 2;
+
+fn write_u64<W: Write>(writer: &mut W, value: usize) -> Result<(), Error> {
+    writer.write_all(&(value as u64).to_le_bytes())?;
+    Ok(())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<usize, Error> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes) as usize)
+}
+
+fn read_format<R: Read>(reader: &mut R, format: u64) -> Result<(), Error> {
+    if read_u64(reader)? as u64 != format {
+        return Err(
+            std::io::Error::new(ErrorKind::InvalidData, "Invalid encoded set format").into(),
+        );
+    }
+    Ok(())
+}
+
+/// Default false positive rate of the encoded set, in bits. Each queried item
+/// not in the sender's set is a false positive with probability about
+/// `2^{-40}`.
+pub const DEFAULT_FPBITS: usize = 40;
+
+/// Default fingerprint size of a cuckoo-filter encoded set. Each queried item
+/// not in the sender's set is a false positive with probability about
+/// `2^{-40}`.
+pub const DEFAULT_CUCKOO_FPBITS: usize = 43;
+
+/// A sender's set, encoded as a Bloom filter over the OPRF outputs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncodedSet {
+    nitems: usize,
+    filter: BloomFilter,
+}
+
+impl EncodedSet {
+    /// Return the number of encoded items.
+    pub fn len(&self) -> usize {
+        self.nitems
+    }
+
+    /// Return whether the encoded set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.nitems == 0
+    }
+
+    /// Write the encoded set to `writer`.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let bytes = self.filter.as_bytes();
+        write_u64(writer, BLOOM_FORMAT as usize)?;
+        write_u64(writer, self.nitems)?;
+        write_u64(writer, self.filter.len())?;
+        write_u64(writer, self.filter.nhashes())?;
+        write_u64(writer, bytes.len())?;
+        writer.write_all(&bytes)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read an encoded set, as written by `write`, from `reader`.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        read_format(reader, BLOOM_FORMAT)?;
+        let nitems = read_u64(reader)?;
+        let size = read_u64(reader)?;
+        let nhashes = read_u64(reader)?;
+        let nbytes = read_u64(reader)?;
+        let mut bytes = vec![];
+        reader.take(nbytes as u64).read_to_end(&mut bytes)?;
+        if size == 0 || bytes.len() != size.div_ceil(8) {
+            return Err(std::io::Error::new(ErrorKind::InvalidData, "Invalid encoded set").into());
+        }
+        let filter = BloomFilter::from_bytes(&bytes, size, nhashes);
+        Ok(Self { nitems, filter })
+    }
+}
+
+/// A sender's set, encoded as a cuckoo filter over the OPRF outputs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CuckooEncodedSet {
+    nitems: usize,
+    filter: CuckooFilter,
+}
+
+impl CuckooEncodedSet {
+    /// Return the number of encoded items.
+    pub fn len(&self) -> usize {
+        self.nitems
+    }
+
+    /// Return whether the encoded set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.nitems == 0
+    }
+
+    /// Write the encoded set to `writer`.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let bytes = self.filter.as_bytes();
+        write_u64(writer, CUCKOO_FORMAT as usize)?;
+        write_u64(writer, self.nitems)?;
+        write_u64(writer, self.filter.nbuckets())?;
+        write_u64(writer, self.filter.fpbits())?;
+        write_u64(writer, bytes.len())?;
+        writer.write_all(&bytes)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read an encoded set, as written by `write`, from `reader`.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        read_format(reader, CUCKOO_FORMAT)?;
+        let nitems = read_u64(reader)?;
+        let nbuckets = read_u64(reader)?;
+        let fpbits = read_u64(reader)?;
+        let nbytes = read_u64(reader)?;
+        let mut bytes = vec![];
+        reader.take(nbytes as u64).read_to_end(&mut bytes)?;
+        let filter = CuckooFilter::from_bytes(&bytes, nbuckets, fpbits).ok_or_else(|| {
+            std::io::Error::new(ErrorKind::InvalidData, "Invalid encoded set")
+        })?;
+        Ok(Self { nitems, filter })
+    }
+}
+
+/// Unbalanced private set intersection sender.
+pub struct Sender {
+    oprf: oprf::DhSender,
+}
+
+/// Unbalanced private set intersection receiver.
+pub struct Receiver {
+    oprf: oprf::DhReceiver,
+}
+
+impl Sender {
+    /// Initialize the PSI sender with a random OPRF key.
+    pub fn init<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let oprf = oprf::DhSender::init(channel, rng)?;
+        Ok(Self { oprf })
+    }
+
+    /// Create a PSI sender using the OPRF key `key`. Sets encoded under the
+    /// same key can be reused across senders.
+    pub fn from_key(key: Scalar) -> Self {
+        Self {
+            oprf: oprf::DhSender::from_key(key),
+        }
+    }
+
+    /// Return the sender's OPRF key.
+    pub fn key(&self) -> &Scalar {
+        self.oprf.key()
+    }
+
+    /// Set the number of threads used for evaluating the OPRF.
+    pub fn set_nthreads(&mut self, nthreads: usize) {
+        self.oprf.set_nthreads(nthreads);
+    }
+
+    /// Encode `inputs` under the sender's key, such that each queried item not
+    /// in `inputs` is a false positive with probability about `2^{-fpbits}`.
+    pub fn encode(&self, inputs: &[Vec<u8>], fpbits: usize) -> Result<EncodedSet, Error> {
+        if fpbits == 0 || fpbits > 64 {
+            return Err(Error::PsiProtocolError(format!(
+                "invalid fingerprint size: {}",
+                fpbits
+            )));
+        }
+        let inputs = compress_inputs(inputs);
+        let outputs = self.oprf.evaluate_many(&inputs);
+        let p = 2f64.powi(-(fpbits as i32));
+        let size = (BloomFilter::compute_expansion(p) * outputs.len() as f64).ceil() as usize;
+        let mut filter = BloomFilter::new(size.max(1), BloomFilter::compute_nhashes(p));
+        for output in outputs.iter() {
+            filter.insert(output);
+        }
+        Ok(EncodedSet {
+            nitems: outputs.len(),
+            filter,
+        })
+    }
+
+    /// Encode `inputs` under the sender's key as a cuckoo filter, using
+    /// fingerprints of `fpbits` bits. Each queried item not in `inputs` is a
+    /// false positive with probability about `2^{3 - fpbits}`.
+    pub fn encode_cuckoo<RNG: CryptoRng + RngCore>(
+        &self,
+        inputs: &[Vec<u8>],
+        fpbits: usize,
+        rng: &mut RNG,
+    ) -> Result<CuckooEncodedSet, Error> {
+        let inputs = compress_inputs(inputs);
+        let mut outputs = self.oprf.evaluate_many(&inputs);
+        // Insert the outputs in random order, as the filter layout depends on
+        // the insertion order.
+        outputs.shuffle(rng);
+        let mut filter = CuckooFilter::new(outputs.len(), fpbits)?;
+        for output in outputs.iter() {
+            if !filter.insert(output, rng) {
+                return Err(Error::CuckooHashFull);
+            }
+        }
+        Ok(CuckooEncodedSet {
+            nitems: outputs.len(),
+            filter,
+        })
+    }
+
+    /// Send the encoded set to the receiver. This only needs to happen once
+    /// per encoding, as the receiver can store it for later queries.
+    pub fn send_encoded<C: AbstractChannel>(
+        &mut self,
+        set: &EncodedSet,
+        channel: &mut C,
+    ) -> Result<(), Error> {
+        set.write(channel)
+    }
+
+    /// Send the cuckoo-filter encoded set to the receiver.
+    pub fn send_cuckoo_encoded<C: AbstractChannel>(
+        &mut self,
+        set: &CuckooEncodedSet,
+        channel: &mut C,
+    ) -> Result<(), Error> {
+        set.write(channel)
+    }
+
+    /// Answer a single query from the receiver. The cost is linear in the
+    /// receiver's set size only.
+    pub fn send<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<(), Error> {
+        let n = channel.read_usize()?;
+        self.oprf.send(channel, n, rng)?;
+        Ok(())
+    }
+}
+
+impl Receiver {
+    /// Initialize the PSI receiver.
+    pub fn init<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let oprf = oprf::DhReceiver::init(channel, rng)?;
+        Ok(Self { oprf })
+    }
+
+    /// Receive the sender's encoded set.
+    pub fn receive_encoded<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+    ) -> Result<EncodedSet, Error> {
+        EncodedSet::read(channel)
+    }
+
+    /// Receive the sender's cuckoo-filter encoded set.
+    pub fn receive_cuckoo_encoded<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+    ) -> Result<CuckooEncodedSet, Error> {
+        CuckooEncodedSet::read(channel)
+    }
+
+    /// Query the intersection of `inputs` with the sender's set `set`.
+    pub fn receive<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        set: &EncodedSet,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        self.query(inputs, channel, rng, |output| set.filter.contains(output))
+    }
+
+    /// Query the intersection of `inputs` with the sender's cuckoo-filter
+    /// encoded set `set`.
+    pub fn receive_cuckoo<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        set: &CuckooEncodedSet,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        self.query(inputs, channel, rng, |output| set.filter.contains(output))
+    }
+
+    // Run the OPRF on `inputs`, and return those whose output is in the
+    // sender's set according to `contains`.
+    fn query<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        channel: &mut C,
+        rng: &mut RNG,
+        contains: impl Fn(&Block512) -> bool,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let inputs_ = compress_inputs(inputs);
+        channel.write_usize(inputs_.len())?;
+        let outputs = self.oprf.receive(channel, &inputs_, rng)?;
+        Ok(inputs
+            .iter()
+            .zip(outputs)
+            .filter(|(_, output)| contains(output))
+            .map(|(x, _)| x.clone())
+            .collect())
+    }
+}
+
+impl SemiHonest for Sender {}
+impl SemiHonest for Receiver {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::rand_vec_vec;
+    use scuttlebutt::{AesRng, Channel};
+    use std::{
+        io::{BufReader, BufWriter, Cursor},
+        os::unix::net::UnixStream,
+    };
+
+    const ITEM_SIZE: usize = 8;
+    const SET_SIZE: usize = 1 << 12;
+    const QUERY_SIZE: usize = 1 << 6;
+
+    fn queries(sender_inputs: &[Vec<u8>], rng: &mut AesRng) -> Vec<Vec<Vec<u8>>> {
+        (0..3)
+            .map(|i| {
+                let mut query = rand_vec_vec(QUERY_SIZE - i, ITEM_SIZE, rng);
+                query.extend(sender_inputs[0..i].iter().cloned());
+                query
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_unbalanced_psi() {
+        let mut rng = AesRng::new();
+        let sender_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        let key = Scalar::random(&mut rng);
+        // Encode the large set once, and persist it.
+        let set = Sender::from_key(key)
+            .encode(&sender_inputs, DEFAULT_FPBITS)
+            .unwrap();
+        let mut bytes = vec![];
+        set.write(&mut bytes).unwrap();
+        assert_eq!(EncodedSet::read(&mut Cursor::new(&bytes)).unwrap(), set);
+        assert!(CuckooEncodedSet::read(&mut Cursor::new(&bytes)).is_err());
+
+        let queries = queries(&sender_inputs, &mut rng);
+        let nqueries = queries.len();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut psi = Sender::from_key(key);
+            psi.send_encoded(&set, &mut channel).unwrap();
+            for _ in 0..nqueries {
+                psi.send(&mut channel, &mut rng).unwrap();
+            }
+        });
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi = Receiver::init(&mut channel, &mut rng).unwrap();
+        let set = psi.receive_encoded(&mut channel).unwrap();
+        assert_eq!(set.len(), SET_SIZE);
+        for (i, query) in queries.iter().enumerate() {
+            let result = psi.receive(&set, query, &mut channel, &mut rng).unwrap();
+            assert_eq!(result, sender_inputs[0..i].to_vec());
+        }
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_unbalanced_psi_cuckoo() {
+        let mut rng = AesRng::new();
+        let sender_inputs = rand_vec_vec(SET_SIZE, ITEM_SIZE, &mut rng);
+        let key = Scalar::random(&mut rng);
+        let set = Sender::from_key(key)
+            .encode_cuckoo(&sender_inputs, DEFAULT_CUCKOO_FPBITS, &mut rng)
+            .unwrap();
+        let mut bytes = vec![];
+        set.write(&mut bytes).unwrap();
+        assert_eq!(
+            CuckooEncodedSet::read(&mut Cursor::new(&bytes)).unwrap(),
+            set
+        );
+        assert!(EncodedSet::read(&mut Cursor::new(&bytes)).is_err());
+
+        let queries = queries(&sender_inputs, &mut rng);
+        let nqueries = queries.len();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut psi = Sender::from_key(key);
+            psi.send_cuckoo_encoded(&set, &mut channel).unwrap();
+            for _ in 0..nqueries {
+                psi.send(&mut channel, &mut rng).unwrap();
+            }
+        });
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi = Receiver::init(&mut channel, &mut rng).unwrap();
+        let set = psi.receive_cuckoo_encoded(&mut channel).unwrap();
+        assert_eq!(set.len(), SET_SIZE);
+        for (i, query) in queries.iter().enumerate() {
+            let result = psi
+                .receive_cuckoo(&set, query, &mut channel, &mut rng)
+                .unwrap();
+            assert_eq!(result, sender_inputs[0..i].to_vec());
+        }
+        handle.join().unwrap();
+    }
+}
@@ -5,10 +5,38 @@
 // See LICENSE for licensing information.
 
 //! Implementations of AES-128 and AES-256, encryption only, using Intel's
-//! AES-NI instructions.
+//! AES-NI instructions, or a constant-time software implementation when the
+//! `portable` feature is enabled.
 //!
-//! Most of this implementation is borrowed and simplified from the `aesni`
-//! crate.
+//! Most of the AES-NI implementation is borrowed and simplified from the
+//! `aesni` crate.
 
+#[cfg(not(feature = "portable"))]
 pub mod aes128;
+#[cfg(not(feature = "portable"))]
 pub mod aes256;
+#[cfg(any(feature = "portable", test))]
+pub mod soft;
+
+#[cfg(not(feature = "portable"))]
+pub use self::{
+    aes128::{Aes128, FIXED_KEY_AES128},
+    aes256::Aes256,
+};
+#[cfg(feature = "portable")]
+pub use self::soft::{Aes128, Aes256, FIXED_KEY_AES128};
+
+// The round keys of `FIXED_KEY_AES128`.
+const FIXED_KEY_RKEYS: [u128; 11] = [
+    0x15B5_32C2_F193_1C94,
+    0xD754_876D_FE7E_6726,
+    0xA7EB_4F98_1986_CFCF,
+    0x80E6_BBED_F88D_E8C9,
+    0x1210_4B44_43D8_B35C,
+    0xF467_7B3C_8DCB_047B,
+    0x578C_DBAC_AED1_C9DC,
+    0x295D_2051_CF6F_5E25,
+    0x0CE1_FD36_50DE_FFAB,
+    0xDDFA_4FE9_E2CD_2D23,
+    0x96F6_769D_AF14_18D2,
+];
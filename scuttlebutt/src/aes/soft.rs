@@ -0,0 +1,290 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! A constant-time software implementation of AES-128 and AES-256, encryption
+//! only, used in place of AES-NI by the `portable` feature.
+//!
+//! The state is kept as a `u128` whose `i`th byte (in little-endian order) is
+//! the `i`th byte of the AES state, which is also how AES-NI lays out an
+//! `__m128i`. The S-box is computed, rather than looked up, as an inversion in
+//! `GF(2^8)` followed by the affine map, on eight bytes at a time packed into a
+//! `u64`. There are no secret-dependent memory accesses or branches.
+
+use crate::Block;
+
+// The low bit of every byte of a `u64`.
+const LO: u64 = 0x0101_0101_0101_0101;
+
+// The round constants of the key schedule.
+const RCON: [u32; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1B, 0x36];
+
+// Multiply each byte of `x` by `X` in `GF(2^8) = GF(2)[X] / (X^8 + X^4 + X^3 +
+// X + 1)`.
+#[inline(always)]
+fn xtime(x: u64) -> u64 {
+    ((x & 0x7F7F_7F7F_7F7F_7F7F) << 1) ^ (((x >> 7) & LO) * 0x1B)
+}
+
+// Multiply each byte of `x` by the corresponding byte of `y` in `GF(2^8)`.
+#[inline(always)]
+fn gf_mul(mut x: u64, y: u64) -> u64 {
+    let mut z = 0;
+    for i in 0..8 {
+        z ^= x & (((y >> i) & LO) * 0xFF);
+        x = xtime(x);
+    }
+    z
+}
+
+// Rotate each byte of `x` left by `n` bits.
+#[inline(always)]
+fn rotl_bytes(x: u64, n: u32) -> u64 {
+    ((x << n) & (((0xFF << n) & 0xFF) * LO)) | ((x >> (8 - n)) & ((0xFF >> (8 - n)) * LO))
+}
+
+// Apply the S-box to each byte of `x`.
+#[inline]
+fn sbox(x: u64) -> u64 {
+    // Invert by computing `x^254`, which maps zero to zero.
+    let x2 = gf_mul(x, x);
+    let x3 = gf_mul(x2, x);
+    let x6 = gf_mul(x3, x3);
+    let x12 = gf_mul(x6, x6);
+    let x15 = gf_mul(x12, x3);
+    let x30 = gf_mul(x15, x15);
+    let x60 = gf_mul(x30, x30);
+    let x120 = gf_mul(x60, x60);
+    let x240 = gf_mul(x120, x120);
+    let x252 = gf_mul(x240, x12);
+    let y = gf_mul(x252, x2);
+    y ^ rotl_bytes(y, 1) ^ rotl_bytes(y, 2) ^ rotl_bytes(y, 3) ^ rotl_bytes(y, 4) ^ (0x63 * LO)
+}
+
+#[inline]
+fn sub_bytes(s: u128) -> u128 {
+    u128::from(sbox(s as u64)) | (u128::from(sbox((s >> 64) as u64)) << 64)
+}
+
+#[inline]
+fn shift_rows(s: u128) -> u128 {
+    let b = s.to_le_bytes();
+    let mut out = [0u8; 16];
+    for c in 0..4 {
+        for r in 0..4 {
+            out[4 * c + r] = b[4 * ((c + r) % 4) + r];
+        }
+    }
+    u128::from_le_bytes(out)
+}
+
+// Rotate each of the two columns in `x` down by `n` bytes, so that row `r`
+// ends up holding row `r + n`.
+#[inline(always)]
+fn rotate_columns(x: u64, n: u32) -> u64 {
+    let mask = (0xFFFF_FFFF >> (8 * n)) * 0x1_0000_0001;
+    ((x >> (8 * n)) & mask) | ((x << (32 - 8 * n)) & !mask)
+}
+
+// Mix the two columns in `x`, using `2a₀ ⊕ 3a₁ ⊕ a₂ ⊕ a₃ = 2(a₀ ⊕ a₁) ⊕ a₁ ⊕
+// a₂ ⊕ a₃` for each row.
+#[inline]
+fn mix(x: u64) -> u64 {
+    let (x1, x2, x3) = (
+        rotate_columns(x, 1),
+        rotate_columns(x, 2),
+        rotate_columns(x, 3),
+    );
+    xtime(x ^ x1) ^ x1 ^ x2 ^ x3
+}
+
+#[inline]
+fn mix_columns(s: u128) -> u128 {
+    u128::from(mix(s as u64)) | (u128::from(mix((s >> 64) as u64)) << 64)
+}
+
+#[inline]
+fn sub_word(w: u32) -> u32 {
+    sbox(u64::from(w)) as u32
+}
+
+// Expand `key` into `rkeys.len()` round keys.
+#[inline]
+fn expand(key: &[u32], rkeys: &mut [u128]) {
+    let nk = key.len();
+    let mut w = [0u32; 60];
+    let w = &mut w[..4 * rkeys.len()];
+    w[..nk].copy_from_slice(key);
+    for i in nk..w.len() {
+        let mut t = w[i - 1];
+        if i % nk == 0 {
+            t = sub_word(t.rotate_right(8)) ^ RCON[i / nk - 1];
+        } else if nk > 6 && i % nk == 4 {
+            t = sub_word(t);
+        }
+        w[i] = w[i - nk] ^ t;
+    }
+    for (rkey, w) in rkeys.iter_mut().zip(w.chunks(4)) {
+        *rkey = w
+            .iter()
+            .rev()
+            .fold(0, |acc, w| (acc << 32) | u128::from(*w));
+    }
+}
+
+#[inline]
+fn encrypt(rkeys: &[u128], m: u128) -> u128 {
+    let (last, middle) = rkeys[1..].split_last().unwrap();
+    let mut c = m ^ rkeys[0];
+    for rkey in middle {
+        c = mix_columns(shift_rows(sub_bytes(c))) ^ rkey;
+    }
+    shift_rows(sub_bytes(c)) ^ last
+}
+
+/// AES-128, encryption only.
+#[derive(Clone)]
+pub struct Aes128 {
+    rkeys: [u128; 11],
+}
+
+impl Aes128 {
+    /// Create a new `Aes128` object, using `key` as the AES key.
+    #[inline]
+    pub fn new(key: Block) -> Self {
+        let key = u128::from(key);
+        let key = [
+            key as u32,
+            (key >> 32) as u32,
+            (key >> 64) as u32,
+            (key >> 96) as u32,
+        ];
+        let mut rkeys = [0; 11];
+        expand(&key, &mut rkeys);
+        Aes128 { rkeys }
+    }
+    /// Encrypt a block, outputting the ciphertext.
+    #[inline]
+    pub fn encrypt(&self, m: Block) -> Block {
+        Block::from(encrypt(&self.rkeys, u128::from(m)))
+    }
+    /// Encrypt four blocks at a time, outputting the ciphertexts.
+    #[inline]
+    pub fn encrypt4(&self, mut blocks: [Block; 4]) -> [Block; 4] {
+        for block in blocks.iter_mut() {
+            *block = self.encrypt(*block);
+        }
+        blocks
+    }
+    /// Encrypt eight blocks at a time, outputting the ciphertexts.
+    #[inline]
+    pub fn encrypt8(&self, mut blocks: [Block; 8]) -> [Block; 8] {
+        for block in blocks.iter_mut() {
+            *block = self.encrypt(*block);
+        }
+        blocks
+    }
+}
+
+/// Fixed-key AES-128.
+pub const FIXED_KEY_AES128: Aes128 = Aes128 {
+    rkeys: super::FIXED_KEY_RKEYS,
+};
+
+/// AES-256, encryption only.
+#[derive(Clone)]
+pub struct Aes256 {
+    rkeys: [u128; 15],
+}
+
+impl Aes256 {
+    /// Make a new `Aes256` object with key `key`.
+    #[inline]
+    pub fn new(key: &[u8; 32]) -> Self {
+        let mut key_ = [0u32; 8];
+        for (w, bytes) in key_.iter_mut().zip(key.chunks(4)) {
+            *w = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        let mut rkeys = [0; 15];
+        expand(&key_, &mut rkeys);
+        Aes256 { rkeys }
+    }
+    /// Encrypt block `m`.
+    #[inline]
+    pub fn encrypt(&self, m: Block) -> Block {
+        Block::from(encrypt(&self.rkeys, u128::from(m)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_hex(s: &str) -> Block {
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&hex::decode(s).unwrap());
+        Block::from(bytes)
+    }
+
+    #[test]
+    fn test_sbox() {
+        let xs = u64::from_le_bytes([0x00, 0x01, 0x53, 0x10, 0xC9, 0x7F, 0xFF, 0xAB]);
+        let ys = u64::from_le_bytes([0x63, 0x7C, 0xED, 0xCA, 0xDD, 0xD2, 0x16, 0x62]);
+        assert_eq!(sbox(xs), ys);
+    }
+
+    // The test vectors from Appendix C of FIPS 197.
+    #[test]
+    fn test_fips_197() {
+        let pt = from_hex("00112233445566778899aabbccddeeff");
+        let cipher = Aes128::new(from_hex("000102030405060708090a0b0c0d0e0f"));
+        assert_eq!(
+            cipher.encrypt(pt),
+            from_hex("69c4e0d86a7b0430d8cdb78070b4c55a")
+        );
+        let mut key = [0u8; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let cipher = Aes256::new(&key);
+        assert_eq!(
+            cipher.encrypt(pt),
+            from_hex("8ea2b7ca516745bfeafc49904b496089")
+        );
+    }
+
+    // Check against the AES-NI implementation.
+    #[cfg(not(feature = "portable"))]
+    #[test]
+    fn test_aesni() {
+        for _ in 0..64 {
+            let key = rand::random::<Block>();
+            let key256 = rand::random::<[u8; 32]>();
+            let m = rand::random::<Block>();
+            assert_eq!(
+                Aes128::new(key).encrypt(m),
+                crate::aes::aes128::Aes128::new(key).encrypt(m)
+            );
+            assert_eq!(
+                FIXED_KEY_AES128.encrypt(m),
+                crate::aes::aes128::FIXED_KEY_AES128.encrypt(m)
+            );
+            assert_eq!(
+                Aes256::new(&key256).encrypt(m),
+                crate::aes::aes256::Aes256::new(&key256).encrypt(m)
+            );
+        }
+        let key = rand::random::<Block>();
+        let (aes, aesni) = (Aes128::new(key), crate::aes::aes128::Aes128::new(key));
+        let ms = rand::random::<[u128; 8]>();
+        let mut blocks = [Block::default(); 8];
+        for (block, m) in blocks.iter_mut().zip(ms.iter()) {
+            *block = Block::from(*m);
+        }
+        assert_eq!(aes.encrypt8(blocks), aesni.encrypt8(blocks));
+        let blocks = [blocks[0], blocks[1], blocks[2], blocks[3]];
+        assert_eq!(aes.encrypt4(blocks), aesni.encrypt4(blocks));
+    }
+}
@@ -5,6 +5,9 @@
 // See LICENSE for licensing information.
 
 //! Defines a block as a 128-bit value, and implements block-related functions.
+//!
+//! By default a block wraps an SSE register. With the `portable` feature it
+//! wraps a `u128` instead, and uses scalar code in place of the intrinsics.
 
 #[cfg(feature = "curve25519-dalek")]
 use crate::Aes256;
@@ -12,27 +15,66 @@ use crate::Aes256;
 use curve25519_dalek::ristretto::RistrettoPoint;
 #[cfg(feature = "serde")]
 use std::convert::TryInto;
-use std::{
-    arch::x86_64::*,
-    hash::{Hash, Hasher},
-};
+#[cfg(not(feature = "portable"))]
+use std::arch::x86_64::*;
+use std::hash::{Hash, Hasher};
 
 /// A 128-bit chunk.
+#[cfg(not(feature = "portable"))]
 #[derive(Clone, Copy)]
 pub struct Block(pub __m128i);
 
+/// A 128-bit chunk.
+///
+/// The `u128` is stored in little-endian byte order, so that the bytes of a
+/// block in memory are the same as with the SSE backend on any platform.
+#[cfg(feature = "portable")]
+#[derive(Clone, Copy)]
+#[repr(C, align(16))]
+pub struct Block(pub u128);
+
+#[cfg(not(feature = "portable"))]
 union __U128 {
     vector: __m128i,
     bytes: u128,
 }
 
+#[cfg(not(feature = "portable"))]
 const ONE: __m128i = unsafe { (__U128 { bytes: 1 }).vector };
+#[cfg(not(feature = "portable"))]
 const ONES: __m128i = unsafe {
     (__U128 {
         bytes: 0xFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF,
     })
     .vector
 };
+#[cfg(feature = "portable")]
+const ONE: u128 = 1u128.to_le();
+#[cfg(feature = "portable")]
+const ONES: u128 = u128::MAX;
+
+// Carryless multiplication of two 64-bit values, in constant time.
+#[cfg(any(feature = "portable", test))]
+#[inline]
+fn clmul64(x: u64, y: u64) -> u128 {
+    let x = u128::from(x);
+    (0..64).fold(0, |acc, i| {
+        acc ^ ((x << i) & 0u128.wrapping_sub(u128::from((y >> i) & 1)))
+    })
+}
+
+// Carryless multiplication of two 128-bit values, returning the low and high
+// halves of the product, using Karatsuba to save a 64-bit multiplication.
+#[cfg(any(feature = "portable", test))]
+#[inline]
+fn clmul128(x: u128, y: u128) -> (u128, u128) {
+    let (x0, x1) = (x as u64, (x >> 64) as u64);
+    let (y0, y1) = (y as u64, (y >> 64) as u64);
+    let lo = clmul64(x0, y0);
+    let hi = clmul64(x1, y1);
+    let mid = clmul64(x0 ^ x1, y0 ^ y1) ^ lo ^ hi;
+    (lo ^ (mid << 64), hi ^ (mid >> 64))
+}
 
 impl Block {
     /// Convert into a pointer.
@@ -49,6 +91,7 @@ impl Block {
     /// Carryless multiplication.
     ///
     /// This code is adapted from the EMP toolkit's implementation.
+    #[cfg(not(feature = "portable"))]
     #[inline]
     pub fn clmul(self, rhs: Self) -> (Self, Self) {
         unsafe {
@@ -67,10 +110,22 @@ impl Block {
         }
     }
 
+    /// Carryless multiplication.
+    #[cfg(feature = "portable")]
+    #[inline]
+    pub fn clmul(self, rhs: Self) -> (Self, Self) {
+        let (x, y) = clmul128(u128::from(self), u128::from(rhs));
+        (Block::from(x), Block::from(y))
+    }
+
     /// Hash an elliptic curve point `pt` and tweak `tweak`.
     ///
     /// Computes the hash by computing `E_{pt}(tweak)`, where `E` is AES-256.
-    #[cfg(all(feature = "curve25519-dalek", feature = "nightly"))]
+    #[cfg(all(
+        feature = "curve25519-dalek",
+        feature = "nightly",
+        not(feature = "portable")
+    ))]
     #[inline]
     pub fn hash_pt(tweak: usize, pt: &RistrettoPoint) -> Self {
         let k = pt.compress();
@@ -82,7 +137,10 @@ impl Block {
     /// Hash an elliptic curve point `pt` and tweak `tweak`.
     ///
     /// Computes the hash by computing `E_{pt}(tweak)`, where `E` is AES-256.
-    #[cfg(all(feature = "curve25519-dalek", not(feature = "nightly")))]
+    #[cfg(all(
+        feature = "curve25519-dalek",
+        any(not(feature = "nightly"), feature = "portable")
+    ))]
     #[inline]
     pub fn hash_pt(tweak: usize, pt: &RistrettoPoint) -> Self {
         let k = pt.compress();
@@ -92,20 +150,41 @@ impl Block {
     }
 
     /// Return the least significant bit.
+    #[cfg(not(feature = "portable"))]
     #[inline]
     pub fn lsb(&self) -> bool {
         unsafe { _mm_extract_epi8(_mm_and_si128(self.0, ONE), 0) == 1 }
     }
+    /// Return the least significant bit.
+    #[cfg(feature = "portable")]
+    #[inline]
+    pub fn lsb(&self) -> bool {
+        self.0 & ONE != 0
+    }
     /// Set the least significant bit.
+    #[cfg(not(feature = "portable"))]
     #[inline]
     pub fn set_lsb(&self) -> Block {
         unsafe { Block(_mm_or_si128(self.0, ONE)) }
     }
+    /// Set the least significant bit.
+    #[cfg(feature = "portable")]
+    #[inline]
+    pub fn set_lsb(&self) -> Block {
+        Block(self.0 | ONE)
+    }
     /// Flip all bits.
+    #[cfg(not(feature = "portable"))]
     #[inline]
     pub fn flip(&self) -> Self {
         unsafe { Block(_mm_xor_si128(self.0, ONES)) }
     }
+    /// Flip all bits.
+    #[cfg(feature = "portable")]
+    #[inline]
+    pub fn flip(&self) -> Self {
+        Block(self.0 ^ ONES)
+    }
 
     /// Try to create a `Block` from a slice of bytes. The slice must have exactly 16 bytes.
     #[inline]
@@ -120,13 +199,20 @@ impl Block {
 }
 
 impl Default for Block {
+    #[cfg(not(feature = "portable"))]
     #[inline]
     fn default() -> Self {
         unsafe { Block(_mm_setzero_si128()) }
     }
+    #[cfg(feature = "portable")]
+    #[inline]
+    fn default() -> Self {
+        Block(0)
+    }
 }
 
 impl PartialEq for Block {
+    #[cfg(not(feature = "portable"))]
     #[inline]
     fn eq(&self, other: &Block) -> bool {
         unsafe {
@@ -134,6 +220,11 @@ impl PartialEq for Block {
             _mm_test_all_zeros(neq, neq) != 0
         }
     }
+    #[cfg(feature = "portable")]
+    #[inline]
+    fn eq(&self, other: &Block) -> bool {
+        self.0 == other.0
+    }
 }
 
 impl Eq for Block {}
@@ -166,47 +257,83 @@ impl AsMut<[u8]> for Block {
 
 impl std::ops::BitAnd for Block {
     type Output = Block;
+    #[cfg(not(feature = "portable"))]
     #[inline]
     fn bitand(self, rhs: Self) -> Self {
         unsafe { Block(_mm_and_si128(self.0, rhs.0)) }
     }
+    #[cfg(feature = "portable")]
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self {
+        Block(self.0 & rhs.0)
+    }
 }
 
 impl std::ops::BitAndAssign for Block {
+    #[cfg(not(feature = "portable"))]
     #[inline]
     fn bitand_assign(&mut self, rhs: Self) {
         unsafe { self.0 = _mm_and_si128(self.0, rhs.0) }
     }
+    #[cfg(feature = "portable")]
+    #[inline]
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0
+    }
 }
 
 impl std::ops::BitOr for Block {
     type Output = Block;
+    #[cfg(not(feature = "portable"))]
     #[inline]
     fn bitor(self, rhs: Self) -> Self {
         unsafe { Block(_mm_or_si128(self.0, rhs.0)) }
     }
+    #[cfg(feature = "portable")]
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Block(self.0 | rhs.0)
+    }
 }
 
 impl std::ops::BitOrAssign for Block {
+    #[cfg(not(feature = "portable"))]
     #[inline]
     fn bitor_assign(&mut self, rhs: Self) {
         unsafe { self.0 = _mm_or_si128(self.0, rhs.0) }
     }
+    #[cfg(feature = "portable")]
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0
+    }
 }
 
 impl std::ops::BitXor for Block {
     type Output = Block;
+    #[cfg(not(feature = "portable"))]
     #[inline]
     fn bitxor(self, rhs: Self) -> Self {
         unsafe { Block(_mm_xor_si128(self.0, rhs.0)) }
     }
+    #[cfg(feature = "portable")]
+    #[inline]
+    fn bitxor(self, rhs: Self) -> Self {
+        Block(self.0 ^ rhs.0)
+    }
 }
 
 impl std::ops::BitXorAssign for Block {
+    #[cfg(not(feature = "portable"))]
     #[inline]
     fn bitxor_assign(&mut self, rhs: Self) {
         unsafe { self.0 = _mm_xor_si128(self.0, rhs.0) }
     }
+    #[cfg(feature = "portable")]
+    #[inline]
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.0 ^= rhs.0
+    }
 }
 
 impl std::fmt::Debug for Block {
@@ -237,21 +364,34 @@ impl rand::distributions::Distribution<Block> for rand::distributions::Standard
 }
 
 impl From<Block> for u128 {
+    #[cfg(not(feature = "portable"))]
     #[inline]
     fn from(m: Block) -> u128 {
         unsafe { *(&m as *const _ as *const u128) }
     }
+    #[cfg(feature = "portable")]
+    #[inline]
+    fn from(m: Block) -> u128 {
+        u128::from_le(m.0)
+    }
 }
 
 impl From<u128> for Block {
+    #[cfg(not(feature = "portable"))]
     #[inline]
     fn from(m: u128) -> Self {
         unsafe { std::mem::transmute(m) }
         // XXX: the below doesn't work due to pointer-alignment issues.
         // unsafe { *(&m as *const _ as *const Block) }
     }
+    #[cfg(feature = "portable")]
+    #[inline]
+    fn from(m: u128) -> Self {
+        Block(m.to_le())
+    }
 }
 
+#[cfg(not(feature = "portable"))]
 impl From<Block> for __m128i {
     #[inline]
     fn from(m: Block) -> __m128i {
@@ -259,6 +399,7 @@ impl From<Block> for __m128i {
     }
 }
 
+#[cfg(not(feature = "portable"))]
 impl From<__m128i> for Block {
     #[inline]
     fn from(m: __m128i) -> Self {
@@ -311,7 +452,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 #[cfg(feature = "serde")]
 impl Serialize for Block {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        serializer.serialize_bytes(&unsafe { std::mem::transmute::<__m128i, [u8; 16]>(self.0) })
+        serializer.serialize_bytes(&<[u8; 16]>::from(*self))
     }
 }
 
@@ -388,6 +529,18 @@ mod tests {
         assert_eq!(x, y);
     }
 
+    #[test]
+    fn test_clmul() {
+        let x = rand::random::<Block>();
+        let y = rand::random::<Block>();
+        let (lo, hi) = x.clmul(y);
+        let (lo_, hi_) = clmul128(u128::from(x), u128::from(y));
+        assert_eq!((u128::from(lo), u128::from(hi)), (lo_, hi_));
+        // `(X^64 + 1)^2 = X^128 + 1`.
+        let x = Block::from((1u128 << 64) | 1);
+        assert_eq!(x.clmul(x), (Block::from(1u128), Block::from(1u128)));
+    }
+
     #[test]
     fn test_conversion() {
         let x = rand::random::<u128>();
@@ -7,8 +7,9 @@
 //! Defines a 512-bit value.
 
 use crate::Block;
+#[cfg(not(feature = "portable"))]
+use std::arch::x86_64::*;
 use std::{
-    arch::x86_64::*,
     convert::TryFrom,
     hash::{Hash, Hasher},
 };
@@ -109,6 +110,7 @@ impl From<Block512> for [u32; 16] {
     }
 }
 
+#[cfg(not(feature = "portable"))]
 impl From<Block512> for [__m128i; 4] {
     #[inline]
     fn from(m: Block512) -> [__m128i; 4] {
@@ -144,6 +146,7 @@ impl<'a> From<&'a mut Block512> for &'a mut [u8; 64] {
     }
 }
 
+#[cfg(not(feature = "portable"))]
 impl From<[__m128i; 4]> for Block512 {
     #[inline]
     fn from(m: [__m128i; 4]) -> Block512 {
@@ -5,7 +5,7 @@ use sha2::{Digest, Sha256};
 /// Simple implementation of a Bloom Filter. Which is guaranteed to return 1 if an element
 /// is in the set, but returns 1 with probability p (settable) if an item is not in the
 /// set. Does not reveal what is in the set.
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd)]
 pub struct BloomFilter {
     bits: Vec<bool>,
     nhashes: usize,
@@ -93,7 +93,7 @@ impl BloomFilter {
     }
 
     /// Check whether an item exists in the BloomFilter.
-    pub fn contains<V: AsRef<[u8]>>(&mut self, value: &V) -> bool {
+    pub fn contains<V: AsRef<[u8]>>(&self, value: &V) -> bool {
         (0..self.nhashes).all(|hash_index| {
             let i = Self::bin(value, hash_index) % self.len();
             self.bits[i]
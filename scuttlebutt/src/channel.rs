@@ -5,12 +5,24 @@
 // See LICENSE for licensing information.
 
 mod hash_channel;
+mod mux_channel;
+mod network;
+mod recording_channel;
+mod replay_channel;
+mod simulated_channel;
 mod track_channel;
+mod transcript_channel;
 #[cfg(unix)]
 mod unix_channel;
 
 pub use hash_channel::HashChannel;
+pub use mux_channel::{multiplex, MuxChannel};
+pub use network::{Network, PartyId, TcpChannel, MAX_MESSAGE_LEN};
+pub use recording_channel::{Direction, Event, Recording, RecordingChannel};
+pub use replay_channel::{Divergence, ReplayChannel};
+pub use simulated_channel::{simulated_channel_pair, SimulatedChannel};
 pub use track_channel::TrackChannel;
+pub use transcript_channel::TranscriptChannel;
 
 #[cfg(unix)]
 pub use unix_channel::{track_unix_channel_pair, unix_channel_pair, TrackUnixChannel, UnixChannel};
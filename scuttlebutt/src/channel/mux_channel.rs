@@ -0,0 +1,343 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+use std::{
+    collections::VecDeque,
+    io::{Error, ErrorKind, Read, Result, Write},
+    sync::{Arc, Condvar, Mutex, MutexGuard, TryLockError},
+};
+
+// The number of bytes a logical channel may send before the other end has
+// consumed them.
+const WINDOW_SIZE: usize = 1 << 20;
+// The maximum number of bytes of data in a single frame.
+const MAX_FRAME_SIZE: usize = 1 << 16;
+
+const FRAME_DATA: u8 = 0;
+const FRAME_CREDIT: u8 = 1;
+
+// The state shared by the logical channels.
+struct State {
+    // Data received for each logical channel, not yet read.
+    queues: Vec<VecDeque<u8>>,
+    // The number of bytes each logical channel may still send.
+    credits: Vec<usize>,
+    // The number of bytes read from each logical channel since the other end
+    // was last granted credit.
+    consumed: Vec<usize>,
+    // Incremented whenever a frame has been received.
+    generation: u64,
+    // Set if reading from the underlying channel failed.
+    error: Option<ErrorKind>,
+}
+
+struct Shared<R, W> {
+    reader: Mutex<R>,
+    writer: Mutex<W>,
+    state: Mutex<State>,
+    cond: Condvar,
+}
+
+/// A logical channel multiplexed over a single connection, as created by
+/// `multiplex`.
+pub struct MuxChannel<R, W> {
+    id: usize,
+    shared: Arc<Shared<R, W>>,
+}
+
+/// Split a connection, given by its reading half `reader` and its writing half
+/// `writer`, into `nchannels` logical channels. The other end of the
+/// connection must call `multiplex` with the same number of channels, and the
+/// logical channels with the same id are connected to each other.
+///
+/// Data is sent in frames tagged with the logical channel's id. Each logical
+/// channel is flow-controlled separately, so that a logical channel whose data
+/// is not being read only blocks writers on that logical channel. The logical
+/// channels can be moved to different threads, which lets independent
+/// sub-protocols run concurrently over the same connection.
+pub fn multiplex<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    nchannels: usize,
+) -> Vec<MuxChannel<R, W>> {
+    let shared = Arc::new(Shared {
+        reader: Mutex::new(reader),
+        writer: Mutex::new(writer),
+        state: Mutex::new(State {
+            queues: vec![VecDeque::new(); nchannels],
+            credits: vec![WINDOW_SIZE; nchannels],
+            consumed: vec![0; nchannels],
+            generation: 0,
+            error: None,
+        }),
+        cond: Condvar::new(),
+    });
+    (0..nchannels)
+        .map(|id| MuxChannel {
+            id,
+            shared: shared.clone(),
+        })
+        .collect()
+}
+
+#[inline]
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl<R: Read, W: Write> MuxChannel<R, W> {
+    /// Return the id of the logical channel.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    fn write_frame(&self, kind: u8, data: &[u8], len: usize) -> Result<()> {
+        let mut writer = lock(&self.shared.writer);
+        writer.write_all(&(self.id as u32).to_le_bytes())?;
+        writer.write_all(&[kind])?;
+        writer.write_all(&(len as u32).to_le_bytes())?;
+        writer.write_all(data)
+    }
+
+    fn read_frame(&self, reader: &mut R) -> Result<(usize, u8, Vec<u8>, usize)> {
+        let mut header = [0u8; 9];
+        reader.read_exact(&mut header)?;
+        let mut id = [0u8; 4];
+        let mut len = [0u8; 4];
+        id.copy_from_slice(&header[0..4]);
+        len.copy_from_slice(&header[5..9]);
+        let id = u32::from_le_bytes(id) as usize;
+        let kind = header[4];
+        let len = u32::from_le_bytes(len) as usize;
+        if id >= lock(&self.shared.state).queues.len() {
+            return Err(Error::new(ErrorKind::InvalidData, "invalid channel id"));
+        }
+        match kind {
+            FRAME_DATA if len <= MAX_FRAME_SIZE => {
+                let mut data = vec![0u8; len];
+                reader.read_exact(&mut data)?;
+                Ok((id, kind, data, len))
+            }
+            FRAME_CREDIT => Ok((id, kind, vec![], len)),
+            _ => Err(Error::new(ErrorKind::InvalidData, "invalid frame")),
+        }
+    }
+
+    // Make progress on receiving frames: either receive a frame ourselves, or
+    // wait for whoever is currently receiving to receive one, where
+    // `generation` is the generation we last saw.
+    fn pump(&self, generation: u64) -> Result<()> {
+        let shared = &self.shared;
+        match shared.reader.try_lock() {
+            Ok(mut reader) => {
+                let frame = self.read_frame(&mut reader);
+                // Release the reader before waking up the waiters, so that
+                // they can pick it up.
+                drop(reader);
+                let mut state = lock(&shared.state);
+                let result = match frame {
+                    Ok((id, FRAME_DATA, data, _)) => {
+                        state.queues[id].extend(data);
+                        Ok(())
+                    }
+                    Ok((id, _, _, credit)) => match state.credits[id].checked_add(credit) {
+                        Some(credits) => {
+                            state.credits[id] = credits;
+                            Ok(())
+                        }
+                        None => {
+                            state.error = Some(ErrorKind::InvalidData);
+                            Err(Error::new(ErrorKind::InvalidData, "credit overflow"))
+                        }
+                    },
+                    Err(e) => {
+                        state.error = Some(e.kind());
+                        Err(e)
+                    }
+                };
+                state.generation += 1;
+                shared.cond.notify_all();
+                result
+            }
+            Err(TryLockError::WouldBlock) => {
+                let mut state = lock(&shared.state);
+                while state.generation == generation && state.error.is_none() {
+                    state = shared.cond.wait(state).unwrap_or_else(|e| e.into_inner());
+                }
+                Ok(())
+            }
+            Err(TryLockError::Poisoned(_)) => Err(Error::other("poisoned lock")),
+        }
+    }
+}
+
+impl<R: Read, W: Write> Read for MuxChannel<R, W> {
+    fn read(&mut self, bytes: &mut [u8]) -> Result<usize> {
+        if bytes.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let generation = {
+                let mut state = lock(&self.shared.state);
+                let queue = &mut state.queues[self.id];
+                if !queue.is_empty() {
+                    let n = bytes.len().min(queue.len());
+                    for (b, x) in bytes.iter_mut().zip(queue.drain(..n)) {
+                        *b = x;
+                    }
+                    state.consumed[self.id] += n;
+                    // Grant more credit once half the window has been read.
+                    let credit = if state.consumed[self.id] >= WINDOW_SIZE / 2 {
+                        std::mem::replace(&mut state.consumed[self.id], 0)
+                    } else {
+                        0
+                    };
+                    drop(state);
+                    if credit > 0 {
+                        self.write_frame(FRAME_CREDIT, &[], credit)?;
+                        lock(&self.shared.writer).flush()?;
+                    }
+                    return Ok(n);
+                }
+                if let Some(kind) = state.error {
+                    return Err(Error::from(kind));
+                }
+                state.generation
+            };
+            self.pump(generation)?;
+        }
+    }
+}
+
+impl<R: Read, W: Write> Write for MuxChannel<R, W> {
+    fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+        if bytes.is_empty() {
+            return Ok(0);
+        }
+        let n = loop {
+            let generation = {
+                let mut state = lock(&self.shared.state);
+                let credit = state.credits[self.id];
+                if credit > 0 {
+                    let n = bytes.len().min(credit).min(MAX_FRAME_SIZE);
+                    state.credits[self.id] -= n;
+                    break n;
+                }
+                if let Some(kind) = state.error {
+                    return Err(Error::from(kind));
+                }
+                state.generation
+            };
+            // Make sure the other end has everything we sent, so that it can
+            // grant us more credit.
+            self.flush()?;
+            self.pump(generation)?;
+        };
+        self.write_frame(FRAME_DATA, &bytes[0..n], n)?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        lock(&self.shared.writer).flush()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::AbstractChannel;
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    type TestChannel = MuxChannel<BufReader<UnixStream>, BufWriter<UnixStream>>;
+
+    fn mux_pair(nchannels: usize) -> (Vec<TestChannel>, Vec<TestChannel>) {
+        let (tx, rx) = UnixStream::pair().unwrap();
+        let left = multiplex(
+            BufReader::new(tx.try_clone().unwrap()),
+            BufWriter::new(tx),
+            nchannels,
+        );
+        let right = multiplex(
+            BufReader::new(rx.try_clone().unwrap()),
+            BufWriter::new(rx),
+            nchannels,
+        );
+        (left, right)
+    }
+
+    #[test]
+    fn test_mux_channels() {
+        let nchannels = 4;
+        let n = 1 << 12;
+        let (left, right) = mux_pair(nchannels);
+        let handles = left
+            .into_iter()
+            .map(|mut channel| {
+                std::thread::spawn(move || {
+                    let id = channel.id() as u64;
+                    for i in 0..n {
+                        channel.write_u64(id * n + i).unwrap();
+                        channel.flush().unwrap();
+                        assert_eq!(channel.read_u64().unwrap(), id * n + i + 1);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let handles_ = right
+            .into_iter()
+            .map(|mut channel| {
+                std::thread::spawn(move || {
+                    for _ in 0..n {
+                        let x = channel.read_u64().unwrap();
+                        channel.write_u64(x + 1).unwrap();
+                        channel.flush().unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles.into_iter().chain(handles_) {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_mux_flow_control() {
+        // Channel 0 sends more than the window while the other end is busy
+        // with channel 1, which must not be blocked by this.
+        let data = (0..4 * WINDOW_SIZE).map(|i| i as u8).collect::<Vec<u8>>();
+        let data_ = data.clone();
+        let (mut left, mut right) = mux_pair(2);
+        let mut left1 = left.pop().unwrap();
+        let mut left0 = left.pop().unwrap();
+        let handle = std::thread::spawn(move || {
+            left0.write_all(&data_).unwrap();
+            left0.flush().unwrap();
+        });
+        for i in 0..16 {
+            left1.write_u64(i).unwrap();
+            left1.flush().unwrap();
+            assert_eq!(right[1].read_u64().unwrap(), i);
+            right[1].write_u64(i).unwrap();
+            right[1].flush().unwrap();
+            assert_eq!(left1.read_u64().unwrap(), i);
+        }
+        assert_eq!(right[0].read_vec(data.len()).unwrap(), data);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_mux_credit_overflow() {
+        // A credit frame for channel 0 granting two bytes.
+        let frame = [0, 0, 0, 0, FRAME_CREDIT, 2, 0, 0, 0];
+        let channels = multiplex(std::io::Cursor::new(frame), Vec::new(), 1);
+        lock(&channels[0].shared.state).credits[0] = usize::MAX - 1;
+        let err = channels[0].pump(0).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
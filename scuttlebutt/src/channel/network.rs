@@ -0,0 +1,402 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+use crate::channel::{simulated_channel_pair, AbstractChannel, Channel, SimulatedChannel};
+use sha2::{Digest, Sha256};
+use std::{
+    io::{BufReader, BufWriter, Error, ErrorKind, Result},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+/// The identifier of a party, ranging from `0` to `nparties - 1`.
+pub type PartyId = usize;
+
+/// The channel type used between parties of a `Network` built over TCP.
+pub type TcpChannel = Channel<BufReader<TcpStream>, BufWriter<TcpStream>>;
+
+// How long to keep retrying to connect to a party which is not yet listening.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+// How long to wait between attempts to connect.
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The maximum length in bytes of a message sent with `send_to` or
+/// `broadcast`. Longer messages are rejected by both the sender and the
+/// receiver, so that a peer cannot make us allocate arbitrary amounts of
+/// memory.
+pub const MAX_MESSAGE_LEN: usize = 1 << 30;
+
+/// A full mesh of channels between `nparties` parties, as seen by one of them,
+/// with the other parties addressed by their `PartyId`.
+///
+/// Messages sent with `send_to` and `broadcast` are length-prefixed, so that
+/// the receiving party does not need to know their lengths in advance. The
+/// underlying channels remain available through `channel` and `channels_mut`,
+/// e.g., for protocols which take a slice of `(PartyId, C)` pairs.
+pub struct Network<C> {
+    me: PartyId,
+    // The channels to the other parties, sorted by id.
+    channels: Vec<(PartyId, C)>,
+}
+
+impl<C: AbstractChannel> Network<C> {
+    /// Make a new `Network` for party `me` from `channels`, which must contain
+    /// exactly one channel to every other party `0..nparties`. Otherwise, an
+    /// error of kind `InvalidInput` is returned.
+    pub fn new(me: PartyId, mut channels: Vec<(PartyId, C)>) -> Result<Self> {
+        channels.sort_by_key(|(id, _)| *id);
+        for (i, (id, _)) in channels.iter().enumerate() {
+            let expected = if i < me { i } else { i + 1 };
+            if *id != expected {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "missing or duplicate channel to a party",
+                ));
+            }
+        }
+        Ok(Network { me, channels })
+    }
+
+    /// Return our own id.
+    pub fn id(&self) -> PartyId {
+        self.me
+    }
+
+    /// Return the number of parties, including ourselves.
+    pub fn nparties(&self) -> usize {
+        self.channels.len() + 1
+    }
+
+    /// Return the ids of the other parties.
+    pub fn peers(&self) -> impl Iterator<Item = PartyId> + '_ {
+        self.channels.iter().map(|(id, _)| *id)
+    }
+
+    fn index(&self, id: PartyId) -> Result<usize> {
+        self.channels
+            .binary_search_by_key(&id, |(id, _)| *id)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid party id"))
+    }
+
+    /// Return the channel to party `id`.
+    ///
+    /// # Panics
+    /// If `id` is our own id or not less than the number of parties.
+    pub fn channel(&mut self, id: PartyId) -> &mut C {
+        let index = self.index(id).unwrap();
+        &mut self.channels[index].1
+    }
+
+    /// Return the channels to all other parties, sorted by id.
+    pub fn channels_mut(&mut self) -> &mut [(PartyId, C)] {
+        &mut self.channels
+    }
+
+    /// Return the channels to all other parties, sorted by id.
+    pub fn into_channels(self) -> Vec<(PartyId, C)> {
+        self.channels
+    }
+
+    /// Apply `f` to all channels, e.g., to wrap them in a `TrackChannel`.
+    pub fn map<D: AbstractChannel, F: FnMut(C) -> D>(self, mut f: F) -> Network<D> {
+        Network {
+            me: self.me,
+            channels: self
+                .channels
+                .into_iter()
+                .map(|(id, channel)| (id, f(channel)))
+                .collect(),
+        }
+    }
+
+    /// Send message `msg` to party `id`, which must be at most
+    /// `MAX_MESSAGE_LEN` bytes long.
+    pub fn send_to(&mut self, id: PartyId, msg: &[u8]) -> Result<()> {
+        if msg.len() > MAX_MESSAGE_LEN {
+            return Err(Error::new(ErrorKind::InvalidInput, "message too long"));
+        }
+        let index = self.index(id)?;
+        let channel = &mut self.channels[index].1;
+        channel.write_u64(msg.len() as u64)?;
+        channel.write_all(msg)?;
+        channel.flush()
+    }
+
+    /// Receive a message sent by party `id` with `send_to`, returning an error
+    /// of kind `InvalidData` if its length exceeds `MAX_MESSAGE_LEN`.
+    pub fn receive_from(&mut self, id: PartyId) -> Result<Vec<u8>> {
+        let index = self.index(id)?;
+        let channel = &mut self.channels[index].1;
+        let len = channel.read_u64()?;
+        if len > MAX_MESSAGE_LEN as u64 {
+            return Err(Error::new(ErrorKind::InvalidData, "message too long"));
+        }
+        channel.read_vec(len as usize)
+    }
+
+    /// Send message `msg` to all other parties, who must receive it with
+    /// `receive_broadcast`.
+    pub fn broadcast(&mut self, msg: &[u8]) -> Result<()> {
+        let ids = self.peers().collect::<Vec<_>>();
+        for id in ids {
+            self.send_to(id, msg)?;
+        }
+        Ok(())
+    }
+
+    /// Receive a message broadcast by party `sender`, checking that every
+    /// other receiver got the same message.
+    ///
+    /// This is echo broadcast: each receiver sends a hash of the message it
+    /// received to all other receivers, and returns an error of kind
+    /// `InvalidData` if any of the hashes it gets back differ from its own. A
+    /// malicious sender thus cannot make honest receivers output different
+    /// messages, although it may make some of them abort.
+    pub fn receive_broadcast(&mut self, sender: PartyId) -> Result<Vec<u8>> {
+        let msg = self.receive_from(sender)?;
+        let digest = Sha256::digest(&msg);
+        let receivers = self
+            .peers()
+            .filter(|id| *id != sender)
+            .collect::<Vec<_>>();
+        // Send our echoes before reading any, so that the receivers do not
+        // wait on each other.
+        for &id in receivers.iter() {
+            self.send_to(id, &digest)?;
+        }
+        let mut consistent = true;
+        for &id in receivers.iter() {
+            // Keep reading after a mismatch, so the other receivers' echoes
+            // are not left in the channels.
+            consistent &= self.receive_from(id)? == digest.as_slice();
+        }
+        if consistent {
+            Ok(msg)
+        } else {
+            Err(Error::new(ErrorKind::InvalidData, "inconsistent broadcast"))
+        }
+    }
+}
+
+impl Network<TcpChannel> {
+    /// Connect party `me` to all other parties over TCP, where `addresses`
+    /// contains the address of each party, indexed by `PartyId`. We listen on
+    /// our own address for connections from the parties with smaller ids, and
+    /// connect to the parties with larger ids, retrying until they are
+    /// listening.
+    pub fn connect<A: ToSocketAddrs>(me: PartyId, addresses: &[A]) -> Result<Self> {
+        if me >= addresses.len() {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid party id"));
+        }
+        let listener = TcpListener::bind(&addresses[me])?;
+        Self::connect_with_listener(me, listener, addresses)
+    }
+
+    /// Connect party `me` to all other parties over TCP as in `connect`, but
+    /// accept connections on `listener`, which is already bound to our own
+    /// address, e.g., to an ephemeral port. Our entry in `addresses` is
+    /// ignored.
+    pub fn connect_with_listener<A: ToSocketAddrs>(
+        me: PartyId,
+        listener: TcpListener,
+        addresses: &[A],
+    ) -> Result<Self> {
+        if me >= addresses.len() {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid party id"));
+        }
+        let listener_thread = std::thread::spawn(move || -> Result<Vec<(PartyId, TcpStream)>> {
+            let mut streams = Vec::with_capacity(me);
+            for stream in listener.incoming().take(me) {
+                let mut stream = stream?;
+                let id = stream.read_u64()? as usize;
+                if id >= me || streams.iter().any(|(id_, _)| *id_ == id) {
+                    return Err(Error::new(ErrorKind::InvalidData, "invalid party id"));
+                }
+                streams.push((id, stream));
+            }
+            Ok(streams)
+        });
+
+        let mut streams = Vec::with_capacity(addresses.len() - 1);
+        for (id, address) in addresses.iter().enumerate().skip(me + 1) {
+            let mut stream = connect(address)?;
+            stream.write_u64(me as u64)?;
+            streams.push((id, stream));
+        }
+        let streams_ = listener_thread
+            .join()
+            .map_err(|_| Error::other("listener thread panicked"))??;
+        streams.extend(streams_);
+
+        let channels = streams
+            .into_iter()
+            .map(|(id, stream)| {
+                stream.set_nodelay(true)?;
+                let reader = BufReader::new(stream.try_clone()?);
+                let writer = BufWriter::new(stream);
+                Ok((id, Channel::new(reader, writer)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(me, channels)
+    }
+}
+
+fn connect<A: ToSocketAddrs>(address: &A) -> Result<TcpStream> {
+    let start = Instant::now();
+    loop {
+        match TcpStream::connect(address) {
+            Ok(stream) => return Ok(stream),
+            Err(e) if start.elapsed() >= CONNECT_TIMEOUT => return Err(e),
+            Err(_) => std::thread::sleep(CONNECT_RETRY_INTERVAL),
+        }
+    }
+}
+
+impl Network<SimulatedChannel> {
+    /// Create the `Network`s of `nparties` parties running in the same
+    /// process, indexed by `PartyId`, connected by `SimulatedChannel`s without
+    /// latency or bandwidth limits.
+    pub fn in_process(nparties: usize) -> Vec<Self> {
+        Self::simulated(nparties, Duration::default(), None)
+    }
+
+    /// Create the `Network`s of `nparties` parties running in the same
+    /// process, indexed by `PartyId`, connected by `SimulatedChannel`s with
+    /// the given `latency` and `bandwidth` (see `simulated_channel_pair`).
+    pub fn simulated(nparties: usize, latency: Duration, bandwidth: Option<u64>) -> Vec<Self> {
+        let mut channels = (0..nparties).map(|_| Vec::new()).collect::<Vec<_>>();
+        for i in 0..nparties {
+            for j in i + 1..nparties {
+                let (left, right) = simulated_channel_pair(latency, bandwidth);
+                channels[i].push((j, left));
+                channels[j].push((i, right));
+            }
+        }
+        // Each party's channels are already sorted by id.
+        channels
+            .into_iter()
+            .enumerate()
+            .map(|(me, channels)| Network { me, channels })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn run<C, F, T>(networks: Vec<Network<C>>, f: F) -> Vec<T>
+    where
+        C: AbstractChannel + Send + 'static,
+        F: Fn(Network<C>) -> T + Send + Sync + 'static,
+        T: Send + 'static,
+    {
+        let f = std::sync::Arc::new(f);
+        let handles = networks
+            .into_iter()
+            .map(|network| {
+                let f = f.clone();
+                std::thread::spawn(move || f(network))
+            })
+            .collect::<Vec<_>>();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    }
+
+    fn exchange<C: AbstractChannel>(mut network: Network<C>) -> Vec<u8> {
+        let me = network.id();
+        let n = network.nparties();
+        // Everyone sends to everyone, then receives from everyone.
+        for id in network.peers().collect::<Vec<_>>() {
+            network.send_to(id, &vec![me as u8; me + id]).unwrap();
+        }
+        for id in network.peers().collect::<Vec<_>>() {
+            assert_eq!(network.receive_from(id).unwrap(), vec![id as u8; me + id]);
+        }
+        // Everyone broadcasts in turn.
+        let mut msgs = Vec::new();
+        for sender in 0..n {
+            if sender == me {
+                network.broadcast(b"hello").unwrap();
+            } else {
+                msgs.extend(network.receive_broadcast(sender).unwrap());
+            }
+        }
+        msgs
+    }
+
+    #[test]
+    fn test_in_process() {
+        let nparties = 4;
+        let results = run(Network::in_process(nparties), exchange);
+        for msgs in results {
+            assert_eq!(msgs, b"hello".repeat(nparties - 1));
+        }
+    }
+
+    #[test]
+    fn test_tcp() {
+        let listeners = (0..3)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect::<Vec<_>>();
+        let addresses = listeners
+            .iter()
+            .map(|listener| listener.local_addr().unwrap())
+            .collect::<Vec<_>>();
+        let handles = listeners
+            .into_iter()
+            .enumerate()
+            .map(|(me, listener)| {
+                let addresses = addresses.clone();
+                std::thread::spawn(move || {
+                    let network = Network::connect_with_listener(me, listener, &addresses).unwrap();
+                    assert_eq!(network.id(), me);
+                    assert_eq!(network.nparties(), 3);
+                    exchange(network)
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), b"hello".repeat(2));
+        }
+    }
+
+    #[test]
+    fn test_inconsistent_broadcast() {
+        let results = run(Network::in_process(4), |mut network| {
+            if network.id() == 0 {
+                // Send party 3 a different message than the others.
+                network.send_to(1, b"hello").unwrap();
+                network.send_to(2, b"hello").unwrap();
+                network.send_to(3, b"world").unwrap();
+                None
+            } else {
+                Some(network.receive_broadcast(0).map_err(|e| e.kind()))
+            }
+        });
+        for result in results.into_iter().flatten() {
+            assert_eq!(result, Err(ErrorKind::InvalidData));
+        }
+    }
+
+    #[test]
+    fn test_invalid_channels() {
+        let (left, right) = simulated_channel_pair(Duration::default(), None);
+        let result = Network::new(0, vec![(1, left), (1, right)]);
+        assert_eq!(result.err().map(|e| e.kind()), Some(ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn test_message_too_long() {
+        let mut networks = Network::in_process(2);
+        let mut network1 = networks.pop().unwrap();
+        let mut network0 = networks.pop().unwrap();
+        network0.channel(1).write_u64(MAX_MESSAGE_LEN as u64 + 1).unwrap();
+        network0.channel(1).flush().unwrap();
+        let err = network1.receive_from(0).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
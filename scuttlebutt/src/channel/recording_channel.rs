@@ -0,0 +1,211 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+use crate::AbstractChannel;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write},
+    path::Path,
+};
+
+const TAG_WRITE: u8 = 0;
+const TAG_READ: u8 = 1;
+const TAG_LABEL: u8 = 2;
+
+/// The direction of traffic on a channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Data read from the channel.
+    Read,
+    /// Data written to the channel.
+    Write,
+}
+
+/// An event recorded by a `RecordingChannel`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The data transferred by a single call to `read` or `write`, starting at
+    /// byte `offset` of the traffic in that direction.
+    Data {
+        /// The direction of the traffic.
+        direction: Direction,
+        /// The offset of the data in the traffic in that direction.
+        offset: u64,
+        /// The data.
+        data: Vec<u8>,
+    },
+    /// A label supplied with `RecordingChannel::label`.
+    Label(String),
+}
+
+/// An instantiation of the `AbstractChannel` trait which records all bytes
+/// read from and written to the channel, along with their direction and
+/// offset, to a log.
+///
+/// Callers can add labels to the log with `label`, e.g., to mark the steps of
+/// a protocol. The log can be loaded as a `Recording`, and replayed with a
+/// `ReplayChannel`.
+pub struct RecordingChannel<C, W = BufWriter<File>> {
+    channel: C,
+    log: W,
+    nbytes_read: u64,
+    nbytes_written: u64,
+}
+
+impl<C: AbstractChannel> RecordingChannel<C> {
+    /// Make a new `RecordingChannel` from `channel`, recording to the file at
+    /// `path`.
+    pub fn create<P: AsRef<Path>>(channel: C, path: P) -> Result<Self> {
+        let log = BufWriter::new(File::create(path)?);
+        Ok(Self::new(channel, log))
+    }
+}
+
+impl<C: AbstractChannel, W: Write> RecordingChannel<C, W> {
+    /// Make a new `RecordingChannel` from `channel`, recording to `log`.
+    pub fn new(channel: C, log: W) -> Self {
+        Self {
+            channel,
+            log,
+            nbytes_read: 0,
+            nbytes_written: 0,
+        }
+    }
+
+    /// Add `label` to the log.
+    pub fn label(&mut self, label: &str) -> Result<()> {
+        self.log.write_all(&[TAG_LABEL])?;
+        self.log.write_all(&(label.len() as u64).to_le_bytes())?;
+        self.log.write_all(label.as_bytes())
+    }
+
+    /// Consume the channel, flush the log, and return the underlying channel
+    /// and log.
+    pub fn into_inner(mut self) -> Result<(C, W)> {
+        self.log.flush()?;
+        Ok((self.channel, self.log))
+    }
+
+    fn record(&mut self, tag: u8, offset: u64, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.log.write_all(&[tag])?;
+        self.log.write_all(&offset.to_le_bytes())?;
+        self.log.write_all(&(data.len() as u64).to_le_bytes())?;
+        self.log.write_all(data)
+    }
+}
+
+impl<C: AbstractChannel, W: Write> Read for RecordingChannel<C, W> {
+    #[inline]
+    fn read(&mut self, bytes: &mut [u8]) -> Result<usize> {
+        let bytes_read = self.channel.read(bytes)?;
+        self.record(TAG_READ, self.nbytes_read, &bytes[..bytes_read])?;
+        self.nbytes_read += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+impl<C: AbstractChannel, W: Write> Write for RecordingChannel<C, W> {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+        let bytes_written = self.channel.write(bytes)?;
+        self.record(TAG_WRITE, self.nbytes_written, &bytes[..bytes_written])?;
+        self.nbytes_written += bytes_written as u64;
+        Ok(bytes_written)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<()> {
+        self.log.flush()?;
+        self.channel.flush()
+    }
+}
+
+/// The events recorded by a `RecordingChannel`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Recording {
+    events: Vec<Event>,
+}
+
+impl Recording {
+    /// Load a recording from the file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Load a recording from `reader`.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self> {
+        let mut events = Vec::new();
+        loop {
+            let mut tag = [0u8; 1];
+            if reader.read(&mut tag)? == 0 {
+                break;
+            }
+            let event = match tag[0] {
+                TAG_READ | TAG_WRITE => {
+                    let direction = if tag[0] == TAG_READ {
+                        Direction::Read
+                    } else {
+                        Direction::Write
+                    };
+                    let offset = read_u64(&mut reader)?;
+                    let data = read_vec(&mut reader)?;
+                    Event::Data {
+                        direction,
+                        offset,
+                        data,
+                    }
+                }
+                TAG_LABEL => {
+                    let label = String::from_utf8(read_vec(&mut reader)?)
+                        .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid label"))?;
+                    Event::Label(label)
+                }
+                _ => return Err(Error::new(ErrorKind::InvalidData, "invalid record")),
+            };
+            events.push(event);
+        }
+        Ok(Self { events })
+    }
+
+    /// Return the recorded events.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Return all the data transferred in `direction`.
+    pub fn traffic(&self, direction: Direction) -> Vec<u8> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Data {
+                    direction: d, data, ..
+                } if *d == direction => Some(data.as_slice()),
+                _ => None,
+            })
+            .flatten()
+            .cloned()
+            .collect()
+    }
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_vec<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let len = read_u64(reader)? as usize;
+    let mut data = Vec::new();
+    reader.take(len as u64).read_to_end(&mut data)?;
+    if data.len() != len {
+        return Err(Error::from(ErrorKind::UnexpectedEof));
+    }
+    Ok(data)
+}
@@ -0,0 +1,331 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+use crate::channel::{Direction, Event, Recording};
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Read, Result, Write},
+};
+
+// The maximum number of bytes of a message shown when displaying a
+// `Divergence`.
+const MAX_DISPLAYED_BYTES: usize = 64;
+
+/// The first point at which the data written by a party differs from a
+/// `Recording`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// The offset of the first differing byte in the written traffic.
+    pub offset: u64,
+    /// The index of the recorded write containing that byte, which equals the
+    /// number of recorded writes if the party wrote more data than recorded.
+    pub message: usize,
+    /// The last label recorded before that write, if any.
+    pub label: Option<String>,
+    /// The recorded write.
+    pub expected: Vec<u8>,
+    /// The data written in its place.
+    pub actual: Vec<u8>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "write diverges from recording at byte {} (message {}",
+            self.offset, self.message
+        )?;
+        if let Some(label) = &self.label {
+            write!(f, ", after label \"{}\"", label)?;
+        }
+        write!(
+            f,
+            "): expected {}, got {}",
+            hex(&self.expected),
+            hex(&self.actual)
+        )
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut s = bytes
+        .iter()
+        .take(MAX_DISPLAYED_BYTES)
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    if bytes.len() > MAX_DISPLAYED_BYTES {
+        s.push_str(&format!("... ({} bytes)", bytes.len()));
+    }
+    if s.is_empty() {
+        s.push_str("nothing");
+    }
+    s
+}
+
+// A recorded write.
+#[derive(Clone, Debug)]
+struct Message {
+    offset: usize,
+    len: usize,
+    label: Option<String>,
+}
+
+// The writes of a recording, concatenated, and split into messages.
+#[derive(Clone, Debug)]
+struct Writes {
+    data: Vec<u8>,
+    messages: Vec<Message>,
+    // The last label of the recording.
+    label: Option<String>,
+}
+
+impl Writes {
+    fn new(recording: &Recording) -> Self {
+        let mut data = Vec::new();
+        let mut messages = Vec::new();
+        let mut label = None;
+        for event in recording.events() {
+            match event {
+                Event::Data {
+                    direction: Direction::Write,
+                    data: data_,
+                    ..
+                } => {
+                    messages.push(Message {
+                        offset: data.len(),
+                        len: data_.len(),
+                        label: label.clone(),
+                    });
+                    data.extend_from_slice(data_);
+                }
+                Event::Data { .. } => (),
+                Event::Label(label_) => label = Some(label_.clone()),
+            }
+        }
+        Writes {
+            data,
+            messages,
+            label,
+        }
+    }
+
+    // Find the first byte where `actual` differs from the recorded writes,
+    // where `actual` is complete if `finished` is set, and otherwise may be a
+    // prefix of the recorded writes. The first `start` bytes of `actual` are
+    // known to match, so only the bytes after them are compared.
+    fn divergence(&self, actual: &[u8], start: usize, finished: bool) -> Option<Divergence> {
+        let start = start.min(actual.len()).min(self.data.len());
+        let offset = match actual[start..]
+            .iter()
+            .zip(self.data[start..].iter())
+            .position(|(a, b)| a != b)
+        {
+            Some(offset) => start + offset,
+            None if actual.len() > self.data.len() => self.data.len(),
+            None if finished && actual.len() < self.data.len() => actual.len(),
+            None => return None,
+        };
+        let message = self
+            .messages
+            .iter()
+            .position(|m| offset < m.offset + m.len)
+            .unwrap_or(self.messages.len());
+        let divergence = match self.messages.get(message) {
+            Some(m) => Divergence {
+                offset: offset as u64,
+                message,
+                label: m.label.clone(),
+                expected: self.data[m.offset..m.offset + m.len].to_vec(),
+                actual: actual[m.offset.min(actual.len())..(m.offset + m.len).min(actual.len())]
+                    .to_vec(),
+            },
+            None => Divergence {
+                offset: offset as u64,
+                message,
+                label: self.label.clone(),
+                expected: vec![],
+                actual: actual[offset..].to_vec(),
+            },
+        };
+        Some(divergence)
+    }
+}
+
+impl Recording {
+    /// Compare the data written in `other` to the data written in this
+    /// recording, e.g., for two runs of the same party, returning the first
+    /// point at which `other` differs.
+    pub fn first_divergence(&self, other: &Recording) -> Option<Divergence> {
+        Writes::new(self).divergence(&other.traffic(Direction::Write), 0, true)
+    }
+}
+
+/// An instantiation of the `AbstractChannel` trait which replays one party's
+/// side of a `Recording`, made with a `RecordingChannel`, without the other
+/// party.
+///
+/// Reads return the data read in the recording. Writes are checked against
+/// the data written in the recording, and fail with an error of kind
+/// `InvalidData` describing the first `Divergence`. Together with a seeded
+/// random number generator, this lets a single party be re-run
+/// deterministically, e.g., under a debugger.
+pub struct ReplayChannel {
+    reads: Vec<u8>,
+    read_offset: usize,
+    writes: Writes,
+    written: Vec<u8>,
+    divergence: Option<Divergence>,
+}
+
+impl ReplayChannel {
+    /// Make a new `ReplayChannel` replaying `recording`.
+    pub fn new(recording: &Recording) -> Self {
+        Self {
+            reads: recording.traffic(Direction::Read),
+            read_offset: 0,
+            writes: Writes::new(recording),
+            written: Vec::new(),
+            divergence: None,
+        }
+    }
+
+    /// Return the first divergence from the recording so far, if any.
+    pub fn divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+
+    /// Consume the channel, checking that everything in the recording was
+    /// written and returning the first divergence otherwise.
+    pub fn finish(self) -> std::result::Result<(), Divergence> {
+        match self.divergence {
+            Some(divergence) => Err(divergence),
+            None => match self.writes.divergence(&self.written, self.written.len(), true) {
+                Some(divergence) => Err(divergence),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+impl Read for ReplayChannel {
+    fn read(&mut self, bytes: &mut [u8]) -> Result<usize> {
+        let n = bytes.len().min(self.reads.len() - self.read_offset);
+        bytes[0..n].copy_from_slice(&self.reads[self.read_offset..self.read_offset + n]);
+        self.read_offset += n;
+        Ok(n)
+    }
+}
+
+impl Write for ReplayChannel {
+    fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+        if self.divergence.is_none() {
+            // Everything written before has already been checked.
+            let start = self.written.len();
+            self.written.extend_from_slice(bytes);
+            self.divergence = self.writes.divergence(&self.written, start, false);
+        }
+        match &self.divergence {
+            Some(divergence) => Err(Error::new(ErrorKind::InvalidData, divergence.to_string())),
+            None => Ok(bytes.len()),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{channel::RecordingChannel, simulated_channel_pair, AbstractChannel, AesRng, Block};
+    use rand::{Rng, SeedableRng};
+    use std::time::Duration;
+
+    fn party<C: AbstractChannel, F: FnMut(&mut C, &str)>(
+        channel: &mut C,
+        seed: Block,
+        mut label: F,
+    ) -> Result<Block> {
+        let mut rng = AesRng::from_seed(seed);
+        label(channel, "keys");
+        for _ in 0..4 {
+            channel.write_block(&rng.gen())?;
+        }
+        channel.flush()?;
+        let x = channel.read_block()?;
+        label(channel, "reply");
+        channel.write_block(&(x ^ rng.gen()))?;
+        channel.flush()?;
+        Ok(x)
+    }
+
+    fn record(seed: Block) -> (Recording, Block) {
+        let (left, mut right) = simulated_channel_pair(Duration::default(), None);
+        let handle = std::thread::spawn(move || {
+            for _ in 0..4 {
+                right.read_block().unwrap();
+            }
+            right.write_block(&Block::from(7u128)).unwrap();
+            right.flush().unwrap();
+            right.read_block().unwrap();
+        });
+        let mut channel = RecordingChannel::new(left, Vec::new());
+        let x = party(&mut channel, seed, |c, label| c.label(label).unwrap()).unwrap();
+        handle.join().unwrap();
+        let (_, log) = channel.into_inner().unwrap();
+        (Recording::from_reader(log.as_slice()).unwrap(), x)
+    }
+
+    #[test]
+    fn test_recording() {
+        let (recording, x) = record(Block::from(1u128));
+        assert_eq!(recording.traffic(Direction::Write).len(), 5 * 16);
+        assert_eq!(recording.traffic(Direction::Read), <[u8; 16]>::from(x).to_vec());
+        assert_eq!(recording.events()[0], Event::Label("keys".to_string()));
+        let (recording_, _) = record(Block::from(1u128));
+        assert_eq!(recording.first_divergence(&recording_), None);
+        let (recording_, _) = record(Block::from(2u128));
+        let divergence = recording.first_divergence(&recording_).unwrap();
+        assert_eq!(divergence.offset, 0);
+        assert_eq!(divergence.label, Some("keys".to_string()));
+    }
+
+    #[test]
+    fn test_replay() {
+        let seed = Block::from(1u128);
+        let (recording, x) = record(seed);
+        let mut channel = ReplayChannel::new(&recording);
+        assert_eq!(party(&mut channel, seed, |_, _| ()).unwrap(), x);
+        assert_eq!(channel.finish(), Ok(()));
+
+        let mut channel = ReplayChannel::new(&recording);
+        let e = party(&mut channel, Block::from(2u128), |_, _| ()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+        let divergence = channel.finish().unwrap_err();
+        assert_eq!(divergence.offset, 0);
+        assert_eq!(divergence.message, 0);
+        assert_eq!(divergence.label, Some("keys".to_string()));
+        assert_eq!(divergence.expected.len(), 16);
+        assert_eq!(divergence.actual.len(), 16);
+    }
+
+    #[test]
+    fn test_replay_partial_writes() {
+        let (recording, _) = record(Block::from(1u128));
+        let mut data = recording.traffic(Direction::Write);
+        data[20] ^= 1;
+        let mut channel = ReplayChannel::new(&recording);
+        for (i, byte) in data.iter().enumerate() {
+            let result = channel.write(std::slice::from_ref(byte));
+            assert_eq!(result.is_ok(), i < 20);
+        }
+        let divergence = channel.finish().unwrap_err();
+        assert_eq!(divergence.offset, 20);
+        assert_eq!(divergence.message, 1);
+        assert_eq!(divergence.actual.len(), 5);
+    }
+}
@@ -0,0 +1,258 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+use std::{
+    io::{Read, Result, Write},
+    sync::mpsc::{channel, Receiver, Sender},
+    time::{Duration, Instant},
+};
+
+// The number of buffered bytes after which a write is sent without waiting
+// for a flush.
+const MAX_BUFFER_SIZE: usize = 1 << 16;
+
+// The link used by `SimulatedChannel::wan_pair`: 40 ms one-way latency at
+// 100 Mbps.
+const WAN_LATENCY: Duration = Duration::from_millis(40);
+const WAN_BANDWIDTH: Option<u64> = Some(100_000_000);
+
+/// An in-process channel simulating a network link with a given one-way
+/// latency and bandwidth, for predicting the performance of protocols over a
+/// WAN.
+///
+/// Written data is buffered until the channel is flushed (or the buffer grows
+/// large), at which point it is put on the link. Data arrives at the other end
+/// once it has been transmitted at the link's bandwidth, after any data sent
+/// before it, and has then spent `latency` in flight. Reads block until then,
+/// so wall-clock timings of protocols run over a `SimulatedChannel` pair
+/// include the simulated network delays.
+///
+/// Each end keeps track of the number of bytes written and read, and the
+/// number of round trips, counted as the number of times it reads data after
+/// having written data.
+pub struct SimulatedChannel {
+    tx: Sender<(Instant, Vec<u8>)>,
+    rx: Receiver<(Instant, Vec<u8>)>,
+    latency: Duration,
+    // The bandwidth, in bits per second, if limited.
+    bandwidth: Option<u64>,
+    // The time at which the outgoing link is done transmitting.
+    link_free_at: Instant,
+    write_buffer: Vec<u8>,
+    read_buffer: Vec<u8>,
+    read_offset: usize,
+    nbytes_written: usize,
+    nbytes_read: usize,
+    nround_trips: usize,
+    // Whether data was written since the last read.
+    written: bool,
+}
+
+/// Create a pair of `SimulatedChannel`s connected by a link with one-way
+/// latency `latency` and a bandwidth of `bandwidth` bits per second in each
+/// direction, where `None` denotes unlimited bandwidth.
+pub fn simulated_channel_pair(
+    latency: Duration,
+    bandwidth: Option<u64>,
+) -> (SimulatedChannel, SimulatedChannel) {
+    let (tx0, rx0) = channel();
+    let (tx1, rx1) = channel();
+    (
+        SimulatedChannel::new(tx0, rx1, latency, bandwidth),
+        SimulatedChannel::new(tx1, rx0, latency, bandwidth),
+    )
+}
+
+impl SimulatedChannel {
+    fn new(
+        tx: Sender<(Instant, Vec<u8>)>,
+        rx: Receiver<(Instant, Vec<u8>)>,
+        latency: Duration,
+        bandwidth: Option<u64>,
+    ) -> Self {
+        assert!(bandwidth != Some(0), "bandwidth must be nonzero");
+        SimulatedChannel {
+            tx,
+            rx,
+            latency,
+            bandwidth,
+            link_free_at: Instant::now(),
+            write_buffer: Vec::new(),
+            read_buffer: Vec::new(),
+            read_offset: 0,
+            nbytes_written: 0,
+            nbytes_read: 0,
+            nround_trips: 0,
+            written: false,
+        }
+    }
+
+    /// Create a pair of `SimulatedChannel`s connected by a typical WAN link,
+    /// with 40 ms one-way latency and 100 Mbps bandwidth in each direction.
+    ///
+    /// This is the setting used in the WAN benchmarks.
+    pub fn wan_pair() -> (SimulatedChannel, SimulatedChannel) {
+        simulated_channel_pair(WAN_LATENCY, WAN_BANDWIDTH)
+    }
+
+    /// Return the one-way latency of the link.
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Return the bandwidth of the link in bits per second, if limited.
+    pub fn bandwidth(&self) -> Option<u64> {
+        self.bandwidth
+    }
+
+    /// Return the number of bytes written to the channel.
+    pub fn bytes_written(&self) -> usize {
+        self.nbytes_written
+    }
+
+    /// Return the number of bytes read from the channel.
+    pub fn bytes_read(&self) -> usize {
+        self.nbytes_read
+    }
+
+    /// Return the number of round trips, i.e., the number of times data was
+    /// read from the channel after writing to it.
+    pub fn round_trips(&self) -> usize {
+        self.nround_trips
+    }
+
+    /// Clear the number of bytes read/written and the number of round trips.
+    pub fn clear(&mut self) {
+        self.nbytes_written = 0;
+        self.nbytes_read = 0;
+        self.nround_trips = 0;
+        self.written = false;
+    }
+
+    // Put the buffered data on the link.
+    fn send_buffer(&mut self) -> Result<()> {
+        if self.write_buffer.is_empty() {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let start = self.link_free_at.max(now);
+        let transmission = match self.bandwidth {
+            Some(bandwidth) => {
+                let nbits = 8 * self.write_buffer.len() as u64;
+                Duration::from_nanos(nbits.saturating_mul(1_000_000_000) / bandwidth)
+            }
+            None => Duration::default(),
+        };
+        self.link_free_at = start + transmission;
+        let data = std::mem::take(&mut self.write_buffer);
+        self.tx
+            .send((self.link_free_at + self.latency, data))
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))
+    }
+}
+
+impl Read for SimulatedChannel {
+    fn read(&mut self, bytes: &mut [u8]) -> Result<usize> {
+        if bytes.is_empty() {
+            return Ok(0);
+        }
+        if self.read_offset == self.read_buffer.len() {
+            // Make sure the other end gets our data before waiting on theirs.
+            self.send_buffer()?;
+            let (arrival, data) = match self.rx.recv() {
+                Ok(msg) => msg,
+                // The other end hung up.
+                Err(_) => return Ok(0),
+            };
+            let now = Instant::now();
+            if arrival > now {
+                std::thread::sleep(arrival - now);
+            }
+            self.read_buffer = data;
+            self.read_offset = 0;
+        }
+        let n = bytes.len().min(self.read_buffer.len() - self.read_offset);
+        bytes[0..n].copy_from_slice(&self.read_buffer[self.read_offset..self.read_offset + n]);
+        self.read_offset += n;
+        self.nbytes_read += n;
+        if self.written {
+            self.nround_trips += 1;
+            self.written = false;
+        }
+        Ok(n)
+    }
+}
+
+impl Write for SimulatedChannel {
+    fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+        self.write_buffer.extend_from_slice(bytes);
+        self.nbytes_written += bytes.len();
+        self.written |= !bytes.is_empty();
+        if self.write_buffer.len() >= MAX_BUFFER_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.send_buffer()
+    }
+}
+
+impl Drop for SimulatedChannel {
+    fn drop(&mut self) {
+        let _ = self.send_buffer();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AbstractChannel;
+
+    #[test]
+    fn test_simulated_channel() {
+        let latency = Duration::from_millis(20);
+        let (mut sender, mut receiver) = simulated_channel_pair(latency, None);
+        let handle = std::thread::spawn(move || {
+            for i in 0..4 {
+                let x = sender.read_u64().unwrap();
+                sender.write_u64(x + i).unwrap();
+                sender.flush().unwrap();
+            }
+            sender
+        });
+        let start = Instant::now();
+        let mut x = 0;
+        for i in 0..4 {
+            receiver.write_u64(x).unwrap();
+            receiver.flush().unwrap();
+            let y = receiver.read_u64().unwrap();
+            assert_eq!(y, x + i);
+            x = y;
+        }
+        assert!(start.elapsed() >= 8 * latency);
+        let sender = handle.join().unwrap();
+        assert_eq!(receiver.round_trips(), 4);
+        assert_eq!(receiver.bytes_written(), 32);
+        assert_eq!(receiver.bytes_read(), 32);
+        assert_eq!(sender.round_trips(), 3);
+        assert_eq!(sender.bytes_written(), 32);
+    }
+
+    #[test]
+    fn test_simulated_channel_bandwidth() {
+        // Sending 100 KB at 8 Mbps takes 100 ms.
+        let (mut sender, mut receiver) =
+            simulated_channel_pair(Duration::default(), Some(8_000_000));
+        let data = vec![1u8; 100_000];
+        let start = Instant::now();
+        sender.write_all(&data).unwrap();
+        sender.flush().unwrap();
+        assert_eq!(receiver.read_vec(data.len()).unwrap(), data);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,149 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+use crate::{AbstractChannel, PartyId, Transcript};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Result, Write};
+
+/// An instantiation of the `AbstractChannel` trait which absorbs all traffic
+/// on the channel into a Fiat–Shamir `Transcript`.
+///
+/// Bytes read and bytes written are hashed separately. Whenever the transcript
+/// is accessed, the digest of the traffic sent by party `0` to party `1` is
+/// appended to the transcript, followed by the digest of the traffic sent by
+/// party `1` to party `0`, each under its own label. Both parties of a
+/// two-party channel therefore end up with the same transcript, no matter how
+/// their reads and writes interleave or how the bytes are split into calls, as
+/// long as they access the transcript at the same points in the protocol, and
+/// the transcript binds the direction in which each message was sent.
+pub struct TranscriptChannel<C> {
+    channel: C,
+    transcript: Transcript,
+    party: PartyId,
+    read: Sha256,
+    written: Sha256,
+    pending: bool,
+}
+
+impl<C: AbstractChannel> TranscriptChannel<C> {
+    /// Make a new `TranscriptChannel`, which absorbs traffic into
+    /// `transcript`, where `party` is our index in the two-party protocol: `0`
+    /// at one end of the channel and `1` at the other.
+    ///
+    /// # Panics
+    /// If `party` is neither `0` nor `1`.
+    pub fn new(channel: C, transcript: Transcript, party: PartyId) -> Self {
+        assert!(party < 2, "party must be 0 or 1");
+        Self {
+            channel,
+            transcript,
+            party,
+            read: Sha256::new(),
+            written: Sha256::new(),
+            pending: false,
+        }
+    }
+
+    /// Absorb the traffic so far and return the transcript, e.g., to derive a
+    /// challenge from it.
+    pub fn transcript(&mut self) -> &mut Transcript {
+        if self.pending {
+            let read = std::mem::replace(&mut self.read, Sha256::new()).result();
+            let written = std::mem::replace(&mut self.written, Sha256::new()).result();
+            let (sent0, sent1) = if self.party == 0 {
+                (written, read)
+            } else {
+                (read, written)
+            };
+            self.transcript.append_bytes("traffic 0 -> 1", &sent0);
+            self.transcript.append_bytes("traffic 1 -> 0", &sent1);
+            self.pending = false;
+        }
+        &mut self.transcript
+    }
+
+    /// Consume the channel and output the underlying channel and transcript.
+    pub fn into_inner(mut self) -> (C, Transcript) {
+        self.transcript();
+        (self.channel, self.transcript)
+    }
+}
+
+impl<C: AbstractChannel> Read for TranscriptChannel<C> {
+    #[inline]
+    fn read(&mut self, bytes: &mut [u8]) -> Result<usize> {
+        let bytes_read = self.channel.read(bytes)?;
+        self.read.input(&bytes[..bytes_read]);
+        self.pending |= bytes_read > 0;
+        Ok(bytes_read)
+    }
+}
+
+impl<C: AbstractChannel> Write for TranscriptChannel<C> {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+        let bytes_written = self.channel.write(bytes)?;
+        self.written.input(&bytes[..bytes_written]);
+        self.pending |= bytes_written > 0;
+        Ok(bytes_written)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<()> {
+        self.channel.flush()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{unix_channel_pair, Block, Channel};
+
+    #[test]
+    fn test_transcript_channel() {
+        let (x, y, z) = (
+            rand::random::<Block>(),
+            rand::random::<Block>(),
+            rand::random::<Block>(),
+        );
+        let (sender, receiver) = unix_channel_pair();
+        let handle = std::thread::spawn(move || {
+            let mut channel = TranscriptChannel::new(sender, Transcript::new("test"), 0);
+            channel.write_block(&x).unwrap();
+            channel.flush().unwrap();
+            assert_eq!(channel.read_block().unwrap(), y);
+            let c1 = channel.transcript().challenge_block("c1");
+            channel.write_block(&z).unwrap();
+            channel.flush().unwrap();
+            let c2 = channel.transcript().challenge_block("c2");
+            (c1, c2)
+        });
+        let mut channel = TranscriptChannel::new(receiver, Transcript::new("test"), 1);
+        channel.write_block(&y).unwrap();
+        channel.flush().unwrap();
+        assert_eq!(channel.read_block().unwrap(), x);
+        let c1 = channel.transcript().challenge_block("c1");
+        assert_eq!(channel.read_block().unwrap(), z);
+        let c2 = channel.transcript().challenge_block("c2");
+        assert_eq!(handle.join().unwrap(), (c1, c2));
+        assert_ne!(c1, c2);
+    }
+
+    #[test]
+    fn test_transcript_channel_direction() {
+        let (x, y) = (rand::random::<Block>(), rand::random::<Block>());
+        // Party 0 sends `x` and receives `y`, or the other way around.
+        let challenge = |sent: Block, received: Block| {
+            let reader = std::io::Cursor::new(<[u8; 16]>::from(received));
+            let channel = Channel::new(reader, Vec::new());
+            let mut channel = TranscriptChannel::new(channel, Transcript::new("test"), 0);
+            channel.write_block(&sent).unwrap();
+            assert_eq!(channel.read_block().unwrap(), received);
+            channel.transcript().challenge_block("c")
+        };
+        assert_ne!(challenge(x, y), challenge(y, x));
+    }
+}
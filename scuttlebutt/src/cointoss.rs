@@ -4,59 +4,66 @@
 // Copyright © 2019 Galois, Inc.
 // See LICENSE for licensing information.
 
-//! Implementation of a simple two-party coin tossing protocol using a PRG as a
+//! Implementation of a simple coin tossing protocol using a hash-based
 //! commitment.
 //!
-//! On input `seed`, the sender computes `r := PRG(seed)` and sends `r` to the
-//! receiver. It then receives `seed_` from the receiver and outputs `seed ⊕
-//! seed_`. Likewise, on input `seed`, the receiver gets `r`, sends `seed` to
-//! the sender, and then receives `seed_`, checking that `PRG(seed_) = r`.
+//! On input `seed`, the sender commits to `seed` and sends the commitment to
+//! the receiver. It then receives `seed_` from the receiver, opens the
+//! commitment, and outputs `seed ⊕ seed_`. Likewise, on input `seed`, the
+//! receiver gets the commitment, sends `seed` to the sender, and then receives
+//! the opening of the commitment to `seed_`, checking that it is valid.
+//!
+//! `toss` generalizes this to any number of parties: every party broadcasts
+//! a commitment to its seed, after which all parties open their commitments
+//! and output the XOR of all seeds.
 
-use crate::{AbstractChannel, AesRng, Block};
-use rand_core::{RngCore, SeedableRng};
+use crate::{
+    commitment::{
+        commit,
+        receive_commitment,
+        receive_opening,
+        send_commitment,
+        send_opening,
+        CommitmentValue,
+    },
+    AbstractChannel,
+    Block,
+    Network,
+};
+use rand::{CryptoRng, Rng};
 
 /// Errors produced by the coin tossing protocol.
-#[derive(Debug)]
-pub enum Error {
-    /// An I/O error occurred.
-    IoError(std::io::Error),
-    /// The commitment check failed.
-    CommitmentCheckFailed,
-}
+pub use crate::commitment::Error;
 
-impl From<std::io::Error> for Error {
-    fn from(e: std::io::Error) -> Self {
-        Error::IoError(e)
-    }
+fn to_bytes(seeds: &[Block]) -> Vec<u8> {
+    seeds.iter().flat_map(|seed| seed.as_ref().to_vec()).collect()
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            Error::IoError(e) => write!(f, "IO error: {}", e),
-            Error::CommitmentCheckFailed => "committment check failed".fmt(f),
-        }
+fn xor_bytes(out: &mut [Block], bytes: &[u8]) {
+    for (x, chunk) in out.iter_mut().zip(bytes.chunks(16)) {
+        let mut seed = [0u8; 16];
+        seed.copy_from_slice(chunk);
+        *x ^= Block::from(seed);
     }
 }
 
-/// Coin tossing sender.
+/// Coin tossing sender, using `rng` for the randomness of the commitment.
 #[inline]
-pub fn send<C: AbstractChannel>(channel: &mut C, seeds: &[Block]) -> Result<Vec<Block>, Error> {
-    let mut out = Vec::with_capacity(seeds.len());
-    for seed in seeds.iter() {
-        let mut rng = AesRng::from_seed(*seed);
-        let mut com = Block::default();
-        rng.fill_bytes(&mut com.as_mut());
-        channel.write_block(&com)?;
-    }
+pub fn send<C: AbstractChannel, RNG: CryptoRng + Rng>(
+    channel: &mut C,
+    seeds: &[Block],
+    rng: &mut RNG,
+) -> Result<Vec<Block>, Error> {
+    let msg = to_bytes(seeds);
+    let (commitment, opening) = commit(&msg, rng);
+    send_commitment(channel, &commitment)?;
     channel.flush()?;
+    let mut out = Vec::with_capacity(seeds.len());
     for seed in seeds.iter() {
         let seed_ = channel.read_block()?;
         out.push(*seed ^ seed_);
     }
-    for seed in seeds.iter() {
-        channel.write_block(&seed)?;
-    }
+    send_opening(channel, &msg, &opening)?;
     channel.flush()?;
     Ok(out)
 }
@@ -64,25 +71,52 @@ pub fn send<C: AbstractChannel>(channel: &mut C, seeds: &[Block]) -> Result<Vec<
 /// Coin tossing receiver.
 #[inline]
 pub fn receive<C: AbstractChannel>(channel: &mut C, seeds: &[Block]) -> Result<Vec<Block>, Error> {
-    let mut coms = Vec::with_capacity(seeds.len());
-    let mut out = Vec::with_capacity(seeds.len());
-    for _ in 0..seeds.len() {
-        let com = channel.read_block()?;
-        coms.push(com);
-    }
+    let commitment = receive_commitment(channel)?;
     for seed in seeds.iter() {
-        channel.write_block(&seed)?;
+        channel.write_block(seed)?;
     }
     channel.flush()?;
-    for (seed, com) in seeds.iter().zip(coms.into_iter()) {
-        let seed_ = channel.read_block()?;
-        let mut rng_ = AesRng::from_seed(seed_);
-        let mut check = Block::default();
-        rng_.fill_bytes(&mut check.as_mut());
-        if check != com {
-            return Err(Error::CommitmentCheckFailed);
+    let msg = receive_opening(channel, &commitment, 16 * seeds.len())?;
+    let mut out = seeds.to_vec();
+    xor_bytes(&mut out, &msg);
+    Ok(out)
+}
+
+/// Multi-party coin tossing, where every party in `network` calls `toss` with
+/// the same number of seeds, using `rng` for the randomness of its commitment.
+///
+/// The commitments are sent with echo broadcast, so that a malicious party
+/// cannot make honest parties output different values without being
+/// detected.
+pub fn toss<C: AbstractChannel, RNG: CryptoRng + Rng>(
+    network: &mut Network<C>,
+    seeds: &[Block],
+    rng: &mut RNG,
+) -> Result<Vec<Block>, Error> {
+    let msg = to_bytes(seeds);
+    let (commitment, opening) = commit(&msg, rng);
+    let mut commitments = Vec::with_capacity(network.nparties() - 1);
+    for sender in 0..network.nparties() {
+        if sender == network.id() {
+            network.broadcast(&<[u8; 32]>::from(commitment))?;
+        } else {
+            let bytes = network.receive_broadcast(sender)?;
+            if bytes.len() != 32 {
+                return Err(Error::CommitmentCheckFailed);
+            }
+            let mut commitment = [0u8; 32];
+            commitment.copy_from_slice(&bytes);
+            commitments.push((sender, CommitmentValue::from(commitment)));
         }
-        out.push(*seed ^ seed_)
+    }
+    for (_, channel) in network.channels_mut().iter_mut() {
+        send_opening(channel, &msg, &opening)?;
+        channel.flush()?;
+    }
+    let mut out = seeds.to_vec();
+    for (id, commitment) in commitments.iter() {
+        let msg_ = receive_opening(network.channel(*id), commitment, msg.len())?;
+        xor_bytes(&mut out, &msg_);
     }
     Ok(out)
 }
@@ -92,7 +126,7 @@ mod tests {
     #[cfg(feature = "nightly")]
     extern crate test;
     use super::*;
-    use crate::Channel;
+    use crate::{AesRng, Channel};
     use std::{
         io::{BufReader, BufWriter},
         os::unix::net::UnixStream,
@@ -107,7 +141,7 @@ mod tests {
             let reader = BufReader::new(sender.try_clone().unwrap());
             let writer = BufWriter::new(sender);
             let mut channel = Channel::new(reader, writer);
-            let output = send(&mut channel, &[seed]).unwrap();
+            let output = send(&mut channel, &[seed], &mut AesRng::new()).unwrap();
             assert_eq!(output[0], seed ^ seed_);
         });
         let reader = BufReader::new(receiver.try_clone().unwrap());
@@ -117,4 +151,30 @@ mod tests {
         assert_eq!(output_[0], seed ^ seed_);
         handle.join().unwrap();
     }
+
+    #[test]
+    fn test_toss() {
+        let nparties = 4;
+        let handles = Network::in_process(nparties)
+            .into_iter()
+            .map(|mut network| {
+                std::thread::spawn(move || {
+                    let seeds = [rand::random::<Block>(), rand::random::<Block>()];
+                    (seeds, toss(&mut network, &seeds, &mut AesRng::new()).unwrap())
+                })
+            })
+            .collect::<Vec<_>>();
+        let results = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>();
+        let mut expected = [Block::default(); 2];
+        for (seeds, _) in results.iter() {
+            expected[0] ^= seeds[0];
+            expected[1] ^= seeds[1];
+        }
+        for (_, output) in results {
+            assert_eq!(output, expected);
+        }
+    }
 }
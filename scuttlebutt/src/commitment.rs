@@ -4,12 +4,31 @@
 // Copyright © 2019 Galois, Inc.
 // See LICENSE for licensing information.
 
-//! A trait defining a Commitment Scheme and an implementation in the random
-//! oracle model using SHA256.
+//! A commitment scheme in the random oracle model using SHA256.
+//!
+//! `commit` commits to a message using fresh randomness, returning a
+//! `CommitmentValue` to send to the other party and an `Opening` to keep until
+//! the commitment is opened, which the other party checks with `verify`. The
+//! `send_commitment`, `receive_commitment`, `send_opening`, and
+//! `receive_opening` functions do the same over a channel.
+//!
+//! The lower-level `Commitment` trait commits to messages using a
+//! caller-provided seed.
 //!
 //! # Usage
 //! ```rust
-//! use crate::scuttlebutt::commitment::{Commitment, ShaCommitment};
+//! use scuttlebutt::{
+//!     commitment::{commit, verify, Commitment, ShaCommitment},
+//!     AesRng,
+//! };
+//!
+//! // commit to a message
+//! let mut rng = AesRng::new();
+//! let (commitment, opening) = commit(b"hello world", &mut rng);
+//!
+//! // check the opening of a commitment
+//! assert!(verify(&commitment, b"hello world", &opening));
+//! assert!(!verify(&commitment, b"hello there", &opening));
 //!
 //! // define a seed
 //! let seed = [0u8; 32];
@@ -34,6 +53,8 @@
 //! assert!(ShaCommitment::check(&commitment,&commitment_));
 //! ```
 
+use crate::AbstractChannel;
+use rand_core::{CryptoRng, RngCore};
 use sha2::{Digest, Sha256};
 
 /// Generic commitment scheme.
@@ -85,10 +106,143 @@ impl Commitment for ShaCommitment {
     }
 }
 
+/// Errors produced when opening a commitment.
+#[derive(Debug)]
+pub enum Error {
+    /// An I/O error occurred.
+    IoError(std::io::Error),
+    /// The commitment check failed.
+    CommitmentCheckFailed,
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::IoError(e) => write!(f, "IO error: {}", e),
+            Error::CommitmentCheckFailed => "committment check failed".fmt(f),
+        }
+    }
+}
+
+/// A commitment to a message, as produced by `commit`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CommitmentValue([u8; 32]);
+
+/// The randomness needed to open a `CommitmentValue`, as produced by `commit`.
+/// It must be kept secret until the commitment is opened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Opening([u8; 32]);
+
+impl From<[u8; 32]> for CommitmentValue {
+    #[inline]
+    fn from(bytes: [u8; 32]) -> Self {
+        CommitmentValue(bytes)
+    }
+}
+
+impl From<CommitmentValue> for [u8; 32] {
+    #[inline]
+    fn from(commitment: CommitmentValue) -> Self {
+        commitment.0
+    }
+}
+
+impl From<[u8; 32]> for Opening {
+    #[inline]
+    fn from(bytes: [u8; 32]) -> Self {
+        Opening(bytes)
+    }
+}
+
+impl From<Opening> for [u8; 32] {
+    #[inline]
+    fn from(opening: Opening) -> Self {
+        opening.0
+    }
+}
+
+/// Commit to `msg`, using randomness from `rng`.
+#[inline]
+pub fn commit<RNG: CryptoRng + RngCore>(
+    msg: &[u8],
+    rng: &mut RNG,
+) -> (CommitmentValue, Opening) {
+    let mut randomness = [0u8; 32];
+    rng.fill_bytes(&mut randomness);
+    let mut commit = ShaCommitment::new(randomness);
+    commit.input(msg);
+    (CommitmentValue(commit.finish()), Opening(randomness))
+}
+
+/// Check that `opening` opens `commitment` to `msg`.
+#[inline]
+pub fn verify(commitment: &CommitmentValue, msg: &[u8], opening: &Opening) -> bool {
+    let mut commit = ShaCommitment::new(opening.0);
+    commit.input(msg);
+    ShaCommitment::check(&commitment.0, &commit.finish())
+}
+
+/// Send `commitment` over `channel`.
+#[inline]
+pub fn send_commitment<C: AbstractChannel>(
+    channel: &mut C,
+    commitment: &CommitmentValue,
+) -> std::io::Result<()> {
+    channel.write_all(&commitment.0)
+}
+
+/// Receive a commitment sent with `send_commitment` over `channel`.
+#[inline]
+pub fn receive_commitment<C: AbstractChannel>(
+    channel: &mut C,
+) -> std::io::Result<CommitmentValue> {
+    let mut commitment = [0u8; 32];
+    channel.read_exact(&mut commitment)?;
+    Ok(CommitmentValue(commitment))
+}
+
+/// Open a commitment to `msg` over `channel` by sending `msg` and `opening`.
+#[inline]
+pub fn send_opening<C: AbstractChannel>(
+    channel: &mut C,
+    msg: &[u8],
+    opening: &Opening,
+) -> std::io::Result<()> {
+    channel.write_all(msg)?;
+    channel.write_all(&opening.0)
+}
+
+/// Receive the opening of `commitment`, sent with `send_opening`, to a
+/// message of `nbytes` bytes over `channel`, and return the message if the
+/// opening is valid.
+#[inline]
+pub fn receive_opening<C: AbstractChannel>(
+    channel: &mut C,
+    commitment: &CommitmentValue,
+    nbytes: usize,
+) -> Result<Vec<u8>, Error> {
+    let msg = channel.read_vec(nbytes)?;
+    let mut opening = [0u8; 32];
+    channel.read_exact(&mut opening)?;
+    if verify(commitment, &msg, &Opening(opening)) {
+        Ok(msg)
+    } else {
+        Err(Error::CommitmentCheckFailed)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{simulated_channel_pair, AesRng};
     use rand::Rng;
+    use std::io::Write;
 
     #[test]
     fn commit_hello_world() {
@@ -129,4 +283,37 @@ mod tests {
 
         assert!(ShaCommitment::check(&commitment, &commitment_));
     }
+
+    #[test]
+    fn commit_verify() {
+        let mut rng = AesRng::new();
+        let (commitment, opening) = commit(b"hello world", &mut rng);
+        assert!(verify(&commitment, b"hello world", &opening));
+        assert!(!verify(&commitment, b"hello world!", &opening));
+        let (_, opening_) = commit(b"hello world", &mut rng);
+        assert!(!verify(&commitment, b"hello world", &opening_));
+    }
+
+    #[test]
+    fn commit_channel() {
+        let (mut sender, mut receiver) =
+            simulated_channel_pair(std::time::Duration::default(), None);
+        let mut rng = AesRng::new();
+        let (commitment, opening) = commit(b"hello world", &mut rng);
+        send_commitment(&mut sender, &commitment).unwrap();
+        send_opening(&mut sender, b"hello world", &opening).unwrap();
+        send_commitment(&mut sender, &commitment).unwrap();
+        send_opening(&mut sender, b"hello there", &opening).unwrap();
+        sender.flush().unwrap();
+
+        let commitment_ = receive_commitment(&mut receiver).unwrap();
+        assert_eq!(commitment_, commitment);
+        let msg = receive_opening(&mut receiver, &commitment_, 11).unwrap();
+        assert_eq!(msg, b"hello world");
+        let commitment_ = receive_commitment(&mut receiver).unwrap();
+        match receive_opening(&mut receiver, &commitment_, 11) {
+            Err(Error::CommitmentCheckFailed) => (),
+            _ => panic!("opening to a different message accepted"),
+        }
+    }
 }
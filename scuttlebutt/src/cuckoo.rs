@@ -0,0 +1,437 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Cuckoo hashing with a stash, and the simple hashing that goes along with it.
+//!
+//! In hashing-based PSI and OPPRF protocols, one party places each of its
+//! items in a single bin of a `CuckooHash`, while the other places each of its
+//! items in every bin it could land in, using a `SimpleHash` with the same
+//! hash functions. Items that cannot be placed end up in a small stash, which
+//! protocols handle separately.
+//!
+//! For a cuckoo filter, which only supports membership tests, see
+//! `cuckoofilter::CuckooFilter`.
+
+use crate::{Aes128, Block};
+
+/// The number of evictions tried before an item is moved to the stash.
+const MAX_ITERS: usize = 1000;
+
+/// Errors produced by cuckoo hashing.
+#[derive(Debug)]
+pub enum Error {
+    /// No parameters are known for the given number of items and hashes.
+    InvalidParameters {
+        /// Number of items.
+        nitems: usize,
+        /// Number of hashes.
+        nhashes: usize,
+    },
+    /// The cuckoo hash table and its stash are full.
+    CuckooHashFull,
+    /// The fingerprint size of a cuckoo filter is not between 1 and 64 bits.
+    InvalidFingerprintSize(usize),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::InvalidParameters { nitems, nhashes } => write!(
+                f,
+                "no parameters known for {} items and {} hashes",
+                nitems, nhashes
+            ),
+            Error::CuckooHashFull => "cuckoo hash table is full".fmt(f),
+            Error::InvalidFingerprintSize(n) => write!(f, "invalid fingerprint size {}", n),
+        }
+    }
+}
+
+/// A family of hash functions mapping items to bins.
+pub trait BinHasher {
+    /// Output the bin in `[0, nbins)` of item `x` under the `hidx`th hash
+    /// function.
+    fn bin(&self, x: Block, hidx: usize, nbins: usize) -> usize;
+}
+
+/// Hash functions for items that are already uniformly random, such as the
+/// outputs of a random oracle, which use the `hidx`th 32 bits of an item for
+/// `hidx < 3`, and `AES_x(hidx)` otherwise.
+///
+/// The lowest byte of an item is never used for the first three hashes, so
+/// protocols can use it to tag an item with its hash index.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BlockHasher;
+
+impl BinHasher for BlockHasher {
+    #[inline]
+    fn bin(&self, x: Block, hidx: usize, nbins: usize) -> usize {
+        if hidx < 3 {
+            let bytes: [u8; 16] = x.into();
+            let mut array = [0u8; 4];
+            array.copy_from_slice(&bytes[4 * hidx + 1..4 * (hidx + 1) + 1]);
+            (u32::from_le_bytes(array) as usize) % nbins
+        } else {
+            let h = Aes128::new(x).encrypt(Block::from(hidx as u128));
+            (u128::from(h) % (nbins as u128)) as usize
+        }
+    }
+}
+
+/// Hash functions for arbitrary items, where the `i`th function is
+/// `AES_{kᵢ}(x) ⊕ x` for a key `kᵢ`, as in the Davies–Meyer construction.
+#[derive(Clone)]
+pub struct AesHasher {
+    keys: Vec<Aes128>,
+}
+
+impl AesHasher {
+    /// Make a new `AesHasher`, with one hash function for each of `keys`.
+    pub fn new(keys: &[Block]) -> Self {
+        let keys = keys.iter().map(|k| Aes128::new(*k)).collect();
+        Self { keys }
+    }
+
+    /// Get the number of hash functions.
+    pub fn nhashes(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+impl BinHasher for AesHasher {
+    #[inline]
+    fn bin(&self, x: Block, hidx: usize, nbins: usize) -> usize {
+        let h = self.keys[hidx].encrypt(x) ^ x;
+        (u128::from(h) % (nbins as u128)) as usize
+    }
+}
+
+/// Parameters of a cuckoo hash table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parameters {
+    /// The number of bins.
+    pub nbins: usize,
+    /// The number of hash functions.
+    pub nhashes: usize,
+    /// The maximum number of items in the stash.
+    pub stash_size: usize,
+}
+
+impl Parameters {
+    /// Make new `Parameters` from their components.
+    pub fn new(nbins: usize, nhashes: usize, stash_size: usize) -> Self {
+        Self {
+            nbins,
+            nhashes,
+            stash_size,
+        }
+    }
+
+    /// Make `Parameters` for inserting `n` items using `nhashes` hash
+    /// functions and no stash, such that insertion fails with probability at
+    /// most `2^-ssp`.
+    pub fn with_statistical_security(n: usize, nhashes: usize, ssp: usize) -> Result<Self, Error> {
+        let nbins = compute_nbins(n, nhashes, ssp)?;
+        Ok(Self::new(nbins, nhashes, 0))
+    }
+}
+
+// The cumulative distribution function of the normal distribution with mean
+// `mu` and standard deviation `sigma`, using the approximation of `erf` from
+// Abramowitz and Stegun, 7.1.26, which is accurate to within `1.5 ⋅ 10^-7`.
+fn normal_cdf(x: f64, mu: f64, sigma: f64) -> f64 {
+    let z = (x - mu) / (sigma * std::f64::consts::SQRT_2);
+    let t = 1.0 / (1.0 + 0.327_591_1 * z.abs());
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erf = 1.0 - poly * (-z * z).exp();
+    0.5 * (1.0 + erf.copysign(z))
+}
+
+/// Compute the number of bins needed to insert `n` items using `nhashes` hash
+/// functions and no stash, such that insertion fails with probability at most
+/// `2^-ssp`.
+///
+/// For three, four, and five hash functions and `ssp ≤ 40`, this uses the
+/// stretch factors from <https://eprint.iacr.org/2016/799>, §5: `1.27n` bins
+/// for three hash functions (or `1.62n` for `n ≥ 2^27`), `1.09n` for four, and
+/// `1.05n` for five. Otherwise, it uses the interpolation of the failure
+/// probability for three hash functions from
+/// <https://eprint.iacr.org/2018/579>, §7, which is conservative for more hash
+/// functions, as adding hash functions only makes failures less likely. Fewer
+/// than three hash functions are not supported.
+pub fn compute_nbins(n: usize, nhashes: usize, ssp: usize) -> Result<usize, Error> {
+    if nhashes < 3 {
+        return Err(Error::InvalidParameters { nitems: n, nhashes });
+    }
+    if ssp <= 40 {
+        let e = match nhashes {
+            3 if n < 1 << 27 => Some(1.27),
+            3 => Some(1.62),
+            4 => Some(1.09),
+            5 => Some(1.05),
+            _ => None,
+        };
+        if let Some(e) = e {
+            return Ok(((e * n as f64).ceil() as usize).max(1));
+        }
+    }
+    // The failure probability is `2^-(a ⋅ e + b)` for `e ⋅ n` bins.
+    let logn = (n.max(1) as f64).log2();
+    let a = 123.5 * normal_cdf(logn, 6.3, 2.3);
+    let b = -130.0 * normal_cdf(logn, 6.45, 2.18) - logn;
+    let e = (ssp as f64 - b) / a;
+    Ok(((e * n as f64).ceil() as usize).max(1))
+}
+
+/// An item in a hash table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CuckooItem {
+    /// The item itself.
+    pub entry: Block,
+    /// The index of the input the item corresponds to.
+    pub input_index: usize,
+    /// The index of the hash function used to place the item in its bin.
+    pub hash_index: usize,
+}
+
+/// A cuckoo hash table with a stash, in which each item is placed in one of
+/// the bins given by its hash functions.
+pub struct CuckooHash<H> {
+    /// The bins of the table, each holding at most one item.
+    pub items: Vec<Option<CuckooItem>>,
+    /// The items that could not be placed in a bin.
+    pub stash: Vec<CuckooItem>,
+    params: Parameters,
+    hasher: H,
+}
+
+impl<H: BinHasher> CuckooHash<H> {
+    /// Make a new, empty cuckoo hash table with parameters `params` and hash
+    /// functions `hasher`.
+    pub fn new(params: Parameters, hasher: H) -> Self {
+        Self {
+            items: vec![None; params.nbins],
+            stash: Vec::new(),
+            params,
+            hasher,
+        }
+    }
+
+    /// Build a cuckoo hash table holding `inputs`, where the `i`th input gets
+    /// input index `i`.
+    pub fn build(inputs: &[Block], params: Parameters, hasher: H) -> Result<Self, Error> {
+        let mut table = Self::new(params, hasher);
+        for (i, input) in inputs.iter().enumerate() {
+            table.insert(*input, i)?;
+        }
+        Ok(table)
+    }
+
+    /// Insert `entry`, with input index `input_index`, into the table, moving
+    /// an item to the stash if no bin can be found for it. Fails if the stash
+    /// then holds more than `stash_size` items.
+    pub fn insert(&mut self, entry: Block, input_index: usize) -> Result<(), Error> {
+        let mut item = CuckooItem {
+            entry,
+            input_index,
+            hash_index: 0,
+        };
+        for _ in 0..MAX_ITERS {
+            let bin = self
+                .hasher
+                .bin(item.entry, item.hash_index, self.params.nbins);
+            match self.items[bin].replace(item) {
+                None => return Ok(()),
+                Some(evicted) => {
+                    // Place the evicted item using its next hash function.
+                    item = evicted;
+                    item.hash_index = (item.hash_index + 1) % self.params.nhashes;
+                }
+            }
+        }
+        self.stash.push(item);
+        if self.stash.len() > self.params.stash_size {
+            Err(Error::CuckooHashFull)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get the parameters of the table.
+    pub fn params(&self) -> Parameters {
+        self.params
+    }
+
+    /// Get the number of bins.
+    pub fn nbins(&self) -> usize {
+        self.params.nbins
+    }
+
+    /// Get the number of hash functions.
+    pub fn nhashes(&self) -> usize {
+        self.params.nhashes
+    }
+
+    /// Get the hash functions.
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+}
+
+/// A simple hash table, in which each item is placed in every bin given by
+/// its hash functions, as the counterpart of a `CuckooHash`.
+pub struct SimpleHash {
+    /// The bins of the table.
+    pub bins: Vec<Vec<CuckooItem>>,
+}
+
+impl SimpleHash {
+    /// Make a new, empty simple hash table with `nbins` bins.
+    pub fn new(nbins: usize) -> Self {
+        Self {
+            bins: vec![Vec::new(); nbins],
+        }
+    }
+
+    /// Build a simple hash table with `nbins` bins holding `inputs` under the
+    /// first `nhashes` hash functions of `hasher`, where the `i`th input gets
+    /// input index `i`.
+    pub fn build<H: BinHasher>(
+        inputs: &[Block],
+        nbins: usize,
+        nhashes: usize,
+        hasher: &H,
+    ) -> Self {
+        let mut table = Self::new(nbins);
+        for (i, input) in inputs.iter().enumerate() {
+            table.insert(hasher, nhashes, *input, i);
+        }
+        table
+    }
+
+    /// Insert `entry`, with input index `input_index`, into each bin given by
+    /// the first `nhashes` hash functions of `hasher`. If several hash
+    /// functions map `entry` to the same bin, it is only placed there once,
+    /// with the smallest such hash index.
+    pub fn insert<H: BinHasher>(
+        &mut self,
+        hasher: &H,
+        nhashes: usize,
+        entry: Block,
+        input_index: usize,
+    ) {
+        let nbins = self.bins.len();
+        let mut bins = Vec::with_capacity(nhashes);
+        for hash_index in 0..nhashes {
+            let bin = hasher.bin(entry, hash_index, nbins);
+            if !bins.contains(&bin) {
+                self.bins[bin].push(CuckooItem {
+                    entry,
+                    input_index,
+                    hash_index,
+                });
+                bins.push(bin);
+            }
+        }
+    }
+
+    /// Get the number of bins.
+    pub fn nbins(&self) -> usize {
+        self.bins.len()
+    }
+
+    /// Get the size of the largest bin.
+    pub fn max_bin_size(&self) -> usize {
+        self.bins.iter().map(Vec::len).max().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check<H: BinHasher>(inputs: &[Block], params: Parameters, hasher: H) {
+        let cuckoo = CuckooHash::build(inputs, params, hasher).unwrap();
+        let simple = SimpleHash::build(inputs, params.nbins, params.nhashes, cuckoo.hasher());
+        let mut seen = vec![false; inputs.len()];
+        // Each item is in the bin given by its hash index, and in the same bin
+        // of the simple hash table.
+        for (bin, opt_item) in cuckoo.items.iter().enumerate() {
+            if let Some(item) = opt_item {
+                assert_eq!(item.entry, inputs[item.input_index]);
+                assert_eq!(
+                    cuckoo.hasher().bin(item.entry, item.hash_index, params.nbins),
+                    bin
+                );
+                assert!(simple.bins[bin]
+                    .iter()
+                    .any(|x| x.input_index == item.input_index));
+                seen[item.input_index] = true;
+            }
+        }
+        for item in cuckoo.stash.iter() {
+            seen[item.input_index] = true;
+        }
+        assert!(cuckoo.stash.len() <= params.stash_size);
+        assert!(seen.into_iter().all(|x| x));
+    }
+
+    #[test]
+    fn test_cuckoo_hash() {
+        let inputs = (0..1 << 12)
+            .map(|_| rand::random::<Block>())
+            .collect::<Vec<Block>>();
+        let params = Parameters::with_statistical_security(inputs.len(), 3, 40).unwrap();
+        check(&inputs, params, BlockHasher);
+        let keys = rand::random::<[Block; 3]>();
+        check(&inputs, params, AesHasher::new(&keys));
+        let params = Parameters::with_statistical_security(inputs.len(), 4, 40).unwrap();
+        check(&inputs, params, BlockHasher);
+    }
+
+    #[test]
+    fn test_stash() {
+        // With as many items as bins, some end up in the stash.
+        let inputs = (0..64)
+            .map(|_| rand::random::<Block>())
+            .collect::<Vec<Block>>();
+        let keys = rand::random::<[Block; 2]>();
+        let params = Parameters::new(inputs.len(), 2, inputs.len());
+        let cuckoo = CuckooHash::build(&inputs, params, AesHasher::new(&keys)).unwrap();
+        assert!(!cuckoo.stash.is_empty());
+        check(&inputs, params, AesHasher::new(&keys));
+        let params = Parameters::new(inputs.len(), 2, 0);
+        assert!(CuckooHash::build(&inputs, params, AesHasher::new(&keys)).is_err());
+    }
+
+    #[test]
+    fn test_compute_nbins() {
+        assert!(compute_nbins(1 << 10, 2, 40).is_err());
+        // The parameters used by `popsicle`, with three hash functions.
+        assert_eq!(compute_nbins(1 << 8, 3, 40).unwrap(), 326);
+        assert_eq!(compute_nbins(1 << 12, 3, 40).unwrap(), 5202);
+        assert_eq!(compute_nbins(1 << 20, 3, 40).unwrap(), 1_331_692);
+        assert_eq!(compute_nbins(1 << 27, 3, 40).unwrap(), 217_432_720);
+        assert_eq!(compute_nbins(1 << 12, 4, 40).unwrap(), 4465);
+        assert_eq!(compute_nbins(1 << 12, 5, 40).unwrap(), 4301);
+        let mut prev = 0.0;
+        for logn in 4..28 {
+            let n = 1 << logn;
+            let e = compute_nbins(n, 6, 40).unwrap() as f64 / n as f64;
+            // The fit for three hash functions needs more than `1.09n` bins.
+            assert!(e > 1.09);
+            if logn > 12 {
+                // The stretch factor grows with the set size, beyond small
+                // sets.
+                assert!(e >= prev);
+            }
+            prev = e;
+            assert!(compute_nbins(n, 6, 80).unwrap() > compute_nbins(n, 6, 40).unwrap());
+        }
+    }
+}
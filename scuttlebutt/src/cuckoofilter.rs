@@ -0,0 +1,274 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Implementation of a cuckoo filter.
+
+use crate::cuckoo::Error;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+// The number of fingerprints stored per bucket.
+const BUCKET_SIZE: usize = 4;
+// The maximum number of evictions before an insertion fails.
+const MAX_KICKS: usize = 500;
+// The maximal load of the filter, for which insertion succeeds with high
+// probability when using buckets of size four.
+const LOAD_FACTOR: f64 = 0.95;
+
+/// Implementation of a cuckoo filter (cf. <https://doi.org/10.1145/2674005.2674994>).
+/// Like a bloom filter, it is guaranteed to return true if an element is in the set, but
+/// returns true with probability about `2 * 4 / 2^fpbits` if an item is not in the set.
+/// Each item takes up a little over `fpbits` bits of space. Does not reveal what is in
+/// the set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CuckooFilter {
+    // The fingerprints, `BUCKET_SIZE` per bucket, where zero denotes an empty
+    // slot.
+    slots: Vec<u64>,
+    nbuckets: usize,
+    fpbits: usize,
+    // A fingerprint that got evicted by a failed insertion, alongside its
+    // bucket.
+    victim: Option<(usize, u64)>,
+}
+
+impl CuckooFilter {
+    /// Create a new CuckooFilter which can support up to `n` insertions, using
+    /// fingerprints of `fpbits` bits. Fails if `fpbits` is zero or larger than
+    /// 64.
+    pub fn new(n: usize, fpbits: usize) -> Result<Self, Error> {
+        if fpbits == 0 || fpbits > 64 {
+            return Err(Error::InvalidFingerprintSize(fpbits));
+        }
+        let nbuckets = (n as f64 / (BUCKET_SIZE as f64 * LOAD_FACTOR)).ceil() as usize;
+        let nbuckets = nbuckets.max(1).next_power_of_two();
+        Ok(CuckooFilter {
+            slots: vec![0; nbuckets * BUCKET_SIZE],
+            nbuckets,
+            fpbits,
+            victim: None,
+        })
+    }
+
+    /// Compute the fingerprint size needed for false positive probability `p`.
+    pub fn compute_fpbits(p: f64) -> usize {
+        (-p.log2()).ceil() as usize + 3
+    }
+
+    /// Create a new CuckooFilter with false positive probability `p` which can
+    /// support up to `n` insertions.
+    pub fn with_false_positive_prob(p: f64, n: usize) -> Result<Self, Error> {
+        Self::new(n, Self::compute_fpbits(p).min(64))
+    }
+
+    /// Get the number of buckets in this CuckooFilter.
+    pub fn nbuckets(&self) -> usize {
+        self.nbuckets
+    }
+
+    /// Get the fingerprint size (in bits) of this CuckooFilter.
+    pub fn fpbits(&self) -> usize {
+        self.fpbits
+    }
+
+    // The number of bytes used to store a single fingerprint.
+    #[inline]
+    fn fpbytes(&self) -> usize {
+        self.fpbits.div_ceil(8)
+    }
+
+    /// Get the CuckooFilter packed in bytes.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let fpbytes = self.fpbytes();
+        let mut bytes = Vec::with_capacity((self.slots.len() + 1) * fpbytes + 8);
+        for fp in self.slots.iter() {
+            bytes.extend(&fp.to_le_bytes()[0..fpbytes]);
+        }
+        let (bucket, fp) = self.victim.unwrap_or((0, 0));
+        bytes.extend(&(bucket as u64).to_le_bytes());
+        bytes.extend(&fp.to_le_bytes()[0..fpbytes]);
+        bytes
+    }
+
+    /// Create a CuckooFilter from bytes, as output by `as_bytes`, where
+    /// `nbuckets` and `fpbits` are as given by the corresponding methods of the
+    /// original filter. Returns `None` if `bytes` is of the wrong length.
+    pub fn from_bytes(bytes: &[u8], nbuckets: usize, fpbits: usize) -> Option<Self> {
+        if fpbits == 0 || fpbits > 64 || !nbuckets.is_power_of_two() {
+            return None;
+        }
+        let fpbytes = fpbits.div_ceil(8);
+        if bytes.len() != (nbuckets * BUCKET_SIZE + 1) * fpbytes + 8 {
+            return None;
+        }
+        let read = |chunk: &[u8]| {
+            let mut fp = [0u8; 8];
+            fp[0..chunk.len()].copy_from_slice(chunk);
+            u64::from_le_bytes(fp)
+        };
+        let (slots, victim) = bytes.split_at(nbuckets * BUCKET_SIZE * fpbytes);
+        let slots = slots.chunks(fpbytes).map(read).collect();
+        let bucket = read(&victim[0..8]) as usize;
+        let fp = read(&victim[8..]);
+        if bucket >= nbuckets {
+            return None;
+        }
+        Some(CuckooFilter {
+            slots,
+            nbuckets,
+            fpbits,
+            victim: if fp == 0 { None } else { Some((bucket, fp)) },
+        })
+    }
+
+    // Compute the first bucket and the (nonzero) fingerprint of `value`.
+    #[inline]
+    fn hash<V: AsRef<[u8]>>(&self, value: &V) -> (usize, u64) {
+        let h = Sha256::digest(value.as_ref());
+        let mut index = [0u8; 8];
+        let mut fp = [0u8; 8];
+        index.copy_from_slice(&h[0..8]);
+        fp.copy_from_slice(&h[8..16]);
+        let index = u64::from_le_bytes(index) as usize & (self.nbuckets - 1);
+        let fp = u64::from_le_bytes(fp) >> (64 - self.fpbits);
+        (index, fp.max(1))
+    }
+
+    // Compute the alternate bucket of fingerprint `fp` stored in bucket
+    // `index`. This is an involution, so it maps either bucket to the other.
+    #[inline]
+    fn alt_index(&self, index: usize, fp: u64) -> usize {
+        // Mix the fingerprint using the `splitmix64` finalizer.
+        let mut h = fp.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        h ^= h >> 31;
+        index ^ (h as usize & (self.nbuckets - 1))
+    }
+
+    #[inline]
+    fn bucket(&self, index: usize) -> &[u64] {
+        &self.slots[index * BUCKET_SIZE..(index + 1) * BUCKET_SIZE]
+    }
+
+    #[inline]
+    fn bucket_mut(&mut self, index: usize) -> &mut [u64] {
+        &mut self.slots[index * BUCKET_SIZE..(index + 1) * BUCKET_SIZE]
+    }
+
+    // Try to put `fp` in an empty slot of bucket `index`.
+    #[inline]
+    fn put(&mut self, index: usize, fp: u64) -> bool {
+        match self.bucket_mut(index).iter_mut().find(|slot| **slot == 0) {
+            Some(slot) => {
+                *slot = fp;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Insert an item into the CuckooFilter, using `rng` to pick which
+    /// fingerprints to evict. Returns `false` if the filter is full, in which
+    /// case the item is not inserted.
+    pub fn insert<V: AsRef<[u8]>, R: Rng>(&mut self, value: &V, rng: &mut R) -> bool {
+        if self.victim.is_some() {
+            return false;
+        }
+        let (i1, fp) = self.hash(value);
+        let i2 = self.alt_index(i1, fp);
+        if self.put(i1, fp) || self.put(i2, fp) {
+            return true;
+        }
+        // Evict fingerprints until one fits into its alternate bucket.
+        let mut index = if rng.gen::<bool>() { i1 } else { i2 };
+        let mut fp = fp;
+        for _ in 0..MAX_KICKS {
+            let slot = rng.gen_range(0, BUCKET_SIZE);
+            std::mem::swap(&mut fp, &mut self.bucket_mut(index)[slot]);
+            index = self.alt_index(index, fp);
+            if self.put(index, fp) {
+                return true;
+            }
+        }
+        // Keep the last evicted fingerprint around, so that no previously
+        // inserted item is lost.
+        self.victim = Some((index, fp));
+        true
+    }
+
+    /// Check whether an item exists in the CuckooFilter.
+    pub fn contains<V: AsRef<[u8]>>(&self, value: &V) -> bool {
+        let (i1, fp) = self.hash(value);
+        let i2 = self.alt_index(i1, fp);
+        let victim = match self.victim {
+            Some((index, fp_)) => fp == fp_ && (index == i1 || index == i2),
+            None => false,
+        };
+        victim || self.bucket(i1).contains(&fp) || self.bucket(i2).contains(&fp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AesRng, Block};
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_cuckoo_filter_membership() {
+        let mut rng = AesRng::new();
+        let n = 10000;
+        let fpbits = 20;
+        let mut filter = CuckooFilter::new(n, fpbits).unwrap();
+        let xs = (0..n).map(|_| rng.gen::<Block>()).collect::<Vec<Block>>();
+        for x in xs.iter() {
+            assert!(filter.insert(x, &mut rng));
+        }
+        for x in xs.iter() {
+            assert!(filter.contains(x));
+        }
+        let nfalse = (0..n)
+            .filter(|_| filter.contains(&rng.gen::<Block>()))
+            .count();
+        // The expected number of false positives is `8 * n / 2^20 < 1`.
+        assert!(nfalse < 10);
+        assert_eq!(
+            filter,
+            CuckooFilter::from_bytes(&filter.as_bytes(), filter.nbuckets(), fpbits).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cuckoo_filter_full() {
+        let mut rng = AesRng::new();
+        let mut filter = CuckooFilter::new(100, 8).unwrap();
+        let xs = (0..1000).map(|_| rng.gen::<Block>()).collect::<Vec<Block>>();
+        let ninserted = xs.iter().take_while(|x| filter.insert(*x, &mut rng)).count();
+        assert!(ninserted < xs.len());
+        for x in xs[0..ninserted].iter() {
+            assert!(filter.contains(x));
+        }
+    }
+
+    #[test]
+    fn test_cuckoo_filter_params() {
+        assert!(CuckooFilter::new(100, 0).is_err());
+        assert!(CuckooFilter::new(100, 65).is_err());
+        // Filters built with the same randomness are identical.
+        let xs = (0..1000).map(|_| rand::random::<Block>()).collect::<Vec<Block>>();
+        let build = |seed: Block| {
+            let mut rng = AesRng::from_seed(seed);
+            let mut filter = CuckooFilter::new(xs.len(), 16).unwrap();
+            for x in xs.iter() {
+                assert!(filter.insert(x, &mut rng));
+            }
+            filter
+        };
+        let seed = rand::random::<Block>();
+        assert_eq!(build(seed), build(seed));
+    }
+}
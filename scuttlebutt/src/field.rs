@@ -0,0 +1,286 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Finite field arithmetic.
+//!
+//! The `FiniteField` trait is implemented by `Gf128`, the binary field
+//! `GF(2^128)`, and by `F61p`, the prime field of integers modulo the Mersenne
+//! prime `2^61 - 1`. The module also provides batch inversion and polynomial
+//! evaluation and interpolation over any `FiniteField`.
+//!
+//! # Usage
+//! ```rust
+//! use scuttlebutt::{
+//!     field::{evaluate, interpolate, F61p, FiniteField},
+//!     AesRng,
+//! };
+//!
+//! let mut rng = AesRng::new();
+//! let x = F61p::random(&mut rng);
+//! assert_eq!(x * x.inverse().unwrap(), F61p::ONE);
+//!
+//! // interpolate the polynomial through three points
+//! let points = [
+//!     (F61p::from(1), F61p::from(2)),
+//!     (F61p::from(2), F61p::from(3)),
+//!     (F61p::from(3), F61p::from(4)),
+//! ];
+//! let coeffs = interpolate(&points).unwrap();
+//! assert_eq!(evaluate(&coeffs, F61p::from(4)), F61p::from(5));
+//! ```
+
+// Implement the compound assignment operators in terms of the binary ones.
+macro_rules! impl_assign_ops {
+    ($t:ty) => {
+        impl std::ops::AddAssign for $t {
+            #[inline]
+            fn add_assign(&mut self, rhs: Self) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl std::ops::SubAssign for $t {
+            #[inline]
+            fn sub_assign(&mut self, rhs: Self) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl std::ops::MulAssign for $t {
+            #[inline]
+            fn mul_assign(&mut self, rhs: Self) {
+                *self = *self * rhs;
+            }
+        }
+    };
+}
+
+mod f61p;
+mod gf128;
+
+pub use f61p::F61p;
+pub use gf128::Gf128;
+
+use rand_core::RngCore;
+use std::{
+    fmt::Debug,
+    hash::Hash,
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+/// A finite field.
+pub trait FiniteField:
+    Copy
+    + Clone
+    + Debug
+    + Default
+    + Eq
+    + Hash
+    + Send
+    + Sync
+    + Add<Output = Self>
+    + AddAssign
+    + Sub<Output = Self>
+    + SubAssign
+    + Mul<Output = Self>
+    + MulAssign
+    + Neg<Output = Self>
+    + 'static
+{
+    /// The additive identity.
+    const ZERO: Self;
+    /// The multiplicative identity.
+    const ONE: Self;
+    /// The number of bytes in the serialization of a field element.
+    const BYTE_LENGTH: usize;
+
+    /// Generate a uniformly random field element.
+    fn random<R: RngCore + ?Sized>(rng: &mut R) -> Self;
+
+    /// Compute the multiplicative inverse, or `None` if `self` is zero.
+    fn inverse(&self) -> Option<Self>;
+
+    /// Serialize the field element into `BYTE_LENGTH` bytes.
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Deserialize a field element, returning `None` if `bytes` is not the
+    /// serialization of a field element.
+    fn from_bytes(bytes: &[u8]) -> Option<Self>;
+
+    /// Raise `self` to the power `exp`.
+    #[inline]
+    fn pow(&self, exp: u128) -> Self {
+        let mut result = Self::ONE;
+        for i in (0..128 - exp.leading_zeros()).rev() {
+            result *= result;
+            if (exp >> i) & 1 == 1 {
+                result *= *self;
+            }
+        }
+        result
+    }
+}
+
+/// Invert all elements of `xs` using a single field inversion, returning
+/// `None` if any element is zero.
+pub fn batch_inverse<F: FiniteField>(xs: &[F]) -> Option<Vec<F>> {
+    // Compute the prefix products `x₀ ⋯ xᵢ`, invert their product, and then
+    // peel off one element at a time.
+    let mut products = Vec::with_capacity(xs.len());
+    let mut product = F::ONE;
+    for x in xs.iter() {
+        product *= *x;
+        products.push(product);
+    }
+    let mut inverse = product.inverse()?;
+    let mut inverses = vec![F::ZERO; xs.len()];
+    for i in (0..xs.len()).rev() {
+        inverses[i] = if i > 0 {
+            inverse * products[i - 1]
+        } else {
+            inverse
+        };
+        inverse *= xs[i];
+    }
+    Some(inverses)
+}
+
+/// Evaluate the polynomial with coefficients `coeffs` (lowest degree first)
+/// at `x` using Horner's rule.
+#[inline]
+pub fn evaluate<F: FiniteField>(coeffs: &[F], x: F) -> F {
+    coeffs.iter().rev().fold(F::ZERO, |acc, c| acc * x + *c)
+}
+
+/// Compute the Lagrange basis polynomials for the points `xs`, returning
+/// `None` if the points are not distinct. The `i`th entry contains the
+/// coefficients (lowest degree first) of the polynomial that is one on `xs[i]`
+/// and zero on all other entries of `xs`.
+///
+/// This takes quadratic time, and is worth computing once when interpolating
+/// many polynomials on the same points.
+pub fn lagrange_basis<F: FiniteField>(xs: &[F]) -> Option<Vec<Vec<F>>> {
+    let n = xs.len();
+    if n == 0 {
+        return Some(vec![]);
+    }
+    // Compute `M(X) = ∏ (X - xᵢ)`.
+    let mut m = vec![F::ZERO; n + 1];
+    m[0] = F::ONE;
+    for (i, x) in xs.iter().enumerate() {
+        for j in (1..=i + 1).rev() {
+            m[j] = m[j - 1] - *x * m[j];
+        }
+        m[0] = -(*x * m[0]);
+    }
+    // Compute `M(X) / (X - xᵢ)` using synthetic division, along with its value
+    // at `xᵢ`, which is zero iff `xᵢ` is a repeated point.
+    let (quotients, denominators): (Vec<Vec<F>>, Vec<F>) = xs
+        .iter()
+        .map(|x| {
+            let mut q = vec![F::ZERO; n];
+            q[n - 1] = m[n];
+            for j in (1..n).rev() {
+                q[j - 1] = m[j] + *x * q[j];
+            }
+            let d = evaluate(&q, *x);
+            (q, d)
+        })
+        .unzip();
+    let denominators = batch_inverse(&denominators)?;
+    Some(
+        quotients
+            .into_iter()
+            .zip(denominators)
+            .map(|(q, d)| q.into_iter().map(|c| c * d).collect())
+            .collect(),
+    )
+}
+
+/// Compute the coefficients (lowest degree first) of the polynomial of degree
+/// less than `points.len()` passing through `points`, returning `None` if the
+/// `x` coordinates are not distinct.
+pub fn interpolate<F: FiniteField>(points: &[(F, F)]) -> Option<Vec<F>> {
+    let xs = points.iter().map(|(x, _)| *x).collect::<Vec<F>>();
+    let basis = lagrange_basis(&xs)?;
+    let mut coeffs = vec![F::ZERO; points.len()];
+    for ((_, y), ls) in points.iter().zip(basis.iter()) {
+        for (c, l) in coeffs.iter_mut().zip(ls.iter()) {
+            *c += *l * *y;
+        }
+    }
+    Some(coeffs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AesRng;
+
+    fn test_field<F: FiniteField>() {
+        let mut rng = AesRng::new();
+        for _ in 0..16 {
+            let (x, y, z) = (F::random(&mut rng), F::random(&mut rng), F::random(&mut rng));
+            assert_eq!(x + F::ZERO, x);
+            assert_eq!(x * F::ONE, x);
+            assert_eq!(x + y, y + x);
+            assert_eq!(x * y, y * x);
+            assert_eq!((x * y) * z, x * (y * z));
+            assert_eq!(x * (y + z), x * y + x * z);
+            assert_eq!(x + y - y, x);
+            assert_eq!(x + (-x), F::ZERO);
+            assert_eq!(x * x.inverse().unwrap(), F::ONE);
+            assert_eq!(x.pow(3), x * x * x);
+            assert_eq!(x.pow(0), F::ONE);
+            let bytes = x.to_bytes();
+            assert_eq!(bytes.len(), F::BYTE_LENGTH);
+            assert_eq!(F::from_bytes(&bytes), Some(x));
+        }
+        assert_eq!(F::ZERO.inverse(), None);
+        assert_eq!(F::from_bytes(&[]), None);
+    }
+
+    fn test_polynomials<F: FiniteField>() {
+        let mut rng = AesRng::new();
+        let xs = (0..20).map(|_| F::random(&mut rng)).collect::<Vec<F>>();
+        let inverses = batch_inverse(&xs).unwrap();
+        for (x, inverse) in xs.iter().zip(inverses.iter()) {
+            assert_eq!(*x * *inverse, F::ONE);
+        }
+        assert_eq!(batch_inverse(&[xs[0], F::ZERO]), None);
+
+        let basis = lagrange_basis(&xs).unwrap();
+        for (i, ls) in basis.iter().enumerate() {
+            for (j, x) in xs.iter().enumerate() {
+                let expected = if i == j { F::ONE } else { F::ZERO };
+                assert_eq!(evaluate(ls, *x), expected);
+            }
+        }
+        assert_eq!(lagrange_basis(&[xs[0], xs[1], xs[0]]), None);
+
+        let points = xs
+            .iter()
+            .map(|x| (*x, F::random(&mut rng)))
+            .collect::<Vec<_>>();
+        let coeffs = interpolate(&points).unwrap();
+        assert_eq!(coeffs.len(), points.len());
+        for (x, y) in points.iter() {
+            assert_eq!(evaluate(&coeffs, *x), *y);
+        }
+    }
+
+    #[test]
+    fn test_gf128() {
+        test_field::<Gf128>();
+        test_polynomials::<Gf128>();
+    }
+
+    #[test]
+    fn test_f61p() {
+        test_field::<F61p>();
+        test_polynomials::<F61p>();
+    }
+}
@@ -0,0 +1,154 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+use crate::field::FiniteField;
+use rand_core::RngCore;
+use std::ops::{Add, Mul, Neg, Sub};
+
+// The modulus, `2^61 - 1`.
+const MODULUS: u64 = (1 << 61) - 1;
+
+/// An element of the prime field of integers modulo the Mersenne prime `2^61 -
+/// 1`, stored in canonical form, i.e., as an integer less than the modulus.
+///
+/// Reduction modulo a Mersenne prime only needs shifts and additions, which
+/// makes this field fast to compute with on 64-bit machines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct F61p(u64);
+
+impl F61p {
+    /// The modulus, `2^61 - 1`.
+    pub const MODULUS: u64 = MODULUS;
+
+    // Reduce `x < 2^62` into canonical form.
+    #[inline]
+    fn reduce(x: u64) -> Self {
+        let x = (x & MODULUS) + (x >> 61);
+        F61p(if x >= MODULUS { x - MODULUS } else { x })
+    }
+}
+
+impl FiniteField for F61p {
+    const ZERO: Self = F61p(0);
+    const ONE: Self = F61p(1);
+    const BYTE_LENGTH: usize = 8;
+
+    /// Sample by rejection, which succeeds on the first try with overwhelming
+    /// probability.
+    #[inline]
+    fn random<R: RngCore + ?Sized>(rng: &mut R) -> Self {
+        loop {
+            let x = rng.next_u64() & MODULUS;
+            if x < MODULUS {
+                return F61p(x);
+            }
+        }
+    }
+
+    /// Compute the inverse as `self^(p - 2)`.
+    #[inline]
+    fn inverse(&self) -> Option<Self> {
+        if *self == Self::ZERO {
+            None
+        } else {
+            Some(self.pow(u128::from(MODULUS - 2)))
+        }
+    }
+
+    #[inline]
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_le_bytes().to_vec()
+    }
+
+    #[inline]
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut bytes_ = [0u8; 8];
+        if bytes.len() != bytes_.len() {
+            return None;
+        }
+        bytes_.copy_from_slice(bytes);
+        let x = u64::from_le_bytes(bytes_);
+        if x < MODULUS {
+            Some(F61p(x))
+        } else {
+            None
+        }
+    }
+}
+
+impl Add for F61p {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self::reduce(self.0 + rhs.0)
+    }
+}
+
+impl Sub for F61p {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self::reduce(self.0 + (MODULUS - rhs.0))
+    }
+}
+
+impl Neg for F61p {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self::reduce(MODULUS - self.0)
+    }
+}
+
+impl Mul for F61p {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        // The product is less than `2^122`, so folding the high bits onto the
+        // low bits once leaves something less than `2^62`.
+        let x = u128::from(self.0) * u128::from(rhs.0);
+        let x = (x as u64 & MODULUS) + (x >> 61) as u64;
+        Self::reduce(x)
+    }
+}
+
+impl_assign_ops!(F61p);
+
+impl From<u64> for F61p {
+    /// Reduce `x` modulo `2^61 - 1`.
+    #[inline]
+    fn from(x: u64) -> Self {
+        Self::reduce((x & MODULUS) + (x >> 61))
+    }
+}
+
+impl From<F61p> for u64 {
+    #[inline]
+    fn from(x: F61p) -> Self {
+        x.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduction() {
+        assert_eq!(F61p::from(MODULUS), F61p::ZERO);
+        assert_eq!(F61p::from(u64::MAX), F61p::from(u64::MAX % MODULUS));
+        assert_eq!(-F61p::ONE, F61p::from(MODULUS - 1));
+        assert_eq!(F61p::from(MODULUS - 1) + F61p::from(2), F61p::ONE);
+        assert_eq!(F61p::ZERO - F61p::ONE, F61p::from(MODULUS - 1));
+        let x = F61p::from(MODULUS - 1);
+        assert_eq!(x * x, F61p::ONE);
+        assert_eq!(F61p::from_bytes(&MODULUS.to_le_bytes()), None);
+    }
+}
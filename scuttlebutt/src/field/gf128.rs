@@ -0,0 +1,163 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+use crate::{field::FiniteField, Block};
+use rand_core::RngCore;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// An element of `GF(2^128)`, using the reduction polynomial `X^128 + X^7 +
+/// X^2 + X + 1`. Bit `i` of the underlying `u128` is the coefficient of `X^i`.
+///
+/// Addition is XOR, and multiplication uses carry-less multiplication
+/// (PCLMULQDQ) followed by a reduction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Gf128(u128);
+
+impl Gf128 {
+    /// Multiply by `X`, which is cheaper than a general multiplication.
+    #[inline]
+    pub fn mul_x(self) -> Self {
+        Gf128((self.0 << 1) ^ ((self.0 >> 127) * 0x87))
+    }
+}
+
+impl FiniteField for Gf128 {
+    const ZERO: Self = Gf128(0);
+    const ONE: Self = Gf128(1);
+    const BYTE_LENGTH: usize = 16;
+
+    #[inline]
+    fn random<R: RngCore + ?Sized>(rng: &mut R) -> Self {
+        let mut bytes = [0u8; 16];
+        rng.fill_bytes(&mut bytes);
+        Gf128(u128::from_le_bytes(bytes))
+    }
+
+    /// Compute the inverse as `self^(2^128 - 2)`.
+    #[inline]
+    fn inverse(&self) -> Option<Self> {
+        if *self == Self::ZERO {
+            None
+        } else {
+            Some(self.pow(u128::MAX - 1))
+        }
+    }
+
+    #[inline]
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_le_bytes().to_vec()
+    }
+
+    #[inline]
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut bytes_ = [0u8; 16];
+        if bytes.len() != bytes_.len() {
+            return None;
+        }
+        bytes_.copy_from_slice(bytes);
+        Some(Gf128(u128::from_le_bytes(bytes_)))
+    }
+}
+
+impl Add for Gf128 {
+    type Output = Self;
+
+    #[inline]
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, rhs: Self) -> Self {
+        Gf128(self.0 ^ rhs.0)
+    }
+}
+
+impl Sub for Gf128 {
+    type Output = Self;
+
+    #[inline]
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, rhs: Self) -> Self {
+        Gf128(self.0 ^ rhs.0)
+    }
+}
+
+impl Neg for Gf128 {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        self
+    }
+}
+
+impl Mul for Gf128 {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        let (lo, hi) = Block::from(self.0).clmul(Block::from(rhs.0));
+        let (lo, hi) = (u128::from(lo), u128::from(hi));
+        // Reduce `hi ⋅ X^128` using `X^128 = X^7 + X^2 + X + 1`. The bits that
+        // overflow get reduced a second time, which always fits in 128 bits.
+        let overflow = (hi >> 127) ^ (hi >> 126) ^ (hi >> 121);
+        let hi = hi ^ (hi << 1) ^ (hi << 2) ^ (hi << 7);
+        let overflow = overflow ^ (overflow << 1) ^ (overflow << 2) ^ (overflow << 7);
+        Gf128(lo ^ hi ^ overflow)
+    }
+}
+
+impl_assign_ops!(Gf128);
+
+impl From<u128> for Gf128 {
+    #[inline]
+    fn from(x: u128) -> Self {
+        Gf128(x)
+    }
+}
+
+impl From<u64> for Gf128 {
+    #[inline]
+    fn from(x: u64) -> Self {
+        Gf128(u128::from(x))
+    }
+}
+
+impl From<Gf128> for u128 {
+    #[inline]
+    fn from(x: Gf128) -> Self {
+        x.0
+    }
+}
+
+impl From<Block> for Gf128 {
+    #[inline]
+    fn from(x: Block) -> Self {
+        Gf128(u128::from(x))
+    }
+}
+
+impl From<Gf128> for Block {
+    #[inline]
+    fn from(x: Gf128) -> Self {
+        Block::from(x.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AesRng;
+
+    #[test]
+    fn test_mul() {
+        // `X^127 ⋅ X = X^128 = X^7 + X^2 + X + 1`.
+        let x = Gf128::from(2u128);
+        assert_eq!(Gf128::from(1u128 << 127) * x, Gf128::from(0x87u128));
+        let mut rng = AesRng::new();
+        for _ in 0..16 {
+            let y = Gf128::random(&mut rng);
+            assert_eq!(y.mul_x(), y * x);
+        }
+    }
+}
@@ -8,6 +8,7 @@
 //! based on fixed-key AES.
 
 use crate::{Aes128, Block, FIXED_KEY_AES128};
+#[cfg(not(feature = "portable"))]
 use core::arch::x86_64::*;
 
 /// AES-based correlation-robust hash function.
@@ -45,6 +46,7 @@ impl AesHash {
     ///
     /// The function computes `H(σ(x))`, where `H` is a correlation-robust hash
     /// function and `σ(x₀ || x₁) = (x₀ ⊕ x₁) || x₀`.
+    #[cfg(not(feature = "portable"))]
     #[inline]
     pub fn ccr_hash(&self, i: Block, x: Block) -> Block {
         unsafe {
@@ -57,6 +59,19 @@ impl AesHash {
         }
     }
 
+    /// Circular correlation-robust hash function (cf.
+    /// <https://eprint.iacr.org/2019/074>, §7.3).
+    ///
+    /// The function computes `H(σ(x))`, where `H` is a correlation-robust hash
+    /// function and `σ(x₀ || x₁) = (x₀ ⊕ x₁) || x₀`.
+    #[cfg(feature = "portable")]
+    #[inline]
+    pub fn ccr_hash(&self, i: Block, x: Block) -> Block {
+        let x = u128::from(x);
+        let x = x.rotate_left(64) ^ (x & (u128::from(u64::MAX) << 64));
+        self.cr_hash(i, Block::from(x))
+    }
+
     /// Tweakable circular correlation robust hash function (cf.
     /// <https://eprint.iacr.org/2019/074>, §7.4).
     ///
@@ -69,3 +84,20 @@ impl AesHash {
         y ^ z
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ccr_hash() {
+        let i = rand::random::<Block>();
+        let x = rand::random::<u128>();
+        let (x0, x1) = (x as u64, (x >> 64) as u64);
+        let sigma = (u128::from(x0 ^ x1) << 64) | u128::from(x1);
+        assert_eq!(
+            AES_HASH.ccr_hash(i, Block::from(x)),
+            AES_HASH.cr_hash(i, Block::from(sigma))
+        );
+    }
+}
@@ -21,21 +21,40 @@ pub mod bloomfilter;
 pub mod channel;
 pub mod cointoss;
 pub mod commitment;
+pub mod cuckoo;
+pub mod cuckoofilter;
+pub mod field;
 mod hash_aes;
 mod rand_aes;
+pub mod sharing;
+mod transcript;
 pub mod utils;
 mod universal_digest;
 
 pub use crate::{
-    aes::{
-        aes128::{Aes128, FIXED_KEY_AES128},
-        aes256::Aes256,
-    },
+    aes::{Aes128, Aes256, FIXED_KEY_AES128},
     block::Block,
     block512::Block512,
-    channel::{AbstractChannel, Channel, HashChannel, TrackChannel},
+    channel::{
+        multiplex,
+        simulated_channel_pair,
+        AbstractChannel,
+        Channel,
+        HashChannel,
+        MuxChannel,
+        Network,
+        PartyId,
+        Recording,
+        RecordingChannel,
+        ReplayChannel,
+        SimulatedChannel,
+        TcpChannel,
+        TrackChannel,
+        TranscriptChannel,
+    },
     hash_aes::{AesHash, AES_HASH},
     rand_aes::AesRng,
+    transcript::Transcript,
     universal_digest::UniversalDigest,
 };
 
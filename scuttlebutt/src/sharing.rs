@@ -0,0 +1,141 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Secret sharing.
+//!
+//! The `additive` module implements `n`-out-of-`n` additive secret sharing
+//! over any `Shareable` group: XOR sharing over `Block` and `Block512`,
+//! sharing over `Z_{2^k}` for `k ∈ {8, 16, 32, 64, 128}` using wrapping
+//! arithmetic on the unsigned integer types, and sharing over a
+//! `FiniteField`, as well as over `Z_q` for arbitrary moduli `q`. The `shamir`
+//! module implements Shamir `t`-out-of-`n` secret sharing over a
+//! `FiniteField`.
+//!
+//! Each scheme provides functions to share and reconstruct a secret, to create
+//! a sharing of zero, and to re-randomize a sharing.
+//!
+//! # Usage
+//! ```rust
+//! use scuttlebutt::{
+//!     field::{F61p, FiniteField},
+//!     sharing::{additive, shamir},
+//!     AesRng,
+//!     Block,
+//! };
+//!
+//! let mut rng = AesRng::new();
+//!
+//! // XOR-share a block among three parties
+//! let secret = Block::from(42u128);
+//! let shares = additive::share(secret, 3, &mut rng);
+//! assert_eq!(additive::reconstruct(&shares), secret);
+//!
+//! // share a field element such that any two of three parties can recover it
+//! let secret = F61p::from(42);
+//! let shares = shamir::share(secret, 2, 3, &mut rng);
+//! assert_eq!(shamir::reconstruct(&[(0, shares[0]), (2, shares[2])]), Some(secret));
+//! ```
+
+pub mod additive;
+pub mod shamir;
+
+use crate::{field::FiniteField, Block, Block512};
+use rand::{CryptoRng, Rng};
+
+/// A group over which values can be additively secret shared.
+pub trait Shareable: Copy + Eq {
+    /// The identity element.
+    fn zero() -> Self;
+    /// The group operation.
+    fn add(self, other: Self) -> Self;
+    /// The group operation with the inverse of `other`.
+    fn sub(self, other: Self) -> Self;
+    /// Generate a uniformly random group element.
+    fn random<R: CryptoRng + Rng>(rng: &mut R) -> Self;
+}
+
+macro_rules! impl_shareable_xor {
+    ($t:ty) => {
+        impl Shareable for $t {
+            #[inline]
+            fn zero() -> Self {
+                <$t>::default()
+            }
+
+            #[inline]
+            fn add(self, other: Self) -> Self {
+                self ^ other
+            }
+
+            #[inline]
+            fn sub(self, other: Self) -> Self {
+                self ^ other
+            }
+
+            #[inline]
+            fn random<R: CryptoRng + Rng>(rng: &mut R) -> Self {
+                rng.gen()
+            }
+        }
+    };
+}
+
+impl_shareable_xor!(Block);
+impl_shareable_xor!(Block512);
+
+macro_rules! impl_shareable_wrapping {
+    ($t:ty) => {
+        impl Shareable for $t {
+            #[inline]
+            fn zero() -> Self {
+                0
+            }
+
+            #[inline]
+            fn add(self, other: Self) -> Self {
+                self.wrapping_add(other)
+            }
+
+            #[inline]
+            fn sub(self, other: Self) -> Self {
+                self.wrapping_sub(other)
+            }
+
+            #[inline]
+            fn random<R: CryptoRng + Rng>(rng: &mut R) -> Self {
+                rng.gen()
+            }
+        }
+    };
+}
+
+impl_shareable_wrapping!(u8);
+impl_shareable_wrapping!(u16);
+impl_shareable_wrapping!(u32);
+impl_shareable_wrapping!(u64);
+impl_shareable_wrapping!(u128);
+
+impl<F: FiniteField> Shareable for F {
+    #[inline]
+    fn zero() -> Self {
+        F::ZERO
+    }
+
+    #[inline]
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    #[inline]
+    fn sub(self, other: Self) -> Self {
+        self - other
+    }
+
+    #[inline]
+    fn random<R: CryptoRng + Rng>(rng: &mut R) -> Self {
+        F::random(rng)
+    }
+}
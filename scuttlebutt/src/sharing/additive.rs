@@ -0,0 +1,159 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Additive `n`-out-of-`n` secret sharing, where the shares sum to the secret.
+//! Over `Block` and `Block512` this is XOR sharing.
+
+use crate::sharing::Shareable;
+use rand::{CryptoRng, Rng};
+
+/// Share `secret` among `nparties` parties.
+///
+/// # Panics
+/// If `nparties` is zero.
+#[inline]
+pub fn share<T: Shareable, R: CryptoRng + Rng>(secret: T, nparties: usize, rng: &mut R) -> Vec<T> {
+    assert!(nparties > 0, "cannot share among zero parties");
+    let mut last = secret;
+    let mut shares = (0..nparties - 1)
+        .map(|_| {
+            let share = T::random(rng);
+            last = last.sub(share);
+            share
+        })
+        .collect::<Vec<T>>();
+    shares.push(last);
+    shares
+}
+
+/// Reconstruct a secret from all of its `shares`.
+#[inline]
+pub fn reconstruct<T: Shareable>(shares: &[T]) -> T {
+    shares.iter().fold(T::zero(), |acc, share| acc.add(*share))
+}
+
+/// Create a sharing of zero among `nparties` parties.
+#[inline]
+pub fn zero_sharing<T: Shareable, R: CryptoRng + Rng>(nparties: usize, rng: &mut R) -> Vec<T> {
+    share(T::zero(), nparties, rng)
+}
+
+/// Re-randomize `shares` by adding a fresh sharing of zero, leaving the secret
+/// unchanged.
+#[inline]
+pub fn rerandomize<T: Shareable, R: CryptoRng + Rng>(shares: &mut [T], rng: &mut R) {
+    let zeros = zero_sharing(shares.len(), rng);
+    for (share, zero) in shares.iter_mut().zip(zeros) {
+        *share = share.add(zero);
+    }
+}
+
+// Compute `(x + y) mod modulus` without overflowing.
+#[inline]
+fn add_mod(x: u64, y: u64, modulus: u64) -> u64 {
+    ((u128::from(x) + u128::from(y)) % u128::from(modulus)) as u64
+}
+
+/// Share `secret < modulus` among `nparties` parties over `Z_modulus`.
+///
+/// # Panics
+/// If `nparties` or `modulus` is zero.
+#[inline]
+pub fn share_mod<R: CryptoRng + Rng>(
+    secret: u64,
+    modulus: u64,
+    nparties: usize,
+    rng: &mut R,
+) -> Vec<u64> {
+    assert!(nparties > 0, "cannot share among zero parties");
+    let mut last = secret % modulus;
+    let mut shares = (0..nparties - 1)
+        .map(|_| {
+            let share = rng.gen_range(0, modulus);
+            last = add_mod(last, modulus - share, modulus);
+            share
+        })
+        .collect::<Vec<u64>>();
+    shares.push(last);
+    shares
+}
+
+/// Reconstruct a secret over `Z_modulus` from all of its `shares`.
+#[inline]
+pub fn reconstruct_mod(shares: &[u64], modulus: u64) -> u64 {
+    shares
+        .iter()
+        .fold(0, |acc, share| add_mod(acc, *share, modulus))
+}
+
+/// Create a sharing of zero over `Z_modulus` among `nparties` parties.
+#[inline]
+pub fn zero_sharing_mod<R: CryptoRng + Rng>(
+    modulus: u64,
+    nparties: usize,
+    rng: &mut R,
+) -> Vec<u64> {
+    share_mod(0, modulus, nparties, rng)
+}
+
+/// Re-randomize `shares` over `Z_modulus` by adding a fresh sharing of zero,
+/// leaving the secret unchanged.
+#[inline]
+pub fn rerandomize_mod<R: CryptoRng + Rng>(shares: &mut [u64], modulus: u64, rng: &mut R) {
+    let zeros = zero_sharing_mod(modulus, shares.len(), rng);
+    for (share, zero) in shares.iter_mut().zip(zeros) {
+        *share = add_mod(*share, zero, modulus);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{field::F61p, AesRng, Block, Block512};
+
+    fn test_sharing<T: Shareable + std::fmt::Debug>(secret: T) {
+        let mut rng = AesRng::new();
+        for nparties in 1..6 {
+            let mut shares = share(secret, nparties, &mut rng);
+            assert_eq!(shares.len(), nparties);
+            assert_eq!(reconstruct(&shares), secret);
+            let shares_ = shares.clone();
+            rerandomize(&mut shares, &mut rng);
+            assert_eq!(reconstruct(&shares), secret);
+            if nparties > 1 {
+                assert_ne!(shares, shares_);
+            }
+            let zeros = zero_sharing::<T, _>(nparties, &mut rng);
+            assert_eq!(reconstruct(&zeros), T::zero());
+        }
+    }
+
+    #[test]
+    fn test_additive() {
+        let mut rng = AesRng::new();
+        test_sharing(rng.gen::<Block>());
+        test_sharing(rng.gen::<Block512>());
+        test_sharing(rng.gen::<u16>());
+        test_sharing(rng.gen::<u64>());
+        test_sharing(F61p::from(rng.gen::<u64>()));
+    }
+
+    #[test]
+    fn test_additive_mod() {
+        let mut rng = AesRng::new();
+        for modulus in [2, 3, 5, 17, 256, u64::MAX].iter() {
+            let secret = rng.gen_range(0, modulus);
+            let mut shares = share_mod(secret, *modulus, 3, &mut rng);
+            assert!(shares.iter().all(|share| share < modulus));
+            assert_eq!(reconstruct_mod(&shares, *modulus), secret);
+            rerandomize_mod(&mut shares, *modulus, &mut rng);
+            assert!(shares.iter().all(|share| share < modulus));
+            assert_eq!(reconstruct_mod(&shares, *modulus), secret);
+            let zeros = zero_sharing_mod(*modulus, 4, &mut rng);
+            assert_eq!(reconstruct_mod(&zeros, *modulus), 0);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Shamir `t`-out-of-`n` secret sharing over a `FiniteField`, where any
+//! `threshold` shares determine the secret and fewer reveal nothing about it.
+//!
+//! The secret is the constant term of a random polynomial of degree
+//! `threshold - 1`, and the share of party `i ∈ {0, …, n - 1}` is its
+//! evaluation at `i + 1`.
+
+use crate::field::{batch_inverse, evaluate, FiniteField};
+use rand::{CryptoRng, Rng};
+
+// The evaluation point of the share of party `id`.
+#[inline]
+fn point<F: FiniteField + From<u64>>(id: usize) -> F {
+    F::from(id as u64 + 1)
+}
+
+/// Share `secret` among `nparties` parties, such that any `threshold` of them
+/// can reconstruct it.
+///
+/// # Panics
+/// If `threshold` is zero or larger than `nparties`.
+pub fn share<F: FiniteField + From<u64>, R: CryptoRng + Rng>(
+    secret: F,
+    threshold: usize,
+    nparties: usize,
+    rng: &mut R,
+) -> Vec<F> {
+    assert!(
+        threshold > 0 && threshold <= nparties,
+        "threshold must be between 1 and the number of parties"
+    );
+    let mut coeffs = Vec::with_capacity(threshold);
+    coeffs.push(secret);
+    coeffs.extend((1..threshold).map(|_| F::random(rng)));
+    (0..nparties)
+        .map(|id| evaluate(&coeffs, point(id)))
+        .collect()
+}
+
+/// Reconstruct a secret from `shares`, given as pairs of a party's index and
+/// its share, using Lagrange interpolation. At least `threshold` shares are
+/// needed to get the right result. Returns `None` if an index appears more
+/// than once.
+pub fn reconstruct<F: FiniteField + From<u64>>(shares: &[(usize, F)]) -> Option<F> {
+    let xs = shares
+        .iter()
+        .map(|(id, _)| point::<F>(*id))
+        .collect::<Vec<F>>();
+    // The Lagrange coefficient of `xᵢ` at zero is `∏_{j ≠ i} xⱼ / (xⱼ - xᵢ)`.
+    let mut numerators = Vec::with_capacity(xs.len());
+    let mut denominators = Vec::with_capacity(xs.len());
+    for (i, xi) in xs.iter().enumerate() {
+        let mut numerator = F::ONE;
+        let mut denominator = F::ONE;
+        for (j, xj) in xs.iter().enumerate() {
+            if i != j {
+                numerator *= *xj;
+                denominator *= *xj - *xi;
+            }
+        }
+        numerators.push(numerator);
+        denominators.push(denominator);
+    }
+    let denominators = batch_inverse(&denominators)?;
+    Some(
+        shares
+            .iter()
+            .zip(numerators.into_iter().zip(denominators))
+            .fold(F::ZERO, |acc, ((_, y), (n, d))| acc + *y * n * d),
+    )
+}
+
+/// Create a sharing of zero among `nparties` parties with threshold
+/// `threshold`.
+#[inline]
+pub fn zero_sharing<F: FiniteField + From<u64>, R: CryptoRng + Rng>(
+    threshold: usize,
+    nparties: usize,
+    rng: &mut R,
+) -> Vec<F> {
+    share(F::ZERO, threshold, nparties, rng)
+}
+
+/// Re-randomize `shares`, with threshold `threshold`, by adding a fresh
+/// sharing of zero, leaving the secret and the threshold unchanged.
+#[inline]
+pub fn rerandomize<F: FiniteField + From<u64>, R: CryptoRng + Rng>(
+    shares: &mut [F],
+    threshold: usize,
+    rng: &mut R,
+) {
+    let zeros = zero_sharing(threshold, shares.len(), rng);
+    for (share, zero) in shares.iter_mut().zip(zeros) {
+        *share += zero;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        field::{F61p, Gf128},
+        AesRng,
+    };
+
+    fn test_shamir<F: FiniteField + From<u64>>() {
+        let mut rng = AesRng::new();
+        let secret = F::random(&mut rng);
+        let (threshold, nparties) = (3, 5);
+        let mut shares = share(secret, threshold, nparties, &mut rng);
+        assert_eq!(shares.len(), nparties);
+        let indexed = |shares: &[F], ids: &[usize]| {
+            ids.iter().map(|id| (*id, shares[*id])).collect::<Vec<_>>()
+        };
+        for ids in [[0, 1, 2], [4, 2, 0], [1, 3, 4]].iter() {
+            assert_eq!(reconstruct(&indexed(&shares, ids)), Some(secret));
+        }
+        assert_eq!(reconstruct(&indexed(&shares, &[0, 1, 2, 3, 4])), Some(secret));
+        // Fewer than `threshold` shares give a wrong answer, except with
+        // negligible probability.
+        assert_ne!(reconstruct(&indexed(&shares, &[0, 1])), Some(secret));
+        assert_eq!(reconstruct(&indexed(&shares, &[0, 1, 1])), None);
+
+        let shares_ = shares.clone();
+        rerandomize(&mut shares, threshold, &mut rng);
+        assert_ne!(shares, shares_);
+        assert_eq!(reconstruct(&indexed(&shares, &[1, 2, 4])), Some(secret));
+
+        let zeros = zero_sharing::<F, _>(threshold, nparties, &mut rng);
+        assert_eq!(reconstruct(&indexed(&zeros, &[0, 2, 3])), Some(F::ZERO));
+    }
+
+    #[test]
+    fn test_shamir_f61p() {
+        test_shamir::<F61p>();
+    }
+
+    #[test]
+    fn test_shamir_gf128() {
+        test_shamir::<Gf128>();
+    }
+}
@@ -0,0 +1,180 @@
+// -*- mode: rust; -*-
+//
+// This file is part of `scuttlebutt`.
+// Copyright © 2019 Galois, Inc.
+// See LICENSE for licensing information.
+
+//! Fiat–Shamir transcripts.
+
+use crate::{field::FiniteField, AesRng, Block, Block512};
+#[cfg(feature = "curve25519-dalek")]
+use curve25519_dalek::ristretto::RistrettoPoint;
+use rand::SeedableRng;
+use sha2::{Digest, Sha256};
+
+// The tags distinguishing the kinds of entries in a transcript.
+const TAG_DOMAIN: u8 = 0;
+const TAG_MESSAGE: u8 = 1;
+const TAG_CHALLENGE: u8 = 2;
+
+/// A transcript of a protocol run, from which challenges are derived using the
+/// Fiat–Shamir transform, with SHA-256 as the random oracle.
+///
+/// A transcript is created with a domain separator, and messages are appended
+/// along with a label. Every entry is encoded with its kind, label and length,
+/// so distinct sequences of entries never hash to the same state. Each
+/// challenge depends on everything appended before it, including earlier
+/// challenges, and is itself appended to the transcript.
+///
+/// # Usage
+/// ```rust
+/// use scuttlebutt::{Block, Transcript};
+///
+/// let mut prover = Transcript::new("example protocol");
+/// let mut verifier = prover.clone();
+/// prover.append_block("commitment", &Block::from(42u128));
+/// verifier.append_block("commitment", &Block::from(42u128));
+/// assert_eq!(
+///     prover.challenge_block("challenge"),
+///     verifier.challenge_block("challenge")
+/// );
+/// ```
+#[derive(Clone)]
+pub struct Transcript {
+    hash: Sha256,
+}
+
+impl Transcript {
+    /// Make a new transcript with domain separator `domain`, which should be
+    /// unique to the protocol.
+    pub fn new(domain: &str) -> Self {
+        let mut transcript = Self {
+            hash: Sha256::new(),
+        };
+        transcript.absorb(TAG_DOMAIN, domain, &[]);
+        transcript
+    }
+
+    fn absorb(&mut self, tag: u8, label: &str, bytes: &[u8]) {
+        self.hash.input([tag]);
+        self.hash.input((label.len() as u64).to_le_bytes());
+        self.hash.input(label.as_bytes());
+        self.hash.input((bytes.len() as u64).to_le_bytes());
+        self.hash.input(bytes);
+    }
+
+    /// Append `bytes` with label `label`.
+    pub fn append_bytes(&mut self, label: &str, bytes: &[u8]) {
+        self.absorb(TAG_MESSAGE, label, bytes);
+    }
+
+    /// Append a `u64` with label `label`.
+    pub fn append_u64(&mut self, label: &str, x: u64) {
+        self.append_bytes(label, &x.to_le_bytes());
+    }
+
+    /// Append a `Block` with label `label`.
+    pub fn append_block(&mut self, label: &str, block: &Block) {
+        self.append_bytes(label, block.as_ref());
+    }
+
+    /// Append a `Block512` with label `label`.
+    pub fn append_block512(&mut self, label: &str, block: &Block512) {
+        self.append_bytes(label, block.as_ref());
+    }
+
+    /// Append a field element with label `label`.
+    pub fn append_field<F: FiniteField>(&mut self, label: &str, x: &F) {
+        self.append_bytes(label, &x.to_bytes());
+    }
+
+    /// Append a `RistrettoPoint`, in compressed form, with label `label`.
+    #[cfg(feature = "curve25519-dalek")]
+    pub fn append_point(&mut self, label: &str, point: &RistrettoPoint) {
+        self.append_bytes(label, point.compress().as_bytes());
+    }
+
+    /// Fill `bytes` with a challenge with label `label`.
+    ///
+    /// The output is `SHA-256(state || i)` for block counters `i = 0, 1, …`,
+    /// where `state` is the transcript so far followed by the label and the
+    /// number of bytes requested.
+    pub fn challenge_bytes(&mut self, label: &str, bytes: &mut [u8]) {
+        self.absorb(TAG_CHALLENGE, label, &(bytes.len() as u64).to_le_bytes());
+        for (i, chunk) in bytes.chunks_mut(32).enumerate() {
+            let mut hash = self.hash.clone();
+            hash.input((i as u64).to_le_bytes());
+            chunk.copy_from_slice(&hash.result()[..chunk.len()]);
+        }
+        self.hash.input(&*bytes);
+    }
+
+    /// Output a `Block` challenge with label `label`.
+    pub fn challenge_block(&mut self, label: &str) -> Block {
+        let mut bytes = [0u8; 16];
+        self.challenge_bytes(label, &mut bytes);
+        Block::from(bytes)
+    }
+
+    /// Output a uniformly random field element challenge with label `label`.
+    pub fn challenge_field<F: FiniteField>(&mut self, label: &str) -> F {
+        F::random(&mut self.challenge_rng(label))
+    }
+
+    /// Output an `AesRng`, seeded by a challenge with label `label`, for when
+    /// many challenges are needed at once.
+    pub fn challenge_rng(&mut self, label: &str) -> AesRng {
+        AesRng::from_seed(self.challenge_block(label))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::F61p;
+    use rand::Rng;
+
+    #[test]
+    fn test_transcript() {
+        let mut t1 = Transcript::new("test");
+        let mut t2 = Transcript::new("test");
+        let x = rand::random::<Block>();
+        t1.append_block("x", &x);
+        t2.append_block("x", &x);
+        let c = t1.challenge_block("c");
+        assert_eq!(c, t2.challenge_block("c"));
+        // Earlier challenges are bound into later ones.
+        assert_ne!(c, t1.challenge_block("c"));
+        t2.challenge_block("c");
+        assert_eq!(
+            t1.challenge_field::<F61p>("f"),
+            t2.challenge_field::<F61p>("f")
+        );
+        assert_eq!(
+            t1.challenge_rng("r").gen::<u128>(),
+            t2.challenge_rng("r").gen::<u128>()
+        );
+        let mut bytes1 = [0u8; 100];
+        let mut bytes2 = [0u8; 100];
+        t1.challenge_bytes("b", &mut bytes1);
+        t2.challenge_bytes("b", &mut bytes2);
+        assert_eq!(bytes1.to_vec(), bytes2.to_vec());
+    }
+
+    #[test]
+    fn test_domain_separation() {
+        let challenge = |domain: &str, entries: &[(&str, &[u8])]| {
+            let mut t = Transcript::new(domain);
+            for (label, bytes) in entries.iter() {
+                t.append_bytes(label, bytes);
+            }
+            t.challenge_block("c")
+        };
+        let c = challenge("test", &[("x", b"ab")]);
+        assert_eq!(c, challenge("test", &[("x", b"ab")]));
+        assert_ne!(c, challenge("test2", &[("x", b"ab")]));
+        assert_ne!(c, challenge("test", &[("y", b"ab")]));
+        assert_ne!(c, challenge("test", &[("x", b"a"), ("x", b"b")]));
+        assert_ne!(c, challenge("test", &[("xa", b"b")]));
+    }
+}